@@ -0,0 +1,117 @@
+use crate::mat44::Mat44;
+use crate::vec3::Vec3;
+
+/// How a `Camera` maps view space onto clip space.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Projection {
+    /// Symmetric perspective with a vertical field of view in radians.
+    Perspective { fov: f32 },
+    /// Parallel projection showing `height` world units vertically; the width
+    /// follows the aspect ratio.
+    Orthographic { height: f32 },
+    /// Off-axis perspective, with the extents given on the near plane.
+    Frustum {
+        left: f32,
+        right: f32,
+        bottom: f32,
+        top: f32,
+    },
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Camera {
+    pub position: Vec3,
+    pub target: Vec3,
+    pub up: Vec3,
+    pub projection: Projection,
+    pub near: f32,
+    pub far: f32,
+}
+
+impl Camera {
+    pub fn perspective(position: Vec3, target: Vec3, fov: f32, near: f32, far: f32) -> Self {
+        Self {
+            position,
+            target,
+            up: Vec3::new(0.0, 1.0, 0.0),
+            projection: Projection::Perspective { fov },
+            near,
+            far,
+        }
+    }
+
+    pub fn orthographic(position: Vec3, target: Vec3, height: f32, near: f32, far: f32) -> Self {
+        Self {
+            position,
+            target,
+            up: Vec3::new(0.0, 1.0, 0.0),
+            projection: Projection::Orthographic { height },
+            near,
+            far,
+        }
+    }
+
+    pub fn view(&self) -> Mat44 {
+        Mat44::look_at(&self.position, &self.target, &self.up)
+    }
+
+    pub fn projection(&self, aspect: f32) -> Mat44 {
+        let (n, f) = (self.near, self.far);
+        match self.projection {
+            Projection::Perspective { fov } => Mat44::persp(fov, aspect, n, f),
+            Projection::Orthographic { height } => {
+                let (hw, hh) = (height * aspect * 0.5, height * 0.5);
+                Mat44::ortho(-hw, hw, -hh, hh, n, f)
+            }
+            Projection::Frustum {
+                left,
+                right,
+                bottom,
+                top,
+            } => Mat44::frustum(left, right, bottom, top, n, f),
+        }
+    }
+
+    pub fn view_projection(&self, aspect: f32) -> Mat44 {
+        self.projection(aspect) * self.view()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::camera::Camera;
+    use crate::vec3::Vec3;
+
+    #[test]
+    fn test_target_projects_to_center() {
+        let target = Vec3::new(3.0, -1.0, 2.0);
+        let camera = Camera::perspective(
+            Vec3::new(10.0, 5.0, 10.0),
+            target,
+            (60.0_f32).to_radians(),
+            0.1,
+            100.0,
+        );
+
+        let p = camera.view_projection(1.5).transform_point(&target);
+        assert!(p.x.abs() < 1e-5 && p.y.abs() < 1e-5);
+        assert!(p.z > 0.0 && p.z < 1.0);
+    }
+
+    #[test]
+    fn test_orthographic_extents() {
+        let camera = Camera::orthographic(
+            Vec3::new(0.0, 0.0, 10.0),
+            Vec3::new(0.0, 0.0, 0.0),
+            4.0,
+            1.0,
+            21.0,
+        );
+
+        let m = camera.view_projection(2.0);
+        let near = m.transform_point(&Vec3::new(4.0, 2.0, 9.0));
+        let far = m.transform_point(&Vec3::new(-4.0, -2.0, -11.0));
+        assert!((near - Vec3::new(1.0, 1.0, 0.0)).len() < 1e-6);
+        assert!((far - Vec3::new(-1.0, -1.0, 1.0)).len() < 1e-6);
+    }
+}
@@ -0,0 +1,151 @@
+use crate::image::RasterVertex;
+use crate::varying::Varying;
+use crate::vec4::Vec4;
+
+/// A frustum plane in homogeneous clip space.
+///
+/// The near and far planes follow `Mat44::persp`, which maps the view volume
+/// to `0 <= z <= w`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Plane {
+    Left,
+    Right,
+    Bottom,
+    Top,
+    Near,
+    Far,
+}
+
+impl Plane {
+    pub const ALL: [Plane; 6] = [
+        Plane::Left,
+        Plane::Right,
+        Plane::Bottom,
+        Plane::Top,
+        Plane::Near,
+        Plane::Far,
+    ];
+
+    /// Signed distance of `p` to the plane, non-negative on the inside.
+    pub fn distance(&self, p: &Vec4) -> f32 {
+        match self {
+            Plane::Left => p.w + p.x,
+            Plane::Right => p.w - p.x,
+            Plane::Bottom => p.w + p.y,
+            Plane::Top => p.w - p.y,
+            Plane::Near => p.z,
+            Plane::Far => p.w - p.z,
+        }
+    }
+}
+
+fn intersect<V: Varying>(
+    a: &RasterVertex<V>,
+    b: &RasterVertex<V>,
+    da: f32,
+    db: f32,
+) -> RasterVertex<V> {
+    let t = da / (da - db);
+    RasterVertex::new(a.pos + (b.pos - a.pos) * t, a.varying.lerp(&b.varying, t))
+}
+
+/// Clips a convex polygon against a single plane (one Sutherland–Hodgman pass).
+pub fn clip_against<V: Varying>(polygon: &[RasterVertex<V>], plane: Plane) -> Vec<RasterVertex<V>> {
+    let mut result = Vec::with_capacity(polygon.len() + 1);
+
+    for (i, cur) in polygon.iter().enumerate() {
+        let prev = &polygon[(i + polygon.len() - 1) % polygon.len()];
+        let d_cur = plane.distance(&cur.pos);
+        let d_prev = plane.distance(&prev.pos);
+
+        if d_cur >= 0.0 {
+            if d_prev < 0.0 {
+                result.push(intersect(prev, cur, d_prev, d_cur));
+            }
+            result.push(*cur);
+        } else if d_prev >= 0.0 {
+            result.push(intersect(prev, cur, d_prev, d_cur));
+        }
+    }
+
+    result
+}
+
+/// Clips a convex polygon against all six frustum planes. The result is empty
+/// when the polygon lies completely outside the view volume.
+pub fn clip_polygon<V: Varying>(polygon: &[RasterVertex<V>]) -> Vec<RasterVertex<V>> {
+    let mut result = polygon.to_vec();
+
+    for plane in Plane::ALL {
+        if result.iter().all(|v| plane.distance(&v.pos) >= 0.0) {
+            continue;
+        }
+
+        result = clip_against(&result, plane);
+        if result.len() < 3 {
+            return Vec::new();
+        }
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::clip::{clip_against, clip_polygon, Plane};
+    use crate::image::RasterVertex;
+    use crate::vec4::Vec4;
+
+    #[test]
+    fn test_inside_is_untouched() {
+        let tri = [
+            RasterVertex::new(Vec4::new(-0.5, -0.5, 0.5, 1.0), 0.0_f32),
+            RasterVertex::new(Vec4::new(0.5, -0.5, 0.5, 1.0), 1.0_f32),
+            RasterVertex::new(Vec4::new(0.0, 0.5, 0.5, 1.0), 2.0_f32),
+        ];
+
+        let clipped = clip_polygon(&tri);
+        assert_eq!(clipped.len(), 3);
+        assert_eq!(clipped[1].pos, tri[1].pos);
+    }
+
+    #[test]
+    fn test_outside_is_rejected() {
+        let tri = [
+            RasterVertex::new(Vec4::new(2.0, 0.0, 0.5, 1.0), ()),
+            RasterVertex::new(Vec4::new(3.0, 0.0, 0.5, 1.0), ()),
+            RasterVertex::new(Vec4::new(2.5, 0.5, 0.5, 1.0), ()),
+        ];
+
+        assert!(clip_polygon(&tri).is_empty());
+    }
+
+    #[test]
+    fn test_clip_right_interpolates_varyings() {
+        let tri = [
+            RasterVertex::new(Vec4::new(0.0, -0.5, 0.5, 1.0), 0.0_f32),
+            RasterVertex::new(Vec4::new(2.0, -0.5, 0.5, 1.0), 1.0_f32),
+            RasterVertex::new(Vec4::new(0.0, 0.5, 0.5, 1.0), 0.0_f32),
+        ];
+
+        let clipped = clip_against(&tri, Plane::Right);
+        assert_eq!(clipped.len(), 4);
+        assert!(clipped.iter().all(|v| v.pos.x <= 1.0));
+
+        let edge = clipped.iter().find(|v| v.pos.y == -0.5 && v.pos.x == 1.0);
+        assert_eq!(edge.unwrap().varying, 0.5);
+    }
+
+    #[test]
+    fn test_clip_near() {
+        let tri = [
+            RasterVertex::new(Vec4::new(0.0, 0.0, -1.0, 1.0), ()),
+            RasterVertex::new(Vec4::new(0.5, 0.0, 1.0, 2.0), ()),
+            RasterVertex::new(Vec4::new(0.0, 0.5, 1.0, 2.0), ()),
+        ];
+
+        let clipped = clip_polygon(&tri);
+        assert_eq!(clipped.len(), 4);
+        assert!(clipped.iter().all(|v| v.pos.z >= 0.0));
+    }
+}
@@ -0,0 +1,244 @@
+use crate::codec::{pixel_count, Bitmap, DecodeError, Reader};
+use crate::image::Image;
+use std::io::{self, Write};
+
+const HEADER_SIZE: u32 = 14 + 40;
+
+/// Writes a 24-bit BMP. Rows are stored bottom-up and padded to four bytes.
+pub fn encode<W: Write>(image: &Image, w: &mut W) -> io::Result<()> {
+    let (width, height) = (image.w() as u32, image.h() as u32);
+    let stride = (width * 3 + 3) & !3;
+    let size = stride * height;
+
+    // BITMAPFILEHEADER
+    w.write_all(b"BM")?;
+    w.write_all(&(HEADER_SIZE + size).to_le_bytes())?;
+    w.write_all(&[0; 4])?;
+    w.write_all(&HEADER_SIZE.to_le_bytes())?;
+
+    // BITMAPINFOHEADER
+    w.write_all(&40u32.to_le_bytes())?;
+    w.write_all(&(width as i32).to_le_bytes())?;
+    w.write_all(&(height as i32).to_le_bytes())?;
+    w.write_all(&1u16.to_le_bytes())?;
+    w.write_all(&24u16.to_le_bytes())?;
+    w.write_all(&0u32.to_le_bytes())?; // BI_RGB
+    w.write_all(&size.to_le_bytes())?;
+    w.write_all(&2835i32.to_le_bytes())?; // 72 DPI
+    w.write_all(&2835i32.to_le_bytes())?;
+    w.write_all(&[0; 8])?;
+
+    for y in (0..image.h()).rev() {
+        let mut row: Vec<u8> = image.row(y).iter().flat_map(|c| [c.b, c.g, c.r]).collect();
+        row.resize(stride as usize, 0);
+        w.write_all(&row)?;
+    }
+
+    Ok(())
+}
+
+/// Extracts the channel selected by `mask` and scales it to 8 bits.
+fn channel(value: u32, mask: u32) -> u8 {
+    if mask == 0 {
+        return 0;
+    }
+
+    let shift = mask.trailing_zeros();
+    let max = u64::from(mask >> shift);
+    let v = u64::from((value & mask) >> shift);
+    ((v * 255 + max / 2) / max) as u8
+}
+
+/// Reads uncompressed BMPs with 1, 4, 8, 16, 24 or 32 bits per pixel,
+/// including `BI_BITFIELDS` channel masks.
+pub fn decode(data: &[u8]) -> Result<Bitmap, DecodeError> {
+    let mut r = Reader::new(data);
+    if r.bytes(2)? != b"BM" {
+        return Err(DecodeError::Invalid("missing BMP signature"));
+    }
+    r.bytes(8)?;
+    let offset = r.u32_le()? as usize;
+
+    let header_size = r.u32_le()? as usize;
+    let (width, height, bits, compression, colors_used) = if header_size == 12 {
+        // BITMAPCOREHEADER
+        let w = i32::from(r.u16_le()?);
+        let h = i32::from(r.u16_le()?);
+        r.u16_le()?;
+        (w, h, r.u16_le()?, 0, 0)
+    } else if header_size >= 40 {
+        let w = r.i32_le()?;
+        let h = r.i32_le()?;
+        r.u16_le()?;
+        let bits = r.u16_le()?;
+        let compression = r.u32_le()?;
+        r.bytes(12)?;
+        let colors_used = r.u32_le()? as usize;
+        r.u32_le()?;
+        (w, h, bits, compression, colors_used)
+    } else {
+        return Err(DecodeError::Invalid("bad BMP header size"));
+    };
+
+    if width <= 0 || height == 0 {
+        return Err(DecodeError::Invalid("bad BMP dimensions"));
+    }
+
+    let mut masks = match bits {
+        16 => [0x7C00, 0x03E0, 0x001F, 0],
+        _ => [0x00FF_0000, 0x0000_FF00, 0x0000_00FF, 0],
+    };
+    match compression {
+        0 => {}
+        // BI_BITFIELDS, BI_ALPHABITFIELDS
+        3 | 6 if header_size >= 40 => {
+            let count = if compression == 6 || header_size >= 56 {
+                4
+            } else {
+                3
+            };
+            for mask in masks.iter_mut().take(count) {
+                *mask = r.u32_le()?;
+            }
+        }
+        _ => return Err(DecodeError::Unsupported("compressed BMP")),
+    }
+    r.pos = 14 + header_size;
+    if compression == 3 && header_size == 40 {
+        r.pos += 12;
+    } else if compression == 6 && header_size == 40 {
+        r.pos += 16;
+    }
+
+    let mut palette = Vec::new();
+    if bits <= 8 {
+        let count = if colors_used > 0 {
+            colors_used
+        } else {
+            1 << bits
+        };
+        let entry = if header_size == 12 { 3 } else { 4 };
+        for _ in 0..count {
+            let bgr = r.bytes(entry)?;
+            palette.push([bgr[2], bgr[1], bgr[0], 255]);
+        }
+    }
+
+    let (width, top_down) = (width as usize, height < 0);
+    let height = height.unsigned_abs() as usize;
+    let stride = (usize::from(bits) * width).div_ceil(32) * 4;
+    pixel_count(width, height)?;
+    let end = stride
+        .checked_mul(height)
+        .and_then(|size| size.checked_add(offset));
+    if end.is_none_or(|end| end > data.len()) {
+        return Err(DecodeError::UnexpectedEof);
+    }
+
+    let mut bitmap = Bitmap::new(width, height);
+    for row in 0..height {
+        let start = offset + row * stride;
+        let line = &data[start..start + stride];
+        let y = if top_down { row } else { height - 1 - row };
+
+        for x in 0..width {
+            let pixel = match bits {
+                1 | 4 | 8 => {
+                    let bit = x * usize::from(bits);
+                    let byte = line[bit / 8];
+                    let shift = 8 - usize::from(bits) - bit % 8;
+                    let index = usize::from(byte >> shift) & ((1 << bits) - 1);
+                    *palette
+                        .get(index)
+                        .ok_or(DecodeError::Invalid("BMP color index out of range"))?
+                }
+                16 | 24 | 32 => {
+                    let size = usize::from(bits) / 8;
+                    let v = line[x * size..x * size + size]
+                        .iter()
+                        .rev()
+                        .fold(0u32, |acc, &b| acc << 8 | u32::from(b));
+                    let a = if masks[3] == 0 {
+                        255
+                    } else {
+                        channel(v, masks[3])
+                    };
+                    [
+                        channel(v, masks[0]),
+                        channel(v, masks[1]),
+                        channel(v, masks[2]),
+                        a,
+                    ]
+                }
+                _ => return Err(DecodeError::Unsupported("BMP pixel depth")),
+            };
+            bitmap.set(x, y, pixel);
+        }
+    }
+
+    Ok(bitmap)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::codec::bmp;
+    use crate::color::Color;
+    use crate::image::Image;
+
+    #[test]
+    fn test_encode() {
+        let mut image = Image::new(3, 2);
+        image.draw_point(0, 0, &Color::new(1, 2, 3));
+
+        let mut out = Vec::new();
+        bmp::encode(&image, &mut out).unwrap();
+
+        // Two rows of 9 bytes padded to 12
+        assert_eq!(out.len(), 54 + 24);
+        assert_eq!(&out[0..2], b"BM");
+        assert_eq!(u32::from_le_bytes(out[2..6].try_into().unwrap()), 78);
+        // The top row comes last
+        assert_eq!(&out[54 + 12..54 + 15], &[3, 2, 1]);
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let mut image = Image::new(3, 2);
+        image.draw_point(2, 0, &Color::new(1, 2, 3));
+
+        let mut out = Vec::new();
+        bmp::encode(&image, &mut out).unwrap();
+        let bitmap = bmp::decode(&out).unwrap();
+        assert_eq!((bitmap.width, bitmap.height), (3, 2));
+        assert_eq!(bitmap.get(2, 0), [1, 2, 3, 255]);
+        assert_eq!(bitmap.get(2, 1), [0, 0, 0, 255]);
+    }
+
+    #[test]
+    fn test_decode_paletted_top_down() {
+        let mut data = b"BM".to_vec();
+        data.extend(0u32.to_le_bytes());
+        data.extend([0; 4]);
+        data.extend((14u32 + 40 + 8).to_le_bytes());
+        data.extend(40u32.to_le_bytes());
+        data.extend(3i32.to_le_bytes());
+        data.extend((-2i32).to_le_bytes());
+        data.extend(1u16.to_le_bytes());
+        data.extend(1u16.to_le_bytes());
+        data.extend([0; 16]);
+        data.extend(2u32.to_le_bytes());
+        data.extend([0; 4]);
+        data.extend([0, 0, 0, 0, 255, 255, 255, 0]); // Black, white
+        data.extend([0b1010_0000, 0, 0, 0, 0b0100_0000, 0, 0, 0]);
+
+        let bitmap = bmp::decode(&data).unwrap();
+        assert_eq!(bitmap.get(0, 0), [255, 255, 255, 255]);
+        assert_eq!(bitmap.get(1, 0), [0, 0, 0, 255]);
+        assert_eq!(bitmap.get(1, 1), [255, 255, 255, 255]);
+        assert!(bmp::decode(&data[..data.len() - 2]).is_err());
+
+        // A huge width fails before allocating anything for it
+        data[18..22].copy_from_slice(&i32::MAX.to_le_bytes());
+        assert!(bmp::decode(&data).is_err());
+    }
+}
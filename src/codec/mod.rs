@@ -0,0 +1,209 @@
+//! Image file encoders and decoders, selected by `ImageFormat`.
+
+pub mod bmp;
+pub mod png;
+pub mod ppm;
+pub mod tga;
+pub mod zlib;
+
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ImageFormat {
+    /// Binary (P6) portable pixmap.
+    Ppm,
+    /// Uncompressed true-color Targa.
+    Tga,
+    /// 24-bit Windows bitmap.
+    Bmp,
+    Png,
+}
+
+impl ImageFormat {
+    /// Picks the format from the file extension, ignoring case.
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Option<Self> {
+        let ext = path.as_ref().extension()?.to_str()?.to_ascii_lowercase();
+        match ext.as_str() {
+            "ppm" | "pnm" => Some(ImageFormat::Ppm),
+            "tga" => Some(ImageFormat::Tga),
+            "bmp" => Some(ImageFormat::Bmp),
+            "png" => Some(ImageFormat::Png),
+            _ => None,
+        }
+    }
+
+    /// Recognizes PPM, BMP and PNG signatures.
+    pub fn from_magic(data: &[u8]) -> Option<Self> {
+        if data.starts_with(&png::SIGNATURE) {
+            Some(ImageFormat::Png)
+        } else if data.starts_with(b"BM") {
+            Some(ImageFormat::Bmp)
+        } else if data.starts_with(b"P3") || data.starts_with(b"P6") {
+            Some(ImageFormat::Ppm)
+        } else {
+            None
+        }
+    }
+}
+
+/// Why an image file could not be decoded.
+#[derive(Debug)]
+pub enum DecodeError {
+    Io(io::Error),
+    /// Neither the file contents nor its extension identify a known format.
+    UnknownFormat,
+    /// The data ends before the headers say it should.
+    UnexpectedEof,
+    /// A valid file using a feature the decoder does not implement.
+    Unsupported(&'static str),
+    /// Malformed data.
+    Invalid(&'static str),
+    /// A CRC or Adler-32 mismatch.
+    Checksum,
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DecodeError::Io(err) => write!(f, "{}", err),
+            DecodeError::UnknownFormat => write!(f, "unknown image format"),
+            DecodeError::UnexpectedEof => write!(f, "unexpected end of image data"),
+            DecodeError::Unsupported(what) => write!(f, "unsupported: {}", what),
+            DecodeError::Invalid(what) => write!(f, "invalid image: {}", what),
+            DecodeError::Checksum => write!(f, "checksum mismatch"),
+        }
+    }
+}
+
+impl Error for DecodeError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            DecodeError::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for DecodeError {
+    fn from(err: io::Error) -> Self {
+        DecodeError::Io(err)
+    }
+}
+
+/// Decoded 8-bit RGBA pixels, stored row by row from the top-left corner.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Bitmap {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<[u8; 4]>,
+}
+
+impl Bitmap {
+    pub fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            pixels: vec![[0, 0, 0, 255]; width * height],
+        }
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> [u8; 4] {
+        self.pixels[y * self.width + x]
+    }
+
+    pub fn set(&mut self, x: usize, y: usize, rgba: [u8; 4]) {
+        self.pixels[y * self.width + x] = rgba;
+    }
+}
+
+/// Number of pixels in an image of the given header size. Empty images and
+/// sizes that overflow are rejected, so decoders can check the input holds
+/// that many pixels before allocating for them.
+pub(crate) fn pixel_count(width: usize, height: usize) -> Result<usize, DecodeError> {
+    if width == 0 || height == 0 {
+        return Err(DecodeError::Invalid("empty image"));
+    }
+    width
+        .checked_mul(height)
+        .filter(|count| count.checked_mul(4).is_some())
+        .ok_or(DecodeError::Invalid("image dimensions too large"))
+}
+
+/// Decodes an image file, identifying the format by its signature and
+/// falling back to the extension (TGA has no signature).
+pub fn load<P: AsRef<Path>>(path: P) -> Result<Bitmap, DecodeError> {
+    let data = fs::read(&path)?;
+    let format = ImageFormat::from_magic(&data)
+        .or_else(|| ImageFormat::from_path(&path))
+        .ok_or(DecodeError::UnknownFormat)?;
+
+    decode(&data, format)
+}
+
+pub fn decode(data: &[u8], format: ImageFormat) -> Result<Bitmap, DecodeError> {
+    match format {
+        ImageFormat::Ppm => ppm::decode(data),
+        ImageFormat::Tga => tga::decode(data),
+        ImageFormat::Bmp => bmp::decode(data),
+        ImageFormat::Png => png::decode(data),
+    }
+}
+
+/// Cursor over little- or big-endian binary data that reports running out
+/// of bytes as `DecodeError::UnexpectedEof`.
+pub(crate) struct Reader<'a> {
+    pub data: &'a [u8],
+    pub pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    pub fn new(data: &'a [u8]) -> Self {
+        Self { data, pos: 0 }
+    }
+
+    pub fn bytes(&mut self, n: usize) -> Result<&'a [u8], DecodeError> {
+        let bytes = self
+            .data
+            .get(self.pos..self.pos + n)
+            .ok_or(DecodeError::UnexpectedEof)?;
+        self.pos += n;
+        Ok(bytes)
+    }
+
+    pub fn u8(&mut self) -> Result<u8, DecodeError> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    pub fn u16_le(&mut self) -> Result<u16, DecodeError> {
+        Ok(u16::from_le_bytes(self.bytes(2)?.try_into().unwrap()))
+    }
+
+    pub fn u32_le(&mut self) -> Result<u32, DecodeError> {
+        Ok(u32::from_le_bytes(self.bytes(4)?.try_into().unwrap()))
+    }
+
+    pub fn i32_le(&mut self) -> Result<i32, DecodeError> {
+        Ok(i32::from_le_bytes(self.bytes(4)?.try_into().unwrap()))
+    }
+
+    pub fn u32_be(&mut self) -> Result<u32, DecodeError> {
+        Ok(u32::from_be_bytes(self.bytes(4)?.try_into().unwrap()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::codec::ImageFormat;
+
+    #[test]
+    fn test_from_path() {
+        assert_eq!(ImageFormat::from_path("out.PNG"), Some(ImageFormat::Png));
+        assert_eq!(ImageFormat::from_path("a/b.tga"), Some(ImageFormat::Tga));
+        assert_eq!(ImageFormat::from_path("image.jpg"), None);
+        assert_eq!(ImageFormat::from_path("image"), None);
+    }
+}
@@ -0,0 +1,459 @@
+use crate::codec::{pixel_count, zlib, Bitmap, DecodeError, Reader};
+use crate::image::Image;
+use std::io::{self, Write};
+
+pub const SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1A, b'\n'];
+
+fn write_chunk<W: Write>(w: &mut W, kind: &[u8; 4], data: &[u8]) -> io::Result<()> {
+    w.write_all(&(data.len() as u32).to_be_bytes())?;
+    w.write_all(kind)?;
+    w.write_all(data)?;
+
+    let crc = zlib::crc32_update(zlib::crc32(kind), data);
+    w.write_all(&crc.to_be_bytes())
+}
+
+fn paeth(a: u8, b: u8, c: u8) -> u8 {
+    let p = i16::from(a) + i16::from(b) - i16::from(c);
+    let (pa, pb, pc) = (
+        (p - i16::from(a)).abs(),
+        (p - i16::from(b)).abs(),
+        (p - i16::from(c)).abs(),
+    );
+    if pa <= pb && pa <= pc {
+        a
+    } else if pb <= pc {
+        b
+    } else {
+        c
+    }
+}
+
+/// Applies PNG filter `kind` to `row`, given the unfiltered previous row and
+/// the pixel size in bytes.
+fn filter(kind: u8, row: &[u8], prev: &[u8], bpp: usize, out: &mut Vec<u8>) {
+    out.push(kind);
+    for i in 0..row.len() {
+        let a = if i >= bpp { row[i - bpp] } else { 0 };
+        let b = prev[i];
+        let c = if i >= bpp { prev[i - bpp] } else { 0 };
+        let predicted = match kind {
+            0 => 0,
+            1 => a,
+            2 => b,
+            3 => ((u16::from(a) + u16::from(b)) / 2) as u8,
+            _ => paeth(a, b, c),
+        };
+        out.push(row[i].wrapping_sub(predicted));
+    }
+}
+
+/// Writes an 8-bit RGB PNG. Each scanline uses whichever filter gives the
+/// smallest sum of absolute differences.
+pub fn encode<W: Write>(image: &Image, w: &mut W) -> io::Result<()> {
+    let (width, height) = (image.w() as u32, image.h() as u32);
+
+    let mut ihdr = Vec::with_capacity(13);
+    ihdr.extend(width.to_be_bytes());
+    ihdr.extend(height.to_be_bytes());
+    ihdr.extend([8, 2, 0, 0, 0]); // 8-bit RGB, deflate, adaptive, no interlace
+
+    let stride = width as usize * 3;
+    let mut raw = Vec::with_capacity((stride + 1) * height as usize);
+    let mut prev = vec![0u8; stride];
+    let mut candidate = Vec::with_capacity(stride + 1);
+
+    for y in 0..image.h() {
+        let row: Vec<u8> = image.row(y).iter().flat_map(|c| [c.r, c.g, c.b]).collect();
+
+        let mut best: Option<(u64, Vec<u8>)> = None;
+        for kind in 0..5 {
+            candidate.clear();
+            filter(kind, &row, &prev, 3, &mut candidate);
+            let cost = candidate[1..]
+                .iter()
+                .map(|&b| u64::from((b as i8).unsigned_abs()))
+                .sum();
+            if best.as_ref().is_none_or(|(c, _)| cost < *c) {
+                best = Some((cost, candidate.clone()));
+            }
+        }
+
+        raw.extend(best.unwrap().1);
+        prev = row;
+    }
+
+    w.write_all(&SIGNATURE)?;
+    write_chunk(w, b"IHDR", &ihdr)?;
+    write_chunk(w, b"IDAT", &zlib::compress(&raw))?;
+    write_chunk(w, b"IEND", &[])
+}
+
+/// Adam7 passes as `(x0, y0, dx, dy)`.
+const ADAM7: [(usize, usize, usize, usize); 7] = [
+    (0, 0, 8, 8),
+    (4, 0, 8, 8),
+    (0, 4, 4, 8),
+    (2, 0, 4, 4),
+    (0, 2, 2, 4),
+    (1, 0, 2, 2),
+    (0, 1, 1, 2),
+];
+
+struct Header {
+    width: usize,
+    height: usize,
+    depth: u8,
+    color_type: u8,
+    interlaced: bool,
+}
+
+impl Header {
+    fn channels(&self) -> usize {
+        match self.color_type {
+            0 | 3 => 1,
+            4 => 2,
+            2 => 3,
+            _ => 4,
+        }
+    }
+
+    fn row_bytes(&self, width: usize) -> usize {
+        (width * self.channels() * usize::from(self.depth)).div_ceil(8)
+    }
+}
+
+/// Reverses the per-scanline filters in place. `data` holds `height` rows,
+/// each prefixed with its filter byte.
+fn unfilter(data: &mut [u8], stride: usize, height: usize, bpp: usize) -> Result<(), DecodeError> {
+    let mut prev = vec![0u8; stride];
+    for y in 0..height {
+        let start = y * (stride + 1);
+        let kind = data[start];
+        let row = &mut data[start + 1..start + 1 + stride];
+
+        for i in 0..stride {
+            let a = if i >= bpp { row[i - bpp] } else { 0 };
+            let b = prev[i];
+            let c = if i >= bpp { prev[i - bpp] } else { 0 };
+            let predicted = match kind {
+                0 => 0,
+                1 => a,
+                2 => b,
+                3 => ((u16::from(a) + u16::from(b)) / 2) as u8,
+                4 => paeth(a, b, c),
+                _ => return Err(DecodeError::Invalid("bad PNG filter type")),
+            };
+            row[i] = row[i].wrapping_add(predicted);
+        }
+
+        prev.copy_from_slice(row);
+    }
+
+    Ok(())
+}
+
+/// Reads sample `index` of a scanline with `depth` bits per sample.
+fn sample(row: &[u8], index: usize, depth: u8) -> u16 {
+    match depth {
+        16 => u16::from_be_bytes([row[index * 2], row[index * 2 + 1]]),
+        8 => u16::from(row[index]),
+        _ => {
+            let bit = index * usize::from(depth);
+            let shift = 8 - usize::from(depth) - bit % 8;
+            u16::from(row[bit / 8] >> shift) & ((1 << depth) - 1)
+        }
+    }
+}
+
+/// Decodes 8-bit RGBA from any PNG color type and bit depth, including
+/// palettes, `tRNS` transparency and Adam7 interlacing.
+pub fn decode(data: &[u8]) -> Result<Bitmap, DecodeError> {
+    let mut r = Reader::new(data);
+    if r.bytes(8)? != SIGNATURE {
+        return Err(DecodeError::Invalid("missing PNG signature"));
+    }
+
+    let mut header = None;
+    let mut palette: Vec<[u8; 4]> = Vec::new();
+    let mut transparent: Option<[u16; 3]> = None;
+    let mut idat = Vec::new();
+
+    loop {
+        let len = r.u32_be()? as usize;
+        let kind = r.bytes(4)?;
+        let body = r.bytes(len)?;
+        let crc = r.u32_be()?;
+        if zlib::crc32_update(zlib::crc32(kind), body) != crc {
+            return Err(DecodeError::Checksum);
+        }
+
+        match kind {
+            b"IHDR" => {
+                if len != 13 {
+                    return Err(DecodeError::Invalid("bad IHDR length"));
+                }
+                let mut h = Reader::new(body);
+                let width = h.u32_be()? as usize;
+                let height = h.u32_be()? as usize;
+                let (depth, color_type) = (h.u8()?, h.u8()?);
+                let (compression, filter, interlace) = (h.u8()?, h.u8()?, h.u8()?);
+
+                let valid_depth = match color_type {
+                    0 => matches!(depth, 1 | 2 | 4 | 8 | 16),
+                    3 => matches!(depth, 1 | 2 | 4 | 8),
+                    2 | 4 | 6 => matches!(depth, 8 | 16),
+                    _ => false,
+                };
+                if !valid_depth || width == 0 || height == 0 {
+                    return Err(DecodeError::Invalid("bad PNG header"));
+                }
+                if compression != 0 || filter != 0 || interlace > 1 {
+                    return Err(DecodeError::Unsupported("PNG compression or filter method"));
+                }
+
+                header = Some(Header {
+                    width,
+                    height,
+                    depth,
+                    color_type,
+                    interlaced: interlace == 1,
+                });
+            }
+            b"PLTE" => {
+                palette = body
+                    .chunks_exact(3)
+                    .map(|c| [c[0], c[1], c[2], 255])
+                    .collect();
+            }
+            b"tRNS" => match header.as_ref().map(|h| h.color_type) {
+                Some(3) => {
+                    for (entry, &a) in palette.iter_mut().zip(body) {
+                        entry[3] = a;
+                    }
+                }
+                Some(0) if len >= 2 => {
+                    let g = u16::from_be_bytes([body[0], body[1]]);
+                    transparent = Some([g, g, g]);
+                }
+                Some(2) if len >= 6 => {
+                    let c = |i: usize| u16::from_be_bytes([body[i], body[i + 1]]);
+                    transparent = Some([c(0), c(2), c(4)]);
+                }
+                _ => {}
+            },
+            b"IDAT" => idat.extend_from_slice(body),
+            b"IEND" => break,
+            _ => {
+                // Ancillary chunks have a lowercase first letter
+                if kind[0].is_ascii_uppercase() {
+                    return Err(DecodeError::Unsupported("critical PNG chunk"));
+                }
+            }
+        }
+    }
+
+    let header = header.ok_or(DecodeError::Invalid("missing IHDR"))?;
+    if header.color_type == 3 && palette.is_empty() {
+        return Err(DecodeError::Invalid("missing PLTE"));
+    }
+
+    let mut raw = zlib::decompress(&idat)?;
+    let channels = header.channels();
+    let bpp = (channels * usize::from(header.depth)).div_ceil(8);
+
+    let passes: Vec<_> = if header.interlaced {
+        ADAM7.to_vec()
+    } else {
+        vec![(0, 0, 1, 1)]
+    };
+
+    // Rows of each pass with their filter bytes, checked against the data
+    // before the bitmap is sized from the header
+    pixel_count(header.width, header.height)?;
+    let mut sizes = Vec::with_capacity(passes.len());
+    for &(x0, y0, dx, dy) in &passes {
+        if x0 >= header.width || y0 >= header.height {
+            sizes.push(None);
+            continue;
+        }
+        let w = (header.width - x0).div_ceil(dx);
+        let h = (header.height - y0).div_ceil(dy);
+        let size = header
+            .row_bytes(w)
+            .checked_add(1)
+            .and_then(|row| row.checked_mul(h))
+            .ok_or(DecodeError::UnexpectedEof)?;
+        sizes.push(Some((w, h, size)));
+    }
+    let total = sizes
+        .iter()
+        .flatten()
+        .try_fold(0usize, |acc, &(_, _, size)| acc.checked_add(size));
+    if total.is_none_or(|total| total > raw.len()) {
+        return Err(DecodeError::UnexpectedEof);
+    }
+
+    let mut bitmap = Bitmap::new(header.width, header.height);
+    let mut offset = 0;
+    for ((x0, y0, dx, dy), size) in passes.into_iter().zip(sizes) {
+        let Some((w, h, size)) = size else {
+            continue;
+        };
+        let stride = header.row_bytes(w);
+
+        let pass = &mut raw[offset..offset + size];
+        unfilter(pass, stride, h, bpp)?;
+
+        for y in 0..h {
+            let row = &pass[y * (stride + 1) + 1..(y + 1) * (stride + 1)];
+            for x in 0..w {
+                let s = |c: usize| sample(row, x * channels + c, header.depth);
+                // Scale samples to 8 bits
+                let to8 = |v: u16| match header.depth {
+                    16 => (v >> 8) as u8,
+                    d => (u32::from(v) * 255 / ((1 << d) - 1)) as u8,
+                };
+
+                let pixel = match header.color_type {
+                    0 => {
+                        let g = s(0);
+                        let a = if transparent == Some([g, g, g]) {
+                            0
+                        } else {
+                            255
+                        };
+                        [to8(g), to8(g), to8(g), a]
+                    }
+                    2 => {
+                        let rgb = [s(0), s(1), s(2)];
+                        let a = if transparent == Some(rgb) { 0 } else { 255 };
+                        [to8(rgb[0]), to8(rgb[1]), to8(rgb[2]), a]
+                    }
+                    3 => *palette
+                        .get(usize::from(s(0)))
+                        .ok_or(DecodeError::Invalid("PNG palette index out of range"))?,
+                    4 => {
+                        let g = to8(s(0));
+                        [g, g, g, to8(s(1))]
+                    }
+                    _ => [to8(s(0)), to8(s(1)), to8(s(2)), to8(s(3))],
+                };
+                bitmap.set(x0 + x * dx, y0 + y * dy, pixel);
+            }
+        }
+
+        offset += size;
+    }
+
+    Ok(bitmap)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::codec::png;
+    use crate::codec::zlib::{compress, crc32};
+    use crate::color::Color;
+    use crate::image::Image;
+
+    fn chunk(out: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+        png::write_chunk(out, kind, data).unwrap();
+    }
+
+    #[test]
+    fn test_encode_structure() {
+        let image = Image::new(4, 3);
+
+        let mut out = Vec::new();
+        png::encode(&image, &mut out).unwrap();
+        assert_eq!(&out[0..8], &png::SIGNATURE);
+        assert_eq!(&out[12..16], b"IHDR");
+        assert_eq!(&out[16..24], &[0, 0, 0, 4, 0, 0, 0, 3]);
+
+        let crc = u32::from_be_bytes(out[29..33].try_into().unwrap());
+        assert_eq!(crc, crc32(&out[12..29]));
+        assert_eq!(&out[out.len() - 12..], b"\0\0\0\0IEND\xAE\x42\x60\x82");
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let mut image = Image::new(5, 4);
+        for x in 0..5 {
+            image.draw_point(x, 2, &Color::new(40 * x as u8, 7, 200));
+        }
+
+        let mut out = Vec::new();
+        png::encode(&image, &mut out).unwrap();
+        let bitmap = png::decode(&out).unwrap();
+        for x in 0..5 {
+            assert_eq!(bitmap.get(x, 2), [40 * x as u8, 7, 200, 255]);
+        }
+        assert_eq!(bitmap.get(4, 3), [0, 0, 0, 255]);
+    }
+
+    #[test]
+    fn test_decode_palette_with_alpha() {
+        let mut data = png::SIGNATURE.to_vec();
+        chunk(&mut data, b"IHDR", &[0, 0, 0, 3, 0, 0, 0, 1, 2, 3, 0, 0, 0]);
+        chunk(&mut data, b"PLTE", &[255, 0, 0, 0, 255, 0, 0, 0, 255]);
+        chunk(&mut data, b"tRNS", &[128]);
+        // 2 bits per index: 0, 1, 2
+        chunk(&mut data, b"IDAT", &compress(&[0, 0b0001_1000]));
+        chunk(&mut data, b"IEND", &[]);
+
+        let bitmap = png::decode(&data).unwrap();
+        assert_eq!(
+            bitmap.pixels,
+            vec![[255, 0, 0, 128], [0, 255, 0, 255], [0, 0, 255, 255]]
+        );
+    }
+
+    #[test]
+    fn test_decode_interlaced_gray_alpha() {
+        // 3x3 8-bit gray + alpha; Adam7 passes 2 and 3 are empty at this size
+        let mut data = png::SIGNATURE.to_vec();
+        chunk(&mut data, b"IHDR", &[0, 0, 0, 3, 0, 0, 0, 3, 8, 4, 0, 0, 1]);
+        let raw = [
+            0, 10, 255, // Pass 1: (0, 0)
+            0, 20, 255, // Pass 4: (2, 0)
+            0, 30, 255, 40, 255, // Pass 5: (0, 2), (2, 2)
+            0, 50, 255, // Pass 6: (1, 0)
+            0, 60, 255, // Pass 6: (1, 2)
+            0, 70, 255, 80, 255, 90, 0, // Pass 7: (0, 1), (1, 1), (2, 1)
+        ];
+        chunk(&mut data, b"IDAT", &compress(&raw));
+        chunk(&mut data, b"IEND", &[]);
+
+        let bitmap = png::decode(&data).unwrap();
+        assert_eq!(bitmap.get(0, 0), [10, 10, 10, 255]);
+        assert_eq!(bitmap.get(2, 0), [20, 20, 20, 255]);
+        assert_eq!(bitmap.get(2, 2), [40, 40, 40, 255]);
+        assert_eq!(bitmap.get(1, 0), [50, 50, 50, 255]);
+        assert_eq!(bitmap.get(1, 2), [60, 60, 60, 255]);
+        assert_eq!(bitmap.get(2, 1), [90, 90, 90, 0]);
+    }
+
+    #[test]
+    fn test_corrupt_crc() {
+        let mut out = Vec::new();
+        png::encode(&Image::new(2, 2), &mut out).unwrap();
+        out[20] ^= 0xFF;
+
+        assert!(png::decode(&out).is_err());
+    }
+
+    #[test]
+    fn test_huge_header() {
+        // The header claims far more pixels than the data holds
+        let mut data = png::SIGNATURE.to_vec();
+        chunk(
+            &mut data,
+            b"IHDR",
+            &[255, 255, 255, 255, 255, 255, 255, 255, 8, 6, 0, 0, 0],
+        );
+        chunk(&mut data, b"IDAT", &compress(&[0, 1, 2, 3, 4]));
+        chunk(&mut data, b"IEND", &[]);
+
+        assert!(png::decode(&data).is_err());
+    }
+}
@@ -0,0 +1,170 @@
+use crate::codec::{pixel_count, Bitmap, DecodeError};
+use crate::image::Image;
+use std::io::{self, Write};
+
+/// Writes a binary (P6) PPM.
+pub fn encode<W: Write>(image: &Image, w: &mut W) -> io::Result<()> {
+    write!(w, "P6\n{} {}\n{}\n", image.w(), image.h(), u8::MAX)?;
+
+    for y in 0..image.h() {
+        let row: Vec<u8> = image.row(y).iter().flat_map(|c| [c.r, c.g, c.b]).collect();
+        w.write_all(&row)?;
+    }
+
+    Ok(())
+}
+
+/// Splits the header into whitespace-separated tokens, skipping `#` comments.
+struct Tokens<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Tokens<'a> {
+    fn next(&mut self) -> Result<&'a [u8], DecodeError> {
+        loop {
+            match self.data.get(self.pos) {
+                Some(b'#') => {
+                    while self.data.get(self.pos).is_some_and(|&c| c != b'\n') {
+                        self.pos += 1;
+                    }
+                }
+                Some(c) if c.is_ascii_whitespace() => self.pos += 1,
+                Some(_) => break,
+                None => return Err(DecodeError::UnexpectedEof),
+            }
+        }
+
+        let start = self.pos;
+        while self
+            .data
+            .get(self.pos)
+            .is_some_and(|c| !c.is_ascii_whitespace())
+        {
+            self.pos += 1;
+        }
+        Ok(&self.data[start..self.pos])
+    }
+
+    fn number(&mut self) -> Result<u32, DecodeError> {
+        std::str::from_utf8(self.next()?)
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .ok_or(DecodeError::Invalid("bad number in PPM"))
+    }
+}
+
+/// Reads an ASCII (P3) or binary (P6) PPM. Samples wider than 8 bits are
+/// rescaled.
+pub fn decode(data: &[u8]) -> Result<Bitmap, DecodeError> {
+    let mut tokens = Tokens { data, pos: 0 };
+    let magic = tokens.next()?;
+    let binary = match magic {
+        b"P3" => false,
+        b"P6" => true,
+        _ => return Err(DecodeError::Invalid("not a P3 or P6 PPM")),
+    };
+
+    let width = tokens.number()? as usize;
+    let height = tokens.number()? as usize;
+    let max = tokens.number()?;
+    if max == 0 || max > 65535 {
+        return Err(DecodeError::Invalid("PPM maxval out of range"));
+    }
+
+    let scale = |v: u32| -> Result<u8, DecodeError> {
+        if v > max {
+            return Err(DecodeError::Invalid("PPM sample above maxval"));
+        }
+        Ok(((v * 255 + max / 2) / max) as u8)
+    };
+
+    let count = pixel_count(width, height)?
+        .checked_mul(3)
+        .ok_or(DecodeError::Invalid("image dimensions too large"))?;
+    let mut samples = Vec::new();
+
+    if binary {
+        // Exactly one whitespace byte separates the header from the raster
+        let start = tokens.pos + 1;
+        let size = if max > 255 { 2 } else { 1 };
+        let raster = count
+            .checked_mul(size)
+            .and_then(|len| data.get(start..start.checked_add(len)?))
+            .ok_or(DecodeError::UnexpectedEof)?;
+        samples.reserve_exact(count);
+        for chunk in raster.chunks(size) {
+            let v = chunk.iter().fold(0u32, |acc, &b| acc << 8 | u32::from(b));
+            samples.push(scale(v)?);
+        }
+    } else {
+        // Every sample takes at least one digit
+        if count > data.len() - tokens.pos {
+            return Err(DecodeError::UnexpectedEof);
+        }
+        for _ in 0..count {
+            samples.push(scale(tokens.number()?)?);
+        }
+    }
+
+    let mut bitmap = Bitmap::new(width, height);
+
+    for (pixel, rgb) in bitmap.pixels.iter_mut().zip(samples.chunks(3)) {
+        *pixel = [rgb[0], rgb[1], rgb[2], 255];
+    }
+
+    Ok(bitmap)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::codec::{ppm, Bitmap};
+    use crate::color::Color;
+    use crate::image::Image;
+
+    #[test]
+    fn test_encode() {
+        let mut image = Image::new(2, 1);
+        image.draw_point(1, 0, &Color::new(1, 2, 3));
+
+        let mut out = Vec::new();
+        ppm::encode(&image, &mut out).unwrap();
+        assert_eq!(out, b"P6\n2 1\n255\n\x00\x00\x00\x01\x02\x03");
+    }
+
+    #[test]
+    fn test_decode_ascii() {
+        let data = b"P3\n# comment\n2 1\n15\n15 0 0  0 15 15\n";
+        let bitmap = ppm::decode(data).unwrap();
+
+        assert_eq!((bitmap.width, bitmap.height), (2, 1));
+        assert_eq!(bitmap.pixels, vec![[255, 0, 0, 255], [0, 255, 255, 255]]);
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let mut image = Image::new(3, 2);
+        image.draw_point(2, 1, &Color::new(10, 20, 30));
+
+        let mut out = Vec::new();
+        ppm::encode(&image, &mut out).unwrap();
+        let bitmap: Bitmap = ppm::decode(&out).unwrap();
+        assert_eq!(bitmap.get(2, 1), [10, 20, 30, 255]);
+        assert_eq!(bitmap.get(0, 0), [0, 0, 0, 255]);
+    }
+
+    #[test]
+    fn test_truncated() {
+        assert!(ppm::decode(b"P6\n2 2\n255\n\x00\x00").is_err());
+        assert!(ppm::decode(b"P5\n2 2\n255\n").is_err());
+    }
+
+    #[test]
+    fn test_bad_dimensions() {
+        // Huge sizes must fail before allocating, not abort
+        assert!(ppm::decode(b"P3 4000000000 4000000000 255 1").is_err());
+        assert!(ppm::decode(b"P6 4000000000 4000000000 255 \x00").is_err());
+        assert!(ppm::decode(b"P3 0 0 255").is_err());
+        assert!(ppm::decode(b"P6 0 1 255 ").is_err());
+    }
+}
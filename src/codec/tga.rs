@@ -0,0 +1,226 @@
+use crate::codec::{pixel_count, Bitmap, DecodeError, Reader};
+use crate::image::Image;
+use std::io::{self, Write};
+
+/// Writes an uncompressed 24-bit TGA with a top-left origin.
+pub fn encode<W: Write>(image: &Image, w: &mut W) -> io::Result<()> {
+    let too_large = |_| io::Error::new(io::ErrorKind::InvalidInput, "image too large for TGA");
+    let width = u16::try_from(image.w()).map_err(too_large)?;
+    let height = u16::try_from(image.h()).map_err(too_large)?;
+
+    let mut header = [0u8; 18];
+    header[2] = 2; // Uncompressed true-color
+    header[12..14].copy_from_slice(&width.to_le_bytes());
+    header[14..16].copy_from_slice(&height.to_le_bytes());
+    header[16] = 24;
+    header[17] = 0x20; // Rows stored top to bottom
+    w.write_all(&header)?;
+
+    for y in 0..image.h() {
+        let row: Vec<u8> = image.row(y).iter().flat_map(|c| [c.b, c.g, c.r]).collect();
+        w.write_all(&row)?;
+    }
+
+    Ok(())
+}
+
+fn read_color(r: &mut Reader, bits: u8, alpha_bits: u8) -> Result<[u8; 4], DecodeError> {
+    match bits {
+        8 => {
+            let v = r.u8()?;
+            Ok([v, v, v, 255])
+        }
+        15 | 16 => {
+            let v = r.u16_le()?;
+            let expand = |c: u16| ((c & 0x1F) * 255 / 31) as u8;
+            let a = if bits == 16 && alpha_bits > 0 && v & 0x8000 == 0 {
+                0
+            } else {
+                255
+            };
+            Ok([expand(v >> 10), expand(v >> 5), expand(v), a])
+        }
+        24 => {
+            let bgr = r.bytes(3)?;
+            Ok([bgr[2], bgr[1], bgr[0], 255])
+        }
+        32 => {
+            let bgra = r.bytes(4)?;
+            let a = if alpha_bits > 0 { bgra[3] } else { 255 };
+            Ok([bgra[2], bgra[1], bgra[0], a])
+        }
+        _ => Err(DecodeError::Unsupported("TGA pixel depth")),
+    }
+}
+
+/// Reads color-mapped, true-color and grayscale TGA files, raw or
+/// run-length encoded.
+pub fn decode(data: &[u8]) -> Result<Bitmap, DecodeError> {
+    let mut r = Reader::new(data);
+    let id_len = r.u8()?;
+    let cmap_type = r.u8()?;
+    let image_type = r.u8()?;
+    let cmap_first = usize::from(r.u16_le()?);
+    let cmap_len = usize::from(r.u16_le()?);
+    let cmap_bits = r.u8()?;
+    r.bytes(4)?; // Origin
+    let width = usize::from(r.u16_le()?);
+    let height = usize::from(r.u16_le()?);
+    let bits = r.u8()?;
+    let descriptor = r.u8()?;
+    let alpha_bits = descriptor & 0x0F;
+    r.bytes(usize::from(id_len))?;
+
+    let mut palette = Vec::with_capacity(cmap_len);
+    if cmap_type == 1 {
+        for _ in 0..cmap_len {
+            palette.push(read_color(&mut r, cmap_bits, alpha_bits)?);
+        }
+    }
+
+    let mapped = match image_type {
+        1 | 9 => true,
+        2 | 3 | 10 | 11 => false,
+        0 => return Err(DecodeError::Invalid("TGA has no image data")),
+        _ => return Err(DecodeError::Unsupported("TGA image type")),
+    };
+    if mapped && (palette.is_empty() || !matches!(bits, 8 | 16)) {
+        return Err(DecodeError::Invalid("bad TGA color map"));
+    }
+    if matches!(image_type, 3 | 11) && bits != 8 {
+        return Err(DecodeError::Unsupported("TGA grayscale depth"));
+    }
+
+    let read_pixel = |r: &mut Reader| -> Result<[u8; 4], DecodeError> {
+        if mapped {
+            let index = if bits == 8 {
+                usize::from(r.u8()?)
+            } else {
+                usize::from(r.u16_le()?)
+            };
+            index
+                .checked_sub(cmap_first)
+                .and_then(|i| palette.get(i).copied())
+                .ok_or(DecodeError::Invalid("TGA color index out of range"))
+        } else {
+            read_color(r, bits, alpha_bits)
+        }
+    };
+
+    let count = pixel_count(width, height)?;
+    // Raw data has a byte per pixel at least, and run-length packets hold up
+    // to 128 pixels in two bytes
+    let max_pixels = if image_type >= 9 {
+        (data.len() - r.pos).saturating_mul(64)
+    } else {
+        data.len() - r.pos
+    };
+    if count > max_pixels {
+        return Err(DecodeError::UnexpectedEof);
+    }
+    let mut pixels = Vec::with_capacity(count);
+    if image_type >= 9 {
+        while pixels.len() < count {
+            let header = r.u8()?;
+            let run = usize::from(header & 0x7F) + 1;
+            if header & 0x80 != 0 {
+                let pixel = read_pixel(&mut r)?;
+                pixels.extend(std::iter::repeat_n(pixel, run));
+            } else {
+                for _ in 0..run {
+                    pixels.push(read_pixel(&mut r)?);
+                }
+            }
+        }
+        // A packet may run past the last pixel
+        pixels.truncate(count);
+    } else {
+        for _ in 0..count {
+            pixels.push(read_pixel(&mut r)?);
+        }
+    }
+
+    // Rows are stored bottom-up unless bit 5 is set; bit 4 flips columns
+    let mut bitmap = Bitmap::new(width, height);
+    for (i, pixel) in pixels.into_iter().enumerate() {
+        let (mut x, mut y) = (i % width, i / width);
+        if descriptor & 0x20 == 0 {
+            y = height - 1 - y;
+        }
+        if descriptor & 0x10 != 0 {
+            x = width - 1 - x;
+        }
+        bitmap.set(x, y, pixel);
+    }
+
+    Ok(bitmap)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::codec::tga;
+    use crate::color::Color;
+    use crate::image::Image;
+    use std::io;
+
+    #[test]
+    fn test_encode() {
+        let mut image = Image::new(3, 2);
+        image.draw_point(0, 1, &Color::new(1, 2, 3));
+
+        let mut out = Vec::new();
+        tga::encode(&image, &mut out).unwrap();
+        assert_eq!(out.len(), 18 + 3 * 2 * 3);
+        assert_eq!(&out[12..18], &[3, 0, 2, 0, 24, 0x20]);
+        assert_eq!(&out[18 + 9..18 + 12], &[3, 2, 1]);
+    }
+
+    #[test]
+    fn test_encode_too_large() {
+        let image = Image::new(65536, 1);
+        let err = tga::encode(&image, &mut Vec::new()).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let mut image = Image::new(3, 2);
+        image.draw_point(0, 1, &Color::new(1, 2, 3));
+
+        let mut out = Vec::new();
+        tga::encode(&image, &mut out).unwrap();
+        let bitmap = tga::decode(&out).unwrap();
+        assert_eq!(bitmap.get(0, 1), [1, 2, 3, 255]);
+        assert_eq!(bitmap.get(1, 1), [0, 0, 0, 255]);
+    }
+
+    #[test]
+    fn test_decode_rle_bottom_up() {
+        // 2x2 RLE true-color with alpha, bottom-left origin
+        let mut data = vec![0, 0, 10, 0, 0, 0, 0, 0, 0, 0, 0, 0, 2, 0, 2, 0, 32, 8];
+        data.extend([0x82, 0, 0, 255, 128]); // Run of 3 red pixels
+        data.extend([0x00, 255, 0, 0, 255]); // One raw blue pixel
+
+        let bitmap = tga::decode(&data).unwrap();
+        assert_eq!(bitmap.get(0, 1), [255, 0, 0, 128]);
+        assert_eq!(bitmap.get(0, 0), [255, 0, 0, 128]);
+        assert_eq!(bitmap.get(1, 0), [0, 0, 255, 255]);
+    }
+
+    #[test]
+    fn test_decode_color_mapped() {
+        let mut data = vec![0, 1, 1, 0, 0, 2, 0, 24, 0, 0, 0, 0, 2, 0, 1, 0, 8, 0x20];
+        data.extend([0, 0, 255, 0, 255, 0]); // Palette: red, green
+        data.extend([1, 0]);
+
+        let bitmap = tga::decode(&data).unwrap();
+        assert_eq!(bitmap.pixels, vec![[0, 255, 0, 255], [255, 0, 0, 255]]);
+        assert!(tga::decode(&data[..20]).is_err());
+
+        // Sizes beyond the data, and empty images, are rejected up front
+        data[12..16].copy_from_slice(&[255, 255, 255, 255]);
+        assert!(tga::decode(&data).is_err());
+        data[12..16].copy_from_slice(&[0, 0, 1, 0]);
+        assert!(tga::decode(&data).is_err());
+    }
+}
@@ -0,0 +1,547 @@
+//! Minimal zlib (RFC 1950) and deflate (RFC 1951) support, plus the CRC-32
+//! used by PNG chunks.
+
+use crate::codec::DecodeError;
+
+const WINDOW_SIZE: usize = 32768;
+const MIN_MATCH: usize = 3;
+const MAX_MATCH: usize = 258;
+const MAX_CHAIN: usize = 64;
+const HASH_BITS: u32 = 15;
+
+const LENGTH_BASE: [u16; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131,
+    163, 195, 227, 258,
+];
+const LENGTH_EXTRA: [u8; 29] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0,
+];
+const DIST_BASE: [u16; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537,
+    2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577,
+];
+const DIST_EXTRA: [u8; 30] = [
+    0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13,
+    13,
+];
+
+pub fn crc32(data: &[u8]) -> u32 {
+    crc32_update(0, data)
+}
+
+/// Continues a CRC-32 over more data; start with 0.
+pub fn crc32_update(crc: u32, data: &[u8]) -> u32 {
+    let mut crc = !crc;
+    for &byte in data {
+        crc ^= u32::from(byte);
+        for _ in 0..8 {
+            let mask = (crc & 1).wrapping_neg();
+            crc = (crc >> 1) ^ (0xEDB8_8320 & mask);
+        }
+    }
+    !crc
+}
+
+pub fn adler32(data: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    // 5552 is the largest block that cannot overflow before the modulo
+    for chunk in data.chunks(5552) {
+        for &byte in chunk {
+            a += u32::from(byte);
+            b += a;
+        }
+        a %= 65521;
+        b %= 65521;
+    }
+    (b << 16) | a
+}
+
+struct BitWriter {
+    out: Vec<u8>,
+    bits: u32,
+    count: u32,
+}
+
+impl BitWriter {
+    fn new(out: Vec<u8>) -> Self {
+        Self {
+            out,
+            bits: 0,
+            count: 0,
+        }
+    }
+
+    /// Writes the low `n` bits of `value`, least significant bit first.
+    fn write(&mut self, value: u32, n: u32) {
+        self.bits |= value << self.count;
+        self.count += n;
+        while self.count >= 8 {
+            self.out.push(self.bits as u8);
+            self.bits >>= 8;
+            self.count -= 8;
+        }
+    }
+
+    /// Huffman codes are packed most significant bit first.
+    fn write_code(&mut self, code: u32, n: u32) {
+        let reversed = code.reverse_bits() >> (32 - n);
+        self.write(reversed, n);
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        if self.count > 0 {
+            self.out.push(self.bits as u8);
+        }
+        self.out
+    }
+}
+
+fn write_literal(w: &mut BitWriter, lit: u16) {
+    let lit = u32::from(lit);
+    match lit {
+        0..=143 => w.write_code(0x30 + lit, 8),
+        144..=255 => w.write_code(0x190 + lit - 144, 9),
+        256..=279 => w.write_code(lit - 256, 7),
+        _ => w.write_code(0xC0 + lit - 280, 8),
+    }
+}
+
+fn write_match(w: &mut BitWriter, len: usize, dist: usize) {
+    let code = LENGTH_BASE
+        .iter()
+        .rposition(|&b| usize::from(b) <= len)
+        .unwrap();
+    write_literal(w, 257 + code as u16);
+    w.write(
+        (len - usize::from(LENGTH_BASE[code])) as u32,
+        u32::from(LENGTH_EXTRA[code]),
+    );
+
+    let code = DIST_BASE
+        .iter()
+        .rposition(|&b| usize::from(b) <= dist)
+        .unwrap();
+    w.write_code(code as u32, 5);
+    w.write(
+        (dist - usize::from(DIST_BASE[code])) as u32,
+        u32::from(DIST_EXTRA[code]),
+    );
+}
+
+fn hash(data: &[u8], i: usize) -> usize {
+    let v = u32::from(data[i]) << 16 | u32::from(data[i + 1]) << 8 | u32::from(data[i + 2]);
+    (v.wrapping_mul(0x9E37_79B1) >> (32 - HASH_BITS)) as usize
+}
+
+/// Hash chains over the last `WINDOW_SIZE` positions.
+struct Matcher<'a> {
+    data: &'a [u8],
+    head: Vec<usize>,
+    prev: Vec<usize>,
+}
+
+impl<'a> Matcher<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self {
+            data,
+            head: vec![usize::MAX; 1 << HASH_BITS],
+            prev: vec![usize::MAX; WINDOW_SIZE],
+        }
+    }
+
+    fn insert(&mut self, i: usize) {
+        if i + MIN_MATCH <= self.data.len() {
+            let h = hash(self.data, i);
+            self.prev[i % WINDOW_SIZE] = self.head[h];
+            self.head[h] = i;
+        }
+    }
+
+    /// Longest earlier match for position `i` as `(length, distance)`.
+    fn find(&self, i: usize) -> (usize, usize) {
+        let data = self.data;
+        let (mut best_len, mut best_dist) = (0, 0);
+        if i + MIN_MATCH > data.len() {
+            return (best_len, best_dist);
+        }
+
+        let max_len = MAX_MATCH.min(data.len() - i);
+        let mut candidate = self.head[hash(data, i)];
+        let mut chain = 0;
+
+        while candidate != usize::MAX && i - candidate <= WINDOW_SIZE && chain < MAX_CHAIN {
+            let len = data[candidate..]
+                .iter()
+                .zip(&data[i..i + max_len])
+                .take_while(|(a, b)| a == b)
+                .count();
+            if len > best_len {
+                best_len = len;
+                best_dist = i - candidate;
+                if len == max_len {
+                    break;
+                }
+            }
+
+            let next = self.prev[candidate % WINDOW_SIZE];
+            if next == usize::MAX || next >= candidate {
+                break;
+            }
+            candidate = next;
+            chain += 1;
+        }
+
+        (best_len, best_dist)
+    }
+}
+
+/// Compresses `data` into a raw deflate stream: greedy LZ77 over hash chains,
+/// emitted as a single block with the fixed Huffman codes.
+pub fn deflate(data: &[u8]) -> Vec<u8> {
+    let mut w = BitWriter::new(Vec::new());
+    // BFINAL = 1, BTYPE = 01 (fixed Huffman)
+    w.write(1, 1);
+    w.write(1, 2);
+
+    let mut matcher = Matcher::new(data);
+    let mut i = 0;
+    while i < data.len() {
+        let (len, dist) = matcher.find(i);
+
+        if len >= MIN_MATCH {
+            write_match(&mut w, len, dist);
+            for j in i..i + len {
+                matcher.insert(j);
+            }
+            i += len;
+        } else {
+            write_literal(&mut w, u16::from(data[i]));
+            matcher.insert(i);
+            i += 1;
+        }
+    }
+
+    write_literal(&mut w, 256);
+    w.finish()
+}
+
+/// Wraps a deflate stream in the zlib header and Adler-32 trailer.
+pub fn compress(data: &[u8]) -> Vec<u8> {
+    // CM = 8 (deflate), CINFO = 7 (32K window), no dictionary, FCHECK makes
+    // the header a multiple of 31
+    let mut out = vec![0x78, 0x01];
+    out.extend(deflate(data));
+    out.extend(adler32(data).to_be_bytes());
+    out
+}
+
+struct BitReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+    bits: u32,
+    count: u32,
+}
+
+impl<'a> BitReader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self {
+            data,
+            pos: 0,
+            bits: 0,
+            count: 0,
+        }
+    }
+
+    fn read(&mut self, n: u32) -> Result<u32, DecodeError> {
+        while self.count < n {
+            let byte = *self.data.get(self.pos).ok_or(DecodeError::UnexpectedEof)?;
+            self.bits |= u32::from(byte) << self.count;
+            self.pos += 1;
+            self.count += 8;
+        }
+
+        let value = self.bits & ((1u64 << n) - 1) as u32;
+        self.bits >>= n;
+        self.count -= n;
+        Ok(value)
+    }
+
+    /// Drops the bits left in the current byte.
+    fn align(&mut self) {
+        self.bits = 0;
+        self.count = 0;
+    }
+}
+
+/// Canonical Huffman code stored as the number of codes per length plus the
+/// symbols sorted by code.
+struct Huffman {
+    counts: [u16; 16],
+    symbols: Vec<u16>,
+}
+
+impl Huffman {
+    fn new(lengths: &[u8]) -> Result<Self, DecodeError> {
+        let mut counts = [0u16; 16];
+        for &len in lengths {
+            counts[usize::from(len)] += 1;
+        }
+        counts[0] = 0;
+
+        // Reject over-subscribed codes; incomplete ones are allowed
+        let mut left = 1i32;
+        for &count in &counts[1..] {
+            left = (left << 1) - i32::from(count);
+            if left < 0 {
+                return Err(DecodeError::Invalid("over-subscribed Huffman code"));
+            }
+        }
+
+        let mut offsets = [0u16; 16];
+        for len in 1..15 {
+            offsets[len + 1] = offsets[len] + counts[len];
+        }
+
+        let mut symbols = vec![0u16; lengths.len()];
+        for (symbol, &len) in lengths.iter().enumerate() {
+            if len != 0 {
+                symbols[usize::from(offsets[usize::from(len)])] = symbol as u16;
+                offsets[usize::from(len)] += 1;
+            }
+        }
+
+        Ok(Self { counts, symbols })
+    }
+
+    fn decode(&self, r: &mut BitReader) -> Result<u16, DecodeError> {
+        let (mut code, mut first, mut index) = (0i32, 0i32, 0i32);
+        for len in 1..16 {
+            code |= r.read(1)? as i32;
+            let count = i32::from(self.counts[len]);
+            if code - first < count {
+                return Ok(self.symbols[(index + code - first) as usize]);
+            }
+            index += count;
+            first = (first + count) << 1;
+            code <<= 1;
+        }
+
+        Err(DecodeError::Invalid("bad Huffman code"))
+    }
+}
+
+fn fixed_codes() -> Result<(Huffman, Huffman), DecodeError> {
+    let mut lengths = [0u8; 288];
+    lengths[..144].fill(8);
+    lengths[144..256].fill(9);
+    lengths[256..280].fill(7);
+    lengths[280..].fill(8);
+
+    Ok((Huffman::new(&lengths)?, Huffman::new(&[5; 30])?))
+}
+
+fn dynamic_codes(r: &mut BitReader) -> Result<(Huffman, Huffman), DecodeError> {
+    const ORDER: [usize; 19] = [
+        16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15,
+    ];
+
+    let nlen = r.read(5)? as usize + 257;
+    let ndist = r.read(5)? as usize + 1;
+    let ncode = r.read(4)? as usize + 4;
+
+    let mut lengths = [0u8; 19];
+    for &i in &ORDER[..ncode] {
+        lengths[i] = r.read(3)? as u8;
+    }
+    let code = Huffman::new(&lengths)?;
+
+    let mut lengths = vec![0u8; nlen + ndist];
+    let mut i = 0;
+    while i < lengths.len() {
+        let symbol = code.decode(r)?;
+        let (value, repeat) = match symbol {
+            0..=15 => (symbol as u8, 1),
+            16 => {
+                let prev = *lengths[..i]
+                    .last()
+                    .ok_or(DecodeError::Invalid("repeat with no previous length"))?;
+                (prev, 3 + r.read(2)? as usize)
+            }
+            17 => (0, 3 + r.read(3)? as usize),
+            _ => (0, 11 + r.read(7)? as usize),
+        };
+
+        if i + repeat > lengths.len() {
+            return Err(DecodeError::Invalid("too many code lengths"));
+        }
+        lengths[i..i + repeat].fill(value);
+        i += repeat;
+    }
+
+    if lengths[256] == 0 {
+        return Err(DecodeError::Invalid("missing end-of-block code"));
+    }
+
+    Ok((
+        Huffman::new(&lengths[..nlen])?,
+        Huffman::new(&lengths[nlen..])?,
+    ))
+}
+
+fn inflate_block(
+    r: &mut BitReader,
+    out: &mut Vec<u8>,
+    lit: &Huffman,
+    dist: &Huffman,
+) -> Result<(), DecodeError> {
+    loop {
+        let symbol = usize::from(lit.decode(r)?);
+        match symbol {
+            0..=255 => out.push(symbol as u8),
+            256 => return Ok(()),
+            _ => {
+                let code = symbol - 257;
+                if code >= LENGTH_BASE.len() {
+                    return Err(DecodeError::Invalid("bad length code"));
+                }
+                let len = usize::from(LENGTH_BASE[code])
+                    + r.read(u32::from(LENGTH_EXTRA[code]))? as usize;
+
+                let code = usize::from(dist.decode(r)?);
+                if code >= DIST_BASE.len() {
+                    return Err(DecodeError::Invalid("bad distance code"));
+                }
+                let d =
+                    usize::from(DIST_BASE[code]) + r.read(u32::from(DIST_EXTRA[code]))? as usize;
+                if d > out.len() {
+                    return Err(DecodeError::Invalid("distance too far back"));
+                }
+
+                // Byte by byte, since the match may overlap its own output
+                let start = out.len() - d;
+                for i in 0..len {
+                    out.push(out[start + i]);
+                }
+            }
+        }
+    }
+}
+
+/// Decompresses a raw deflate stream.
+pub fn inflate(data: &[u8]) -> Result<Vec<u8>, DecodeError> {
+    let mut r = BitReader::new(data);
+    let mut out = Vec::new();
+
+    loop {
+        let last = r.read(1)? == 1;
+        match r.read(2)? {
+            0 => {
+                r.align();
+                let header = data
+                    .get(r.pos..r.pos + 4)
+                    .ok_or(DecodeError::UnexpectedEof)?;
+                let len = usize::from(u16::from_le_bytes([header[0], header[1]]));
+                let nlen = u16::from_le_bytes([header[2], header[3]]);
+                if len as u16 != !nlen {
+                    return Err(DecodeError::Invalid("stored block length mismatch"));
+                }
+
+                let start = r.pos + 4;
+                let block = data
+                    .get(start..start + len)
+                    .ok_or(DecodeError::UnexpectedEof)?;
+                out.extend_from_slice(block);
+                r.pos = start + len;
+            }
+            1 => {
+                let (lit, dist) = fixed_codes()?;
+                inflate_block(&mut r, &mut out, &lit, &dist)?;
+            }
+            2 => {
+                let (lit, dist) = dynamic_codes(&mut r)?;
+                inflate_block(&mut r, &mut out, &lit, &dist)?;
+            }
+            _ => return Err(DecodeError::Invalid("reserved block type")),
+        }
+
+        if last {
+            return Ok(out);
+        }
+    }
+}
+
+/// Unwraps and decompresses a zlib stream, verifying the Adler-32 checksum.
+pub fn decompress(data: &[u8]) -> Result<Vec<u8>, DecodeError> {
+    if data.len() < 6 {
+        return Err(DecodeError::UnexpectedEof);
+    }
+
+    let (cmf, flg) = (data[0], data[1]);
+    if cmf & 0x0F != 8 || (u16::from(cmf) << 8 | u16::from(flg)) % 31 != 0 {
+        return Err(DecodeError::Invalid("bad zlib header"));
+    }
+    if flg & 0x20 != 0 {
+        return Err(DecodeError::Unsupported("zlib preset dictionary"));
+    }
+
+    let out = inflate(&data[2..data.len() - 4])?;
+    let expected = u32::from_be_bytes(data[data.len() - 4..].try_into().unwrap());
+    if adler32(&out) != expected {
+        return Err(DecodeError::Checksum);
+    }
+
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::codec::zlib::{adler32, compress, crc32, decompress, inflate};
+
+    #[test]
+    fn test_crc32() {
+        assert_eq!(crc32(b""), 0);
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+    }
+
+    #[test]
+    fn test_adler32() {
+        assert_eq!(adler32(b"Wikipedia"), 0x11E6_0398);
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let mut data = b"hello hello hello, deflate! ".repeat(50);
+        data.extend((0..5000u32).map(|i| (i * i % 251) as u8));
+
+        assert_eq!(decompress(&compress(&data)).unwrap(), data);
+        assert_eq!(decompress(&compress(&[])).unwrap(), Vec::<u8>::new());
+    }
+
+    #[test]
+    fn test_inflate_stored_and_dynamic() {
+        // Stored block holding "abc"
+        assert_eq!(
+            inflate(&[0x01, 0x03, 0x00, 0xFC, 0xFF, b'a', b'b', b'c']).unwrap(),
+            b"abc"
+        );
+
+        // Dynamic Huffman block produced by CPython's zlib.compress(data, 9)
+        let data = b"aaaa bbaa aaaaacaabbbc baaaaaaaabbbaaaabbbabbb";
+        let stream = [
+            0x78, 0xda, 0x35, 0x85, 0x01, 0x0d, 0x00, 0x00, 0x08, 0x83, 0xaa, 0x58, 0x0d, 0xde,
+            0xbf, 0x83, 0xba, 0x29, 0x1b, 0x00, 0x50, 0x3a, 0x61, 0x09, 0xa8, 0x29, 0x39, 0xd4,
+            0xdf, 0xd8, 0x81, 0x17, 0x10, 0xbf,
+        ];
+        assert_eq!(stream[2] >> 1 & 3, 2);
+        assert_eq!(decompress(&stream).unwrap(), data);
+    }
+
+    #[test]
+    fn test_checksum_mismatch() {
+        let mut stream = compress(b"checksum");
+        let last = stream.len() - 1;
+        stream[last] ^= 1;
+
+        assert!(decompress(&stream).is_err());
+    }
+}
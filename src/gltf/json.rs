@@ -0,0 +1,323 @@
+use std::fmt;
+
+/// A parsed JSON value. Objects keep their keys in document order.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Json {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>),
+}
+
+/// Malformed JSON at a byte offset.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JsonError {
+    pub offset: usize,
+    pub message: &'static str,
+}
+
+impl fmt::Display for JsonError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "JSON error at byte {}: {}", self.offset, self.message)
+    }
+}
+
+impl Json {
+    pub fn parse(text: &str) -> Result<Json, JsonError> {
+        let mut parser = Parser {
+            data: text.as_bytes(),
+            pos: 0,
+        };
+        let value = parser.value(0)?;
+        parser.skip_whitespace();
+        if parser.pos != parser.data.len() {
+            return Err(parser.error("trailing characters"));
+        }
+        Ok(value)
+    }
+
+    /// Member `key` of an object.
+    pub fn get(&self, key: &str) -> Option<&Json> {
+        match self {
+            Json::Object(members) => members.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        match *self {
+            Json::Number(n) => Some(n),
+            _ => None,
+        }
+    }
+
+    pub fn as_f32(&self) -> Option<f32> {
+        self.as_f64().map(|n| n as f32)
+    }
+
+    /// The value as an index or count, if it is a non-negative integer.
+    pub fn as_usize(&self) -> Option<usize> {
+        self.as_f64()
+            .filter(|n| *n >= 0.0 && n.fract() == 0.0)
+            .map(|n| n as usize)
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match *self {
+            Json::Bool(b) => Some(b),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Json::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&[Json]> {
+        match self {
+            Json::Array(items) => Some(items),
+            _ => None,
+        }
+    }
+
+    /// A numeric array of exactly `N` elements.
+    pub fn as_f32_array<const N: usize>(&self) -> Option<[f32; N]> {
+        let items = self.as_array()?;
+        if items.len() != N {
+            return None;
+        }
+        let mut out = [0.0; N];
+        for (o, item) in out.iter_mut().zip(items) {
+            *o = item.as_f32()?;
+        }
+        Some(out)
+    }
+}
+
+// Deeper nesting is rejected rather than risking stack exhaustion
+const MAX_DEPTH: usize = 128;
+
+struct Parser<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn error(&self, message: &'static str) -> JsonError {
+        JsonError {
+            offset: self.pos,
+            message,
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        while matches!(self.data.get(self.pos), Some(b' ' | b'\t' | b'\n' | b'\r')) {
+            self.pos += 1;
+        }
+    }
+
+    fn expect(&mut self, literal: &[u8]) -> Result<(), JsonError> {
+        if self.data[self.pos..].starts_with(literal) {
+            self.pos += literal.len();
+            Ok(())
+        } else {
+            Err(self.error("invalid literal"))
+        }
+    }
+
+    fn value(&mut self, depth: usize) -> Result<Json, JsonError> {
+        if depth > MAX_DEPTH {
+            return Err(self.error("nesting too deep"));
+        }
+
+        self.skip_whitespace();
+        match self.data.get(self.pos) {
+            None => Err(self.error("unexpected end")),
+            Some(b'n') => self.expect(b"null").map(|_| Json::Null),
+            Some(b't') => self.expect(b"true").map(|_| Json::Bool(true)),
+            Some(b'f') => self.expect(b"false").map(|_| Json::Bool(false)),
+            Some(b'"') => self.string().map(Json::String),
+            Some(b'[') => {
+                self.pos += 1;
+                let mut items = Vec::new();
+                self.skip_whitespace();
+                if self.data.get(self.pos) == Some(&b']') {
+                    self.pos += 1;
+                    return Ok(Json::Array(items));
+                }
+                loop {
+                    items.push(self.value(depth + 1)?);
+                    self.skip_whitespace();
+                    match self.data.get(self.pos) {
+                        Some(b',') => self.pos += 1,
+                        Some(b']') => {
+                            self.pos += 1;
+                            return Ok(Json::Array(items));
+                        }
+                        _ => return Err(self.error("expected , or ]")),
+                    }
+                }
+            }
+            Some(b'{') => {
+                self.pos += 1;
+                let mut members = Vec::new();
+                self.skip_whitespace();
+                if self.data.get(self.pos) == Some(&b'}') {
+                    self.pos += 1;
+                    return Ok(Json::Object(members));
+                }
+                loop {
+                    self.skip_whitespace();
+                    if self.data.get(self.pos) != Some(&b'"') {
+                        return Err(self.error("expected key"));
+                    }
+                    let key = self.string()?;
+                    self.skip_whitespace();
+                    if self.data.get(self.pos) != Some(&b':') {
+                        return Err(self.error("expected :"));
+                    }
+                    self.pos += 1;
+                    members.push((key, self.value(depth + 1)?));
+                    self.skip_whitespace();
+                    match self.data.get(self.pos) {
+                        Some(b',') => self.pos += 1,
+                        Some(b'}') => {
+                            self.pos += 1;
+                            return Ok(Json::Object(members));
+                        }
+                        _ => return Err(self.error("expected , or }")),
+                    }
+                }
+            }
+            Some(b'-' | b'0'..=b'9') => self.number(),
+            Some(_) => Err(self.error("unexpected character")),
+        }
+    }
+
+    fn number(&mut self) -> Result<Json, JsonError> {
+        let start = self.pos;
+        while matches!(
+            self.data.get(self.pos),
+            Some(b'-' | b'+' | b'.' | b'e' | b'E' | b'0'..=b'9')
+        ) {
+            self.pos += 1;
+        }
+        std::str::from_utf8(&self.data[start..self.pos])
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .map(Json::Number)
+            .ok_or(JsonError {
+                offset: start,
+                message: "invalid number",
+            })
+    }
+
+    fn hex4(&mut self) -> Result<u32, JsonError> {
+        let digits = self
+            .data
+            .get(self.pos..self.pos + 4)
+            .and_then(|d| std::str::from_utf8(d).ok())
+            .and_then(|d| u32::from_str_radix(d, 16).ok())
+            .ok_or_else(|| self.error("invalid \\u escape"))?;
+        self.pos += 4;
+        Ok(digits)
+    }
+
+    fn string(&mut self) -> Result<String, JsonError> {
+        // Skip the opening quote
+        self.pos += 1;
+        let mut out = Vec::new();
+
+        loop {
+            let Some(&b) = self.data.get(self.pos) else {
+                return Err(self.error("unterminated string"));
+            };
+            self.pos += 1;
+
+            match b {
+                b'"' => break,
+                b'\\' => {
+                    let Some(&escape) = self.data.get(self.pos) else {
+                        return Err(self.error("unterminated string"));
+                    };
+                    self.pos += 1;
+                    let c = match escape {
+                        b'"' => '"',
+                        b'\\' => '\\',
+                        b'/' => '/',
+                        b'b' => '\u{8}',
+                        b'f' => '\u{c}',
+                        b'n' => '\n',
+                        b'r' => '\r',
+                        b't' => '\t',
+                        b'u' => {
+                            let mut code = self.hex4()?;
+                            // Combine a surrogate pair
+                            if (0xd800..0xdc00).contains(&code)
+                                && self.data[self.pos..].starts_with(b"\\u")
+                            {
+                                self.pos += 2;
+                                let low = self.hex4()?;
+                                code = 0x10000
+                                    + ((code - 0xd800) << 10)
+                                    + (low.wrapping_sub(0xdc00) & 0x3ff);
+                            }
+                            char::from_u32(code).unwrap_or('\u{fffd}')
+                        }
+                        _ => return Err(self.error("invalid escape")),
+                    };
+                    let mut buf = [0; 4];
+                    out.extend_from_slice(c.encode_utf8(&mut buf).as_bytes());
+                }
+                _ => out.push(b),
+            }
+        }
+
+        String::from_utf8(out).map_err(|_| self.error("invalid UTF-8"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::gltf::json::Json;
+
+    #[test]
+    fn test_parse() {
+        let json =
+            Json::parse(r#" { "a": [1, -2.5e1, true, null], "b": {"c": "x\"é😀"}, "d": [] } "#)
+                .unwrap();
+
+        let a = json.get("a").unwrap().as_array().unwrap();
+        assert_eq!(a[1].as_f64(), Some(-25.0));
+        assert_eq!(a[2].as_bool(), Some(true));
+        assert_eq!(a[3], Json::Null);
+        assert_eq!(a[0].as_usize(), Some(1));
+        assert_eq!(a[1].as_usize(), None);
+
+        let c = json.get("b").and_then(|b| b.get("c")).unwrap();
+        assert_eq!(c.as_str(), Some("x\"é😀"));
+        let escaped = Json::parse(r#""\u00e9\ud83d\ude00\n""#).unwrap();
+        assert_eq!(escaped.as_str(), Some("é😀\n"));
+        assert_eq!(json.get("d").unwrap().as_array().unwrap().len(), 0);
+        assert_eq!(json.get("e"), None);
+
+        assert_eq!(
+            Json::parse("[1, 2, 3]").unwrap().as_f32_array::<3>(),
+            Some([1.0, 2.0, 3.0])
+        );
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(Json::parse("[1, 2").unwrap_err().offset, 5);
+        assert_eq!(Json::parse("{\"a\" 1}").unwrap_err().message, "expected :");
+        assert!(Json::parse("[1] x").is_err());
+        assert!(Json::parse("tru").is_err());
+        assert!(Json::parse(&"[".repeat(1000)).is_err());
+    }
+}
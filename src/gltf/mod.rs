@@ -0,0 +1,861 @@
+//! glTF 2.0 import from `.gltf` (JSON) and `.glb` (binary) containers.
+//!
+//! Scenes are flattened into an `ObjModel`: every mesh instance is transformed
+//! by its node's world matrix. Animation and skinning are not supported.
+
+pub mod json;
+
+use crate::mat44::Mat44;
+use crate::mtl::{Material, Pbr};
+use crate::obj::{Face, Group, ObjModel, Vertex};
+use crate::quat::Quat;
+use crate::vec3::Vec3;
+use crate::vec4::Vec4;
+use json::{Json, JsonError};
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Why a glTF asset could not be imported.
+#[derive(Debug)]
+pub enum GltfError {
+    Io(io::Error),
+    Json(JsonError),
+    /// A valid asset using a feature the importer does not implement.
+    Unsupported(&'static str),
+    /// Malformed or inconsistent data.
+    Invalid(&'static str),
+}
+
+impl fmt::Display for GltfError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GltfError::Io(err) => write!(f, "{}", err),
+            GltfError::Json(err) => write!(f, "{}", err),
+            GltfError::Unsupported(what) => write!(f, "unsupported: {}", what),
+            GltfError::Invalid(what) => write!(f, "invalid glTF: {}", what),
+        }
+    }
+}
+
+impl Error for GltfError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            GltfError::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for GltfError {
+    fn from(err: io::Error) -> Self {
+        GltfError::Io(err)
+    }
+}
+
+impl From<JsonError> for GltfError {
+    fn from(err: JsonError) -> Self {
+        GltfError::Json(err)
+    }
+}
+
+const GLB_MAGIC: &[u8; 4] = b"glTF";
+const CHUNK_JSON: u32 = 0x4e4f_534a;
+const CHUNK_BIN: u32 = 0x004e_4942;
+
+// Primitive modes
+const TRIANGLES: usize = 4;
+const TRIANGLE_STRIP: usize = 5;
+const TRIANGLE_FAN: usize = 6;
+
+/// Most values a view-less accessor may fill with zeros, since its count
+/// isn't bounded by any buffer.
+const MAX_ZERO_FILL: usize = 1 << 24;
+
+/// Loads a `.gltf` or `.glb` file, resolving external buffers and images
+/// against its directory.
+pub fn load<P: AsRef<Path>>(path: P) -> Result<ObjModel, GltfError> {
+    let path = path.as_ref();
+    decode(&fs::read(path)?, path.parent())
+}
+
+/// Imports a glTF asset from JSON text or a GLB container. Without a `base`
+/// directory only embedded buffers can be read.
+pub fn decode(data: &[u8], base: Option<&Path>) -> Result<ObjModel, GltfError> {
+    let (text, bin) = if data.starts_with(GLB_MAGIC) {
+        parse_glb(data)?
+    } else {
+        (data, None)
+    };
+
+    let text = std::str::from_utf8(text).map_err(|_| GltfError::Invalid("JSON is not UTF-8"))?;
+    let json = Json::parse(text)?;
+    let document = Document::new(&json, bin, base)?;
+    document.import()
+}
+
+/// Splits a GLB container into its JSON and optional binary chunk.
+fn parse_glb(data: &[u8]) -> Result<(&[u8], Option<&[u8]>), GltfError> {
+    let u32_at = |pos: usize| {
+        data.get(pos..pos + 4)
+            .map(|b| u32::from_le_bytes(b.try_into().unwrap()))
+            .ok_or(GltfError::Invalid("truncated GLB"))
+    };
+
+    if u32_at(4)? != 2 {
+        return Err(GltfError::Unsupported("GLB version"));
+    }
+    let length = (u32_at(8)? as usize).min(data.len());
+
+    let mut json = None;
+    let mut bin = None;
+    let mut pos = 12;
+    while pos + 8 <= length {
+        let chunk_length = u32_at(pos)? as usize;
+        let chunk_type = u32_at(pos + 4)?;
+        let chunk = data
+            .get(pos + 8..pos + 8 + chunk_length)
+            .ok_or(GltfError::Invalid("truncated GLB chunk"))?;
+        match chunk_type {
+            CHUNK_JSON if json.is_none() => json = Some(chunk),
+            CHUNK_BIN if bin.is_none() => bin = Some(chunk),
+            _ => {}
+        }
+        // Chunks are padded to four bytes
+        pos += 8 + chunk_length.next_multiple_of(4);
+    }
+
+    Ok((
+        json.ok_or(GltfError::Invalid("GLB without JSON chunk"))?,
+        bin,
+    ))
+}
+
+/// Decodes standard base64, ignoring padding.
+fn base64(text: &str) -> Option<Vec<u8>> {
+    let mut out = Vec::with_capacity(text.len() * 3 / 4);
+    let mut bits = 0u32;
+    let mut count = 0;
+
+    for b in text.bytes().filter(|&b| b != b'=') {
+        let value = match b {
+            b'A'..=b'Z' => b - b'A',
+            b'a'..=b'z' => b - b'a' + 26,
+            b'0'..=b'9' => b - b'0' + 52,
+            b'+' => 62,
+            b'/' => 63,
+            _ => return None,
+        };
+        bits = bits << 6 | value as u32;
+        count += 6;
+        if count >= 8 {
+            count -= 8;
+            out.push((bits >> count) as u8);
+        }
+    }
+    Some(out)
+}
+
+/// Payload of a `data:` URI, if `uri` is one with base64 content.
+fn data_uri(uri: &str) -> Option<Result<Vec<u8>, GltfError>> {
+    let rest = uri.strip_prefix("data:")?;
+    let (_, payload) = rest.split_once(";base64,")?;
+    Some(base64(payload).ok_or(GltfError::Invalid("bad base64 data URI")))
+}
+
+/// The parsed JSON with its buffers loaded.
+struct Document<'a> {
+    json: &'a Json,
+    buffers: Vec<Vec<u8>>,
+    base: Option<&'a Path>,
+}
+
+/// Elements of an array-valued property, or none if it is missing.
+fn array<'a>(json: &'a Json, key: &str) -> &'a [Json] {
+    json.get(key).and_then(Json::as_array).unwrap_or_default()
+}
+
+impl<'a> Document<'a> {
+    fn new(json: &'a Json, bin: Option<&[u8]>, base: Option<&'a Path>) -> Result<Self, GltfError> {
+        let mut buffers = Vec::new();
+        for (i, buffer) in array(json, "buffers").iter().enumerate() {
+            let data = match buffer.get("uri").and_then(Json::as_str) {
+                Some(uri) => match data_uri(uri) {
+                    Some(data) => data?,
+                    None => {
+                        let base =
+                            base.ok_or(GltfError::Invalid("external buffer without a path"))?;
+                        fs::read(base.join(uri))?
+                    }
+                },
+                // Only the first buffer may refer to the GLB binary chunk
+                None if i == 0 => bin
+                    .ok_or(GltfError::Invalid("missing GLB binary chunk"))?
+                    .to_vec(),
+                None => return Err(GltfError::Invalid("buffer without uri")),
+            };
+
+            let length = buffer
+                .get("byteLength")
+                .and_then(Json::as_usize)
+                .unwrap_or(0);
+            if data.len() < length {
+                return Err(GltfError::Invalid("buffer shorter than byteLength"));
+            }
+            buffers.push(data);
+        }
+
+        Ok(Self {
+            json,
+            buffers,
+            base,
+        })
+    }
+
+    /// Element `index` of the top-level array `key`.
+    fn item(&self, key: &str, index: usize) -> Result<&'a Json, GltfError> {
+        array(self.json, key)
+            .get(index)
+            .ok_or(GltfError::Invalid("index out of range"))
+    }
+
+    /// Reads an accessor as `count` rows of components, converted to `f64`.
+    /// Normalized integers are mapped to `[0, 1]` or `[-1, 1]`.
+    fn accessor(&self, index: usize) -> Result<(Vec<f64>, usize), GltfError> {
+        let accessor = self.item("accessors", index)?;
+        if accessor.get("sparse").is_some() {
+            return Err(GltfError::Unsupported("sparse accessors"));
+        }
+
+        let components = match accessor.get("type").and_then(Json::as_str) {
+            Some("SCALAR") => 1,
+            Some("VEC2") => 2,
+            Some("VEC3") => 3,
+            Some("VEC4") | Some("MAT2") => 4,
+            Some("MAT3") => 9,
+            Some("MAT4") => 16,
+            _ => return Err(GltfError::Invalid("accessor type")),
+        };
+        let component_type = accessor.get("componentType").and_then(Json::as_usize);
+        let size = match component_type {
+            Some(5120 | 5121) => 1,
+            Some(5122 | 5123) => 2,
+            Some(5125 | 5126) => 4,
+            _ => return Err(GltfError::Invalid("accessor componentType")),
+        };
+        let normalized = accessor
+            .get("normalized")
+            .and_then(Json::as_bool)
+            .unwrap_or(false);
+        let count = accessor
+            .get("count")
+            .and_then(Json::as_usize)
+            .ok_or(GltfError::Invalid("accessor count"))?;
+
+        let len = count
+            .checked_mul(components)
+            .ok_or(GltfError::Invalid("accessor count"))?;
+
+        // An accessor without a view reads as zeros
+        let Some(view) = accessor.get("bufferView").and_then(Json::as_usize) else {
+            if len > MAX_ZERO_FILL {
+                return Err(GltfError::Unsupported(
+                    "accessor without bufferView this large",
+                ));
+            }
+            return Ok((vec![0.0; len], components));
+        };
+        let view = self.item("bufferViews", view)?;
+        let buffer = view
+            .get("buffer")
+            .and_then(Json::as_usize)
+            .and_then(|b| self.buffers.get(b))
+            .ok_or(GltfError::Invalid("bufferView buffer"))?;
+        let view_offset = view.get("byteOffset").and_then(Json::as_usize).unwrap_or(0);
+        let view_length = view.get("byteLength").and_then(Json::as_usize).unwrap_or(0);
+        let data = view_offset
+            .checked_add(view_length)
+            .and_then(|end| buffer.get(view_offset..end))
+            .ok_or(GltfError::Invalid("bufferView out of range"))?;
+
+        let element = size * components;
+        let stride = view
+            .get("byteStride")
+            .and_then(Json::as_usize)
+            .unwrap_or(element);
+        let offset = accessor
+            .get("byteOffset")
+            .and_then(Json::as_usize)
+            .unwrap_or(0);
+        if count > 0 {
+            let end = stride
+                .checked_mul(count - 1)
+                .and_then(|n| n.checked_add(offset))
+                .and_then(|n| n.checked_add(element));
+            if end.is_none_or(|end| end > data.len()) {
+                return Err(GltfError::Invalid("accessor out of range"));
+            }
+        }
+
+        let mut values = Vec::with_capacity(len);
+        for i in 0..count {
+            let row = &data[offset + i * stride..];
+            for c in 0..components {
+                let b = &row[c * size..(c + 1) * size];
+                let value = match component_type {
+                    Some(5120) if normalized => (b[0] as i8 as f64 / 127.0).max(-1.0),
+                    Some(5120) => b[0] as i8 as f64,
+                    Some(5121) if normalized => b[0] as f64 / 255.0,
+                    Some(5121) => b[0] as f64,
+                    Some(5122) => {
+                        let v = i16::from_le_bytes([b[0], b[1]]) as f64;
+                        if normalized {
+                            (v / 32767.0).max(-1.0)
+                        } else {
+                            v
+                        }
+                    }
+                    Some(5123) => {
+                        let v = u16::from_le_bytes([b[0], b[1]]) as f64;
+                        if normalized {
+                            v / 65535.0
+                        } else {
+                            v
+                        }
+                    }
+                    Some(5125) => u32::from_le_bytes(b.try_into().unwrap()) as f64,
+                    _ => f32::from_le_bytes(b.try_into().unwrap()) as f64,
+                };
+                values.push(value);
+            }
+        }
+        Ok((values, components))
+    }
+
+    /// Path of the image behind texture info `info`, if it is a separate file.
+    fn texture_path(&self, info: Option<&Json>) -> Option<PathBuf> {
+        let texture = info?.get("index")?.as_usize()?;
+        let source = array(self.json, "textures")
+            .get(texture)?
+            .get("source")?
+            .as_usize()?;
+        let uri = array(self.json, "images")
+            .get(source)?
+            .get("uri")?
+            .as_str()?;
+        if uri.starts_with("data:") {
+            return None;
+        }
+        Some(
+            self.base
+                .map_or_else(|| PathBuf::from(uri), |base| base.join(uri)),
+        )
+    }
+
+    fn material(&self, index: usize, json: &Json) -> Material {
+        let name = json
+            .get("name")
+            .and_then(Json::as_str)
+            .map_or_else(|| format!("material{}", index), String::from);
+        let mut pbr = Pbr::default();
+
+        if let Some(params) = json.get("pbrMetallicRoughness") {
+            if let Some([r, g, b, a]) = params.get("baseColorFactor").and_then(Json::as_f32_array) {
+                pbr.base_color = Vec4::new(r, g, b, a);
+            }
+            if let Some(metallic) = params.get("metallicFactor").and_then(Json::as_f32) {
+                pbr.metallic = metallic;
+            }
+            if let Some(roughness) = params.get("roughnessFactor").and_then(Json::as_f32) {
+                pbr.roughness = roughness;
+            }
+            pbr.base_color_map = self.texture_path(params.get("baseColorTexture"));
+            pbr.metallic_roughness_map = self.texture_path(params.get("metallicRoughnessTexture"));
+        }
+        if let Some([r, g, b]) = json.get("emissiveFactor").and_then(Json::as_f32_array) {
+            pbr.emissive = Vec3::new(r, g, b);
+        }
+        pbr.normal_map = self.texture_path(json.get("normalTexture"));
+        pbr.occlusion_map = self.texture_path(json.get("occlusionTexture"));
+        pbr.emissive_map = self.texture_path(json.get("emissiveTexture"));
+
+        // Fill in the classic parameters so MTL-style shading still works
+        let mut material = Material::new(&name);
+        let c = pbr.base_color;
+        material.diffuse = Vec3::new(c.x, c.y, c.z);
+        material.dissolve = c.w;
+        material.diffuse_map = pbr.base_color_map.clone();
+        material.bump_map = pbr.normal_map.clone();
+        material.pbr = Some(pbr);
+        material
+    }
+
+    fn import(&self) -> Result<ObjModel, GltfError> {
+        let mut model = ObjModel::new();
+        for (i, material) in array(self.json, "materials").iter().enumerate() {
+            model.materials.push(self.material(i, material));
+        }
+
+        let nodes = array(self.json, "nodes");
+        let scene = self.json.get("scene").and_then(Json::as_usize).unwrap_or(0);
+        let roots: Vec<usize> = match array(self.json, "scenes").get(scene) {
+            Some(scene) => array(scene, "nodes")
+                .iter()
+                .filter_map(Json::as_usize)
+                .collect(),
+            // Without scenes, render every node that is not a child
+            None => (0..nodes.len())
+                .filter(|&i| {
+                    !nodes
+                        .iter()
+                        .any(|n| array(n, "children").iter().any(|c| c.as_usize() == Some(i)))
+                })
+                .collect(),
+        };
+
+        let mut visited = vec![false; nodes.len()];
+        for root in roots {
+            self.node(&mut model, root, &Mat44::ident(), &mut visited)?;
+        }
+
+        // Models without colors on some primitives get white there
+        if !model.colors.is_empty() {
+            model
+                .colors
+                .resize(model.vertices.len(), Vec4::new(1.0, 1.0, 1.0, 1.0));
+        }
+        Ok(model)
+    }
+
+    /// Imports node `index` and its children under the `parent` transform.
+    fn node(
+        &self,
+        model: &mut ObjModel,
+        index: usize,
+        parent: &Mat44,
+        visited: &mut [bool],
+    ) -> Result<(), GltfError> {
+        let node = self.item("nodes", index)?;
+        if std::mem::replace(&mut visited[index], true) {
+            return Err(GltfError::Invalid("node hierarchy is not a tree"));
+        }
+
+        let local = match node.get("matrix").and_then(Json::as_f32_array::<16>) {
+            // Column-major
+            Some(m) => Mat44::new([
+                [m[0], m[4], m[8], m[12]],
+                [m[1], m[5], m[9], m[13]],
+                [m[2], m[6], m[10], m[14]],
+                [m[3], m[7], m[11], m[15]],
+            ]),
+            None => {
+                let [tx, ty, tz] = node
+                    .get("translation")
+                    .and_then(Json::as_f32_array)
+                    .unwrap_or([0.0; 3]);
+                let [x, y, z, w] = node
+                    .get("rotation")
+                    .and_then(Json::as_f32_array)
+                    .unwrap_or([0.0, 0.0, 0.0, 1.0]);
+                let [sx, sy, sz] = node
+                    .get("scale")
+                    .and_then(Json::as_f32_array)
+                    .unwrap_or([1.0; 3]);
+                Mat44::trans(&Vec3::new(tx, ty, tz))
+                    * Mat44::from(Quat::new(x, y, z, w).norm())
+                    * Mat44::scale(&Vec3::new(sx, sy, sz))
+            }
+        };
+        let world = *parent * local;
+
+        if let Some(mesh) = node.get("mesh").and_then(Json::as_usize) {
+            let mesh = self.item("meshes", mesh)?;
+            let start = model.faces.len();
+            for primitive in array(mesh, "primitives") {
+                self.primitive(model, primitive, &world)?;
+            }
+
+            let name = node
+                .get("name")
+                .or_else(|| mesh.get("name"))
+                .and_then(Json::as_str)
+                .map_or_else(|| format!("node{}", index), String::from);
+            model.objects.push(Group {
+                name,
+                faces: start..model.faces.len(),
+            });
+        }
+
+        for child in array(node, "children") {
+            let child = child.as_usize().ok_or(GltfError::Invalid("child index"))?;
+            self.node(model, child, &world, visited)?;
+        }
+        Ok(())
+    }
+
+    fn primitive(
+        &self,
+        model: &mut ObjModel,
+        primitive: &Json,
+        world: &Mat44,
+    ) -> Result<(), GltfError> {
+        let mode = primitive
+            .get("mode")
+            .and_then(Json::as_usize)
+            .unwrap_or(TRIANGLES);
+        if !matches!(mode, TRIANGLES | TRIANGLE_STRIP | TRIANGLE_FAN) {
+            // Points and lines have no faces to draw
+            return Ok(());
+        }
+
+        let attributes = primitive.get("attributes");
+        let attribute = |name: &str| {
+            attributes
+                .and_then(|a| a.get(name))
+                .and_then(Json::as_usize)
+        };
+        let position =
+            attribute("POSITION").ok_or(GltfError::Invalid("primitive without POSITION"))?;
+
+        let (positions, _) = self.accessor(position)?;
+        let count = positions.len() / 3;
+        let base = model.vertices.len();
+        for p in positions.chunks_exact(3) {
+            let p = Vec3::new(p[0] as f32, p[1] as f32, p[2] as f32);
+            model.vertices.push(world.transform_point(&p));
+        }
+
+        let read = |name: &str, components: usize| -> Result<Option<Vec<f64>>, GltfError> {
+            let Some(index) = attribute(name) else {
+                return Ok(None);
+            };
+            let (values, c) = self.accessor(index)?;
+            if values.len() / c != count || c < components {
+                return Err(GltfError::Invalid("attribute count"));
+            }
+            Ok(Some(
+                values
+                    .chunks_exact(c)
+                    .flat_map(|v| v[..components].to_vec())
+                    .collect(),
+            ))
+        };
+
+        let normal_base = model.normals.len();
+        let normals = read("NORMAL", 3)?;
+        if let Some(normals) = &normals {
+            let normal_matrix = world.normal_matrix().unwrap_or(Mat44::ident());
+            for n in normals.chunks_exact(3) {
+                let n = normal_matrix.transform_vector(&Vec3::new(
+                    n[0] as f32,
+                    n[1] as f32,
+                    n[2] as f32,
+                ));
+                model
+                    .normals
+                    .push(if n.len_sqd() > 0.0 { n.norm() } else { n });
+            }
+        }
+
+        let tex_coord_base = model.tex_coords.len();
+        let tex_coords = read("TEXCOORD_0", 2)?;
+        if let Some(tex_coords) = &tex_coords {
+            // glTF puts v = 0 at the top of the image
+            for t in tex_coords.chunks_exact(2) {
+                model
+                    .tex_coords
+                    .push(Vec3::new(t[0] as f32, 1.0 - t[1] as f32, 0.0));
+            }
+        }
+
+        if let Some(index) = attribute("COLOR_0") {
+            let (colors, c) = self.accessor(index)?;
+            if colors.len() / c != count {
+                return Err(GltfError::Invalid("attribute count"));
+            }
+            model.colors.resize(base, Vec4::new(1.0, 1.0, 1.0, 1.0));
+            for v in colors.chunks_exact(c) {
+                let a = if c >= 4 { v[3] as f32 } else { 1.0 };
+                model
+                    .colors
+                    .push(Vec4::new(v[0] as f32, v[1] as f32, v[2] as f32, a));
+            }
+        }
+
+        let indices: Vec<usize> = match primitive.get("indices").and_then(Json::as_usize) {
+            Some(index) => self
+                .accessor(index)?
+                .0
+                .iter()
+                .map(|&i| i as usize)
+                .collect(),
+            None => (0..count).collect(),
+        };
+        if indices.iter().any(|&i| i >= count) {
+            return Err(GltfError::Invalid("vertex index out of range"));
+        }
+
+        let triangles: Vec<[usize; 3]> = match mode {
+            TRIANGLES => indices
+                .chunks_exact(3)
+                .map(|t| [t[0], t[1], t[2]])
+                .collect(),
+            // Every other strip triangle is flipped to keep the winding
+            TRIANGLE_STRIP => indices
+                .windows(3)
+                .enumerate()
+                .map(|(i, t)| {
+                    if i % 2 == 0 {
+                        [t[0], t[1], t[2]]
+                    } else {
+                        [t[1], t[0], t[2]]
+                    }
+                })
+                .collect(),
+            _ => (1..indices.len().saturating_sub(1))
+                .map(|i| [indices[0], indices[i], indices[i + 1]])
+                .collect(),
+        };
+
+        // Mirroring transforms turn the winding inside out
+        let mirrored = world.determinant() < 0.0;
+        let material = primitive
+            .get("material")
+            .and_then(Json::as_usize)
+            .filter(|&m| m < model.materials.len());
+        for mut triangle in triangles {
+            if mirrored {
+                triangle.swap(1, 2);
+            }
+            let vertices = triangle
+                .iter()
+                .map(|&i| Vertex {
+                    position: base + i,
+                    tex_coord: tex_coords.is_some().then_some(tex_coord_base + i),
+                    normal: normals.is_some().then_some(normal_base + i),
+                })
+                .collect();
+            model.faces.push(Face {
+                vertices,
+                material,
+                smoothing_group: None,
+            });
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::gltf::{self, GltfError};
+    use crate::vec3::Vec3;
+    use crate::vec4::Vec4;
+
+    fn assert_close(a: Vec3, b: Vec3) {
+        assert!((a - b).len() < 1e-5, "{:?} != {:?}", a, b);
+    }
+
+    fn encode_base64(data: &[u8]) -> String {
+        let table = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+        let mut out = String::new();
+        for chunk in data.chunks(3) {
+            let b = [
+                chunk[0],
+                *chunk.get(1).unwrap_or(&0),
+                *chunk.get(2).unwrap_or(&0),
+            ];
+            let n = (b[0] as u32) << 16 | (b[1] as u32) << 8 | b[2] as u32;
+            for i in 0..4 {
+                if i <= chunk.len() {
+                    out.push(table[(n >> (18 - 6 * i) & 63) as usize] as char);
+                } else {
+                    out.push('=');
+                }
+            }
+        }
+        out
+    }
+
+    // A right triangle: positions, normals, UVs, u16 indices and u8 colors
+    fn buffer() -> Vec<u8> {
+        let mut data = Vec::new();
+        for f in [0.0f32, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0] {
+            data.extend_from_slice(&f.to_le_bytes());
+        }
+        for _ in 0..3 {
+            for f in [0.0f32, 0.0, 1.0] {
+                data.extend_from_slice(&f.to_le_bytes());
+            }
+        }
+        for f in [0.0f32, 0.0, 1.0, 0.0, 0.0, 0.25] {
+            data.extend_from_slice(&f.to_le_bytes());
+        }
+        for i in [0u16, 1, 2, 0] {
+            data.extend_from_slice(&i.to_le_bytes());
+        }
+        data.extend_from_slice(&[255, 0, 0, 255, 0, 255, 0, 255, 0, 0, 255, 255]);
+        data
+    }
+
+    fn document(uri: Option<&str>) -> String {
+        let uri = uri.map_or(String::new(), |uri| format!(r#""uri": "{}","#, uri));
+        format!(
+            r#"{{
+                "asset": {{"version": "2.0"}},
+                "scene": 0,
+                "scenes": [{{"nodes": [0, 2]}}],
+                "nodes": [
+                    {{"name": "parent", "translation": [10, 0, 0], "children": [1]}},
+                    {{"name": "child", "mesh": 0, "scale": [2, 2, 2],
+                      "rotation": [0, 0, 0.7071068, 0.7071068]}},
+                    {{"mesh": 0, "matrix": [-1,0,0,0, 0,1,0,0, 0,0,1,0, 0,0,5,1]}}
+                ],
+                "meshes": [{{"name": "tri", "primitives": [{{
+                    "attributes": {{"POSITION": 0, "NORMAL": 1, "TEXCOORD_0": 2, "COLOR_0": 4}},
+                    "indices": 3, "material": 0
+                }}]}}],
+                "materials": [{{
+                    "name": "paint",
+                    "pbrMetallicRoughness": {{
+                        "baseColorFactor": [0.5, 0.25, 1, 0.75],
+                        "metallicFactor": 0.1,
+                        "baseColorTexture": {{"index": 0}}
+                    }},
+                    "emissiveFactor": [1, 0, 0]
+                }}],
+                "textures": [{{"source": 0}}],
+                "images": [{{"uri": "paint.png"}}],
+                "buffers": [{{{} "byteLength": 116}}],
+                "bufferViews": [
+                    {{"buffer": 0, "byteOffset": 0, "byteLength": 72}},
+                    {{"buffer": 0, "byteOffset": 72, "byteLength": 24}},
+                    {{"buffer": 0, "byteOffset": 96, "byteLength": 6}},
+                    {{"buffer": 0, "byteOffset": 104, "byteLength": 12}}
+                ],
+                "accessors": [
+                    {{"bufferView": 0, "componentType": 5126, "count": 3, "type": "VEC3"}},
+                    {{"bufferView": 0, "byteOffset": 36, "componentType": 5126, "count": 3, "type": "VEC3"}},
+                    {{"bufferView": 1, "componentType": 5126, "count": 3, "type": "VEC2"}},
+                    {{"bufferView": 2, "componentType": 5123, "count": 3, "type": "SCALAR"}},
+                    {{"bufferView": 3, "componentType": 5121, "normalized": true, "count": 3, "type": "VEC4"}}
+                ]
+            }}"#,
+            uri
+        )
+    }
+
+    fn check(model: &crate::obj::ObjModel) {
+        assert_eq!(model.vertices.len(), 6);
+        assert_eq!(model.faces.len(), 2);
+        assert_eq!(model.objects[0].name, "child");
+        assert_eq!(model.objects[1].name, "tri");
+
+        // Scaled by 2, turned 90 degrees about z, then moved by the parent
+        let (a, b, c) = model.get_triangle_vertices(&model.faces[0]).unwrap();
+        assert_close(a, Vec3::new(10.0, 0.0, 0.0));
+        assert_close(b, Vec3::new(10.0, 2.0, 0.0));
+        assert_close(c, Vec3::new(8.0, 0.0, 0.0));
+
+        // The mirrored instance has its winding flipped to stay front facing
+        let (a, b, c) = model.get_triangle_vertices(&model.faces[1]).unwrap();
+        assert_close(a, Vec3::new(0.0, 0.0, 5.0));
+        assert_close(b, Vec3::new(0.0, 1.0, 5.0));
+        assert_close(c, Vec3::new(-1.0, 0.0, 5.0));
+        let (n, _, _) = model.get_triangle_normals(&model.faces[1]).unwrap();
+        assert_close(n, Vec3::new(0.0, 0.0, 1.0));
+        assert!((b - a).cross(&(c - a)).z > 0.0);
+
+        let (t0, t1, _) = model.get_triangle_tex_coords(&model.faces[0]).unwrap();
+        assert_eq!(t0, Vec3::new(0.0, 1.0, 0.0));
+        assert_eq!(t1, Vec3::new(1.0, 1.0, 0.0));
+        assert_eq!(model.colors[1], Vec4::new(0.0, 1.0, 0.0, 1.0));
+
+        let material = model.get_triangle_material(&model.faces[1]).unwrap();
+        let pbr = material.pbr.as_ref().unwrap();
+        assert_eq!(material.name, "paint");
+        assert_eq!(material.diffuse, Vec3::new(0.5, 0.25, 1.0));
+        assert_eq!(material.dissolve, 0.75);
+        assert_eq!((pbr.metallic, pbr.roughness), (0.1, 1.0));
+        assert_eq!(pbr.emissive, Vec3::new(1.0, 0.0, 0.0));
+        assert!(pbr.base_color_map.as_ref().unwrap().ends_with("paint.png"));
+    }
+
+    #[test]
+    fn test_embedded_gltf() {
+        let uri = format!(
+            "data:application/octet-stream;base64,{}",
+            encode_base64(&buffer())
+        );
+        let model = gltf::decode(document(Some(&uri)).as_bytes(), None).unwrap();
+        check(&model);
+    }
+
+    #[test]
+    fn test_glb() {
+        let mut json = document(None).into_bytes();
+        json.resize(json.len().next_multiple_of(4), b' ');
+        let mut bin = buffer();
+        bin.resize(bin.len().next_multiple_of(4), 0);
+
+        let mut glb = b"glTF".to_vec();
+        glb.extend_from_slice(&2u32.to_le_bytes());
+        glb.extend_from_slice(&((28 + json.len() + bin.len()) as u32).to_le_bytes());
+        glb.extend_from_slice(&(json.len() as u32).to_le_bytes());
+        glb.extend_from_slice(b"JSON");
+        glb.extend_from_slice(&json);
+        glb.extend_from_slice(&(bin.len() as u32).to_le_bytes());
+        glb.extend_from_slice(b"BIN\0");
+        glb.extend_from_slice(&bin);
+
+        check(&gltf::decode(&glb, None).unwrap());
+
+        glb.truncate(40);
+        assert!(matches!(
+            gltf::decode(&glb, None),
+            Err(GltfError::Invalid(_))
+        ));
+    }
+
+    #[test]
+    fn test_errors() {
+        let err = gltf::decode(b"{\"buffers\": [{\"uri\": \"mesh.bin\"}]}", None).unwrap_err();
+        assert!(matches!(err, GltfError::Invalid(_)));
+        assert!(matches!(gltf::decode(b"{", None), Err(GltfError::Json(_))));
+
+        let uri = format!(
+            "data:application/octet-stream;base64,{}",
+            encode_base64(&buffer())
+        );
+        let src = document(Some(&uri)).replace(
+            r#""count": 3, "type": "SCALAR""#,
+            r#""count": 30, "type": "SCALAR""#,
+        );
+        assert!(matches!(
+            gltf::decode(src.as_bytes(), None),
+            Err(GltfError::Invalid(_))
+        ));
+
+        // Sizes that overflow must fail, not panic or allocate
+        let colors = r#"{"bufferView": 3, "componentType": 5121, "normalized": true, "count": 3"#;
+        let huge = [
+            (colors, r#"{"componentType": 5121, "count": 1e19"#),
+            (colors, r#"{"componentType": 5121, "count": 1e9"#),
+            (
+                r#""byteOffset": 104, "byteLength": 12"#,
+                r#""byteOffset": 1e19, "byteLength": 1e19"#,
+            ),
+            (
+                r#""byteOffset": 36, "componentType": 5126, "count": 3"#,
+                r#""byteOffset": 1e19, "componentType": 5126, "count": 1e18"#,
+            ),
+        ];
+        for (from, to) in huge {
+            let src = document(Some(&uri));
+            assert!(src.contains(from));
+            let src = src.replace(from, to);
+            assert!(gltf::decode(src.as_bytes(), None).is_err());
+        }
+    }
+}
@@ -1,28 +1,153 @@
+use crate::clip;
+use crate::codec::{self, bmp, png, ppm, tga, Bitmap, DecodeError, ImageFormat};
 use crate::color::Color;
+use crate::varying::Varying;
 use crate::vec3::Vec3;
-use std::cmp::{max, min};
+use crate::vec4::Vec4;
 use std::fmt;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+/// Comparison applied by the depth test.
+///
+/// A fragment passes when `func.test(fragment_z, stored_z)` holds. Depth values
+/// follow `Mat44::persp`, so smaller values are closer to the camera.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub enum DepthFunc {
+    #[default]
+    Less,
+    LessEqual,
+    Always,
+    Never,
+}
+
+impl DepthFunc {
+    pub fn test(&self, z: f32, stored: f32) -> bool {
+        match self {
+            DepthFunc::Less => z < stored,
+            DepthFunc::LessEqual => z <= stored,
+            DepthFunc::Always => true,
+            DepthFunc::Never => false,
+        }
+    }
+}
+
+/// A triangle corner ready for rasterization, with `pos` in homogeneous clip
+/// space (before the perspective divide).
+#[derive(Debug, Copy, Clone)]
+pub struct RasterVertex<V> {
+    pub pos: Vec4,
+    pub varying: V,
+}
 
-pub struct Image(Vec<Vec<Color>>);
+impl<V> RasterVertex<V> {
+    pub fn new(pos: Vec4, varying: V) -> Self {
+        Self { pos, varying }
+    }
+}
+
+/// A covered pixel handed to the fragment shader.
+///
+/// `bary` holds the perspective-correct barycentric weights of the three
+/// corners and `varying` the attributes interpolated with them. `ddx` and
+/// `ddy` are the screen-space derivatives of `varying` (the change towards the
+/// next pixel right and down), used for texture level-of-detail selection.
+#[derive(Debug, Copy, Clone)]
+pub struct Fragment<V> {
+    pub x: i32,
+    pub y: i32,
+    pub z: f32,
+    pub bary: Vec3,
+    pub varying: V,
+    pub ddx: V,
+    pub ddy: V,
+}
+
+pub struct Image {
+    pixels: Vec<Vec<Color>>,
+    depth: Vec<Vec<f32>>,
+    depth_func: DepthFunc,
+    depth_write: bool,
+}
 
 impl Image {
     pub fn new(w: usize, h: usize) -> Self {
         let v = vec![Color::new(0, 0, 0); w];
-        Self(vec![v.clone(); h])
+        let d = vec![f32::INFINITY; w];
+        Self {
+            pixels: vec![v; h],
+            depth: vec![d; h],
+            depth_func: DepthFunc::default(),
+            depth_write: true,
+        }
     }
 
     pub fn h(&self) -> i32 {
-        self.0.len() as i32
+        self.pixels.len() as i32
     }
 
     pub fn w(&self) -> i32 {
-        self.0[0].len() as i32
+        self.pixels[0].len() as i32
     }
 
     pub fn aspect(&self) -> f32 {
         self.w() as f32 / self.h() as f32
     }
 
+    pub fn row(&self, y: i32) -> &[Color] {
+        &self.pixels[y as usize]
+    }
+
+    /// Decodes an image file. Alpha is dropped; use `codec::load` to keep it.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, DecodeError> {
+        Ok(Self::from(&codec::load(path)?))
+    }
+
+    /// Writes the image to `path`, choosing the encoder from the extension.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let format = ImageFormat::from_path(&path).ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidInput, "unsupported image extension")
+        })?;
+
+        self.write_to(BufWriter::new(File::create(path)?), format)
+    }
+
+    pub fn write_to<W: Write>(&self, mut w: W, format: ImageFormat) -> io::Result<()> {
+        match format {
+            ImageFormat::Ppm => ppm::encode(self, &mut w)?,
+            ImageFormat::Tga => tga::encode(self, &mut w)?,
+            ImageFormat::Bmp => bmp::encode(self, &mut w)?,
+            ImageFormat::Png => png::encode(self, &mut w)?,
+        }
+
+        w.flush()
+    }
+
+    pub fn set_depth_func(&mut self, func: DepthFunc) {
+        self.depth_func = func;
+    }
+
+    pub fn set_depth_write(&mut self, enabled: bool) {
+        self.depth_write = enabled;
+    }
+
+    /// Resets every depth sample to infinity, so the next fragment always passes
+    /// `Less` and `LessEqual`.
+    pub fn clear_depth(&mut self) {
+        for row in &mut self.depth {
+            row.fill(f32::INFINITY);
+        }
+    }
+
+    pub fn depth(&self, x: i32, y: i32) -> Option<f32> {
+        if x < 0 || x >= self.w() || y < 0 || y >= self.h() {
+            return None;
+        }
+
+        Some(self.depth[y as usize][x as usize])
+    }
+
     pub fn draw_point(&mut self, x: i32, y: i32, color: &Color) {
         if x < 0 || x >= self.w() {
             return;
@@ -32,35 +157,21 @@ impl Image {
             return;
         }
 
-        self.0[y as usize][x as usize] = Color {
-            r: color.r,
-            g: color.g,
-            b: color.b,
-        };
+        self.pixels[y as usize][x as usize] = *color;
     }
 
     pub fn draw_line(&mut self, x0: i32, y0: i32, x1: i32, y1: i32, color: &Color) {
-        let mut dx = x1 as i32 - x0 as i32;
-        let mut dy = y1 as i32 - y0 as i32;
+        Image::trace_line(x0, y0, x1, y1, |x, y, _| self.draw_point(x, y, color));
+    }
 
-        let sign_x = if dx > 0 {
-            1
-        } else {
-            if dx < 0 {
-                -1
-            } else {
-                0
-            }
-        };
-        let sign_y = if dy > 0 {
-            1
-        } else {
-            if dy < 0 {
-                -1
-            } else {
-                0
-            }
-        };
+    /// Walks the pixels of a line, passing each one along with how far it is
+    /// from the start, from 0 to 1.
+    fn trace_line<F: FnMut(i32, i32, f32)>(x0: i32, y0: i32, x1: i32, y1: i32, mut f: F) {
+        let mut dx = x1 - x0;
+        let mut dy = y1 - y0;
+
+        let sign_x = dx.signum();
+        let sign_y = dy.signum();
 
         if dx < 0 {
             dx = -dx;
@@ -87,10 +198,10 @@ impl Image {
             el = dy;
         }
 
-        let (mut x, mut y) = (x0 as i32, y0 as i32);
+        let (mut x, mut y) = (x0, y0);
         let (mut e, mut t) = (el / 2, 0);
 
-        self.draw_point(x, y, color);
+        f(x, y, 0.0);
 
         while t < el {
             e -= es;
@@ -104,79 +215,212 @@ impl Image {
             }
             t += 1;
 
-            self.draw_point(x, y, color);
+            f(x, y, t as f32 / el as f32);
         }
     }
 
-    fn x_coord(&self, x: f32) -> i32 {
-        ((x + 1.0) * 0.5 * self.w() as f32) as i32
+    fn x_coord(&self, x: f32) -> f32 {
+        (x + 1.0) * 0.5 * self.w() as f32
     }
 
-    fn y_coord(&self, y: f32) -> i32 {
-        ((1.0 - (y + 1.0) * 0.5) * self.h() as f32) as i32
+    fn y_coord(&self, y: f32) -> f32 {
+        (1.0 - (y + 1.0) * 0.5) * self.h() as f32
     }
 
-    fn is_inside_triangle(
-        x0: i32,
-        y0: i32,
-        x1: i32,
-        y1: i32,
-        x2: i32,
-        y2: i32,
-        x3: i32,
-        y3: i32,
-    ) -> bool {
-        let a = (x1 - x0) * (y2 - y1) - (x2 - x1) * (y1 - y0);
-        let b = (x2 - x0) * (y3 - y2) - (x3 - x2) * (y2 - y0);
-        let c = (x3 - x0) * (y1 - y3) - (x1 - x3) * (y3 - y0);
+    fn edge(x0: f32, y0: f32, x1: f32, y1: f32, x: f32, y: f32) -> f32 {
+        (x1 - x0) * (y - y0) - (y1 - y0) * (x - x0)
+    }
 
-        return a.signum() == b.signum() && a.signum() == c.signum();
+    fn is_back_facing(a: Vec3, b: Vec3, c: Vec3, dir: &Vec3) -> bool {
+        let e0 = b - a;
+        let e1 = c - a;
+        let normal = e0.cross(&e1).norm();
+
+        normal.dot(dir) < 0.0
     }
 
-    pub fn draw_triangle(&mut self, a: &Vec3, b: &Vec3, c: &Vec3, dir: &Vec3, color: &Color) {
-        if a.x < -1.0 || a.x > 1.0 || a.y < -1.0 || a.y > 1.0 {
-            return;
-        }
+    /// Draws a depth-tested line between two NDC points, interpolating their
+    /// depth along the way.
+    fn draw_edge(&mut self, a: Vec3, b: Vec3, color: &Color) {
+        let (x0, y0) = (self.x_coord(a.x) as i32, self.y_coord(a.y) as i32);
+        let (x1, y1) = (self.x_coord(b.x) as i32, self.y_coord(b.y) as i32);
+
+        Image::trace_line(x0, y0, x1, y1, |x, y, t| {
+            let z = a.z + (b.z - a.z) * t;
+            let Some(stored) = self.depth(x, y) else {
+                return;
+            };
+            if !self.depth_func.test(z, stored) {
+                return;
+            }
+
+            if self.depth_write {
+                self.depth[y as usize][x as usize] = z;
+            }
+
+            self.draw_point(x, y, color);
+        });
+    }
+
+    pub fn draw_triangle(&mut self, a: &Vec4, b: &Vec4, c: &Vec4, dir: &Vec3, color: &Color) {
+        let a = RasterVertex::new(*a, ());
+        let b = RasterVertex::new(*b, ());
+        let c = RasterVertex::new(*c, ());
 
-        if b.x < -1.0 || b.x > 1.0 || b.y < -1.0 || b.y > 1.0 {
+        self.draw_triangle_with(&a, &b, &c, dir, |_| Some(*color));
+
+        // Outline the visible part of the triangle too
+        let polygon: Vec<Vec3> = clip::clip_polygon(&[a, b, c])
+            .iter()
+            .map(|v| v.pos.to_ndc())
+            .collect();
+        if polygon.len() < 3 || Image::is_back_facing(polygon[0], polygon[1], polygon[2], dir) {
             return;
         }
+        for (i, &p) in polygon.iter().enumerate() {
+            self.draw_edge(p, polygon[(i + 1) % polygon.len()], color);
+        }
+    }
 
-        if c.x < -1.0 || c.x > 1.0 || c.y < -1.0 || c.y > 1.0 {
-            return;
+    /// Clips a clip-space triangle against the view frustum, rasterizes what
+    /// is left and calls `shader` for every covered pixel that passes the
+    /// depth test. Returning `None` from the shader discards the fragment
+    /// without touching the color or depth buffers.
+    pub fn draw_triangle_with<V, F>(
+        &mut self,
+        a: &RasterVertex<V>,
+        b: &RasterVertex<V>,
+        c: &RasterVertex<V>,
+        dir: &Vec3,
+        mut shader: F,
+    ) where
+        V: Varying,
+        F: FnMut(&Fragment<V>) -> Option<Color>,
+    {
+        let polygon = clip::clip_polygon(&[*a, *b, *c]);
+
+        // The clipped polygon is convex, so a fan covers it
+        for i in 1..polygon.len().saturating_sub(1) {
+            self.fill_triangle(&polygon[0], &polygon[i], &polygon[i + 1], dir, &mut shader);
         }
+    }
+
+    fn fill_triangle<V, F>(
+        &mut self,
+        a: &RasterVertex<V>,
+        b: &RasterVertex<V>,
+        c: &RasterVertex<V>,
+        dir: &Vec3,
+        shader: &mut F,
+    ) where
+        V: Varying,
+        F: FnMut(&Fragment<V>) -> Option<Color>,
+    {
+        let (pa, pb, pc) = (a.pos.to_ndc(), b.pos.to_ndc(), c.pos.to_ndc());
 
         // Back-face culling
 
-        let e0 = b - a;
-        let e1 = c - a;
-        let normal = e0.cross(&e1).norm();
+        if Image::is_back_facing(pa, pb, pc, dir) {
+            return;
+        }
 
-        if normal.dot(&dir) < 0.0 {
+        let (ax, ay) = (self.x_coord(pa.x), self.y_coord(pa.y));
+        let (bx, by) = (self.x_coord(pb.x), self.y_coord(pb.y));
+        let (cx, cy) = (self.x_coord(pc.x), self.y_coord(pc.y));
+
+        let area = Image::edge(ax, ay, bx, by, cx, cy);
+        if area == 0.0 {
             return;
         }
 
-        let (ax, ay) = (self.x_coord(a.x), self.y_coord(a.y));
-        let (bx, by) = (self.x_coord(b.x), self.y_coord(b.y));
-        let (cx, cy) = (self.x_coord(c.x), self.y_coord(c.y));
+        let min_x = (ax.min(bx).min(cx).floor() as i32).max(0);
+        let max_x = (ax.max(bx).max(cx).ceil() as i32).min(self.w() - 1);
+
+        let min_y = (ay.min(by).min(cy).floor() as i32).max(0);
+        let max_y = (ay.max(by).max(cy).ceil() as i32).min(self.h() - 1);
+
+        let screen_bary = |px: f32, py: f32| {
+            Vec3::new(
+                Image::edge(bx, by, cx, cy, px, py) / area,
+                Image::edge(cx, cy, ax, ay, px, py) / area,
+                Image::edge(ax, ay, bx, by, px, py) / area,
+            )
+        };
+
+        // Everything but depth is affine in clip space: interpolate a/w and
+        // 1/w, then divide
+        let perspective_bary = |w: Vec3| {
+            let bary = Vec3::new(w.x / a.pos.w, w.y / b.pos.w, w.z / c.pos.w);
+            bary / (bary.x + bary.y + bary.z)
+        };
 
-        let min_x = min(min(ax, bx), cx);
-        let max_x = max(max(ax, bx), cx);
+        for y in min_y..=max_y {
+            for x in min_x..=max_x {
+                // Sample at the pixel center
+                let (px, py) = (x as f32 + 0.5, y as f32 + 0.5);
 
-        let min_y = min(min(ay, by), cy);
-        let max_y = max(max(ay, by), cy);
+                let Vec3 {
+                    x: w0,
+                    y: w1,
+                    z: w2,
+                } = screen_bary(px, py);
 
-        for x in min_x..max_x {
-            for y in min_y..max_y {
-                if Image::is_inside_triangle(x, y, ax, ay, bx, by, cx, cy) {
-                    self.draw_point(x, y, color);
+                if w0 < 0.0 || w1 < 0.0 || w2 < 0.0 {
+                    continue;
+                }
+
+                // NDC depth is affine in screen space, so plain barycentric
+                // weights interpolate it exactly
+                let z = w0 * pa.z + w1 * pb.z + w2 * pc.z;
+
+                if !self.depth_func.test(z, self.depth[y as usize][x as usize]) {
+                    continue;
+                }
+
+                let bary = perspective_bary(Vec3::new(w0, w1, w2));
+                let varying = V::weighted(&a.varying, &b.varying, &c.varying, &bary);
+
+                // Varyings are linear in the weights, so the change in weights
+                // towards the neighbouring pixels gives the derivatives
+                let bary_dx = perspective_bary(screen_bary(px + 1.0, py)) - bary;
+                let bary_dy = perspective_bary(screen_bary(px, py + 1.0)) - bary;
+
+                let fragment = Fragment {
+                    x,
+                    y,
+                    z,
+                    bary,
+                    varying,
+                    ddx: V::weighted(&a.varying, &b.varying, &c.varying, &bary_dx),
+                    ddy: V::weighted(&a.varying, &b.varying, &c.varying, &bary_dy),
+                };
+
+                if let Some(color) = shader(&fragment) {
+                    if self.depth_write {
+                        self.depth[y as usize][x as usize] = z;
+                    }
+
+                    self.draw_point(x, y, &color);
                 }
             }
         }
+    }
+}
 
-        self.draw_line(ax, ay, bx, by, color);
-        self.draw_line(bx, by, cx, cy, color);
-        self.draw_line(cx, cy, ax, ay, color);
+impl From<&Bitmap> for Image {
+    fn from(bitmap: &Bitmap) -> Self {
+        let mut image = Image::new(bitmap.width, bitmap.height);
+        // An empty bitmap has no rows, but `chunks` can't take a zero width
+        for (row, pixels) in image
+            .pixels
+            .iter_mut()
+            .zip(bitmap.pixels.chunks(bitmap.width.max(1)))
+        {
+            for (color, rgba) in row.iter_mut().zip(pixels) {
+                *color = Color::new(rgba[0], rgba[1], rgba[2]);
+            }
+        }
+        image
     }
 }
 
@@ -184,7 +428,7 @@ impl fmt::Display for Image {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         writeln!(f, "P3\n{} {}\n{}", self.w(), self.h(), u8::MAX)?;
 
-        for column in &self.0 {
+        for column in &self.pixels {
             for color in column {
                 write!(f, "{} ", color)?;
             }
@@ -194,3 +438,167 @@ impl fmt::Display for Image {
         writeln!(f)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::color::Color;
+    use crate::image::{DepthFunc, Image, RasterVertex};
+    use crate::texture::{Filter, Sampler, Texture, Wrap};
+    use crate::vec3::Vec3;
+    use crate::vec4::Vec4;
+
+    fn draw_quad(image: &mut Image, z: f32, color: &Color) {
+        let dir = Vec3::new(0.0, 0.0, 1.0);
+        let a = Vec4::new(-1.0, -1.0, z, 1.0);
+        let b = Vec4::new(1.0, -1.0, z, 1.0);
+        let c = Vec4::new(1.0, 1.0, z, 1.0);
+        let d = Vec4::new(-1.0, 1.0, z, 1.0);
+        image.draw_triangle(&a, &b, &c, &dir, color);
+        image.draw_triangle(&a, &c, &d, &dir, color);
+    }
+
+    #[test]
+    fn test_depth_less_keeps_nearest() {
+        let red = Color::new(255, 0, 0);
+        let green = Color::new(0, 255, 0);
+
+        let mut image = Image::new(4, 4);
+        draw_quad(&mut image, 0.2, &red);
+        draw_quad(&mut image, 0.5, &green);
+        assert_eq!(image.pixels[1][1], red);
+        assert_eq!(image.depth(1, 1), Some(0.2));
+
+        let mut image = Image::new(4, 4);
+        draw_quad(&mut image, 0.5, &green);
+        draw_quad(&mut image, 0.2, &red);
+        assert_eq!(image.pixels[1][1], red);
+    }
+
+    #[test]
+    fn test_depth_func_never_and_always() {
+        let red = Color::new(255, 0, 0);
+        let green = Color::new(0, 255, 0);
+
+        let mut image = Image::new(4, 4);
+        image.set_depth_func(DepthFunc::Never);
+        draw_quad(&mut image, 0.2, &red);
+        assert_eq!(image.pixels[2][2], Color::new(0, 0, 0));
+
+        image.set_depth_func(DepthFunc::Always);
+        draw_quad(&mut image, 0.2, &red);
+        draw_quad(&mut image, 0.5, &green);
+        assert_eq!(image.pixels[2][2], green);
+        assert_eq!(image.depth(2, 2), Some(0.5));
+    }
+
+    #[test]
+    fn test_depth_write_disabled() {
+        let red = Color::new(255, 0, 0);
+        let green = Color::new(0, 255, 0);
+
+        let mut image = Image::new(4, 4);
+        image.set_depth_write(false);
+        draw_quad(&mut image, 0.5, &green);
+        draw_quad(&mut image, 0.7, &red);
+        assert_eq!(image.pixels[0][3], red);
+        assert_eq!(image.depth(0, 3), Some(f32::INFINITY));
+
+        image.set_depth_write(true);
+        image.set_depth_func(DepthFunc::LessEqual);
+        draw_quad(&mut image, 0.5, &green);
+        draw_quad(&mut image, 0.5, &red);
+        assert_eq!(image.pixels[0][3], red);
+    }
+
+    #[test]
+    fn test_perspective_correct_interpolation() {
+        let dir = Vec3::new(0.0, 0.0, 1.0);
+        let a = RasterVertex::new(Vec4::new(-1.0, -1.0, 0.5, 1.0), 0.0_f32);
+        let b = RasterVertex::new(Vec4::new(3.0, -3.0, 1.5, 3.0), 1.0_f32);
+        let c = RasterVertex::new(Vec4::new(-1.0, 1.0, 0.5, 1.0), 0.0_f32);
+
+        let mut image = Image::new(8, 8);
+        let mut value = None;
+        image.draw_triangle_with(&a, &b, &c, &dir, |frag| {
+            if (frag.x, frag.y) == (3, 7) {
+                value = Some(frag.varying);
+            }
+            Some(Color::new(255, 255, 255))
+        });
+
+        // Pixel (3, 7) has screen-space weights (0.5, 0.4375, 0.0625)
+        let (w0, w1, w2) = (0.5, 0.4375 / 3.0, 0.0625);
+        let expected = w1 / (w0 + w1 + w2);
+        assert!((value.unwrap() - expected).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_discarded_fragment_keeps_depth() {
+        let dir = Vec3::new(0.0, 0.0, 1.0);
+        let a = RasterVertex::new(Vec4::new(-1.0, -1.0, 0.5, 1.0), ());
+        let b = RasterVertex::new(Vec4::new(1.0, -1.0, 0.5, 1.0), ());
+        let c = RasterVertex::new(Vec4::new(-1.0, 1.0, 0.5, 1.0), ());
+
+        let mut image = Image::new(4, 4);
+        image.draw_triangle_with(&a, &b, &c, &dir, |_| None);
+        assert_eq!(image.depth(0, 3), Some(f32::INFINITY));
+        assert_eq!(image.pixels[3][0], Color::new(0, 0, 0));
+    }
+
+    #[test]
+    fn test_triangle_crossing_edge_is_clipped() {
+        let dir = Vec3::new(0.0, 0.0, 1.0);
+        let a = Vec4::new(-1.5, -3.0, 0.5, 1.0);
+        let b = Vec4::new(1.5, -3.0, 0.5, 1.0);
+        let c = Vec4::new(0.0, 3.0, 0.5, 1.0);
+        let white = Color::new(255, 255, 255);
+
+        let mut image = Image::new(4, 4);
+        image.draw_triangle(&a, &b, &c, &dir, &white);
+        assert_eq!(image.pixels[3][0], white);
+        assert_eq!(image.pixels[3][3], white);
+        assert_eq!(image.pixels[0][0], Color::new(0, 0, 0));
+    }
+
+    #[test]
+    fn test_textured_triangle() {
+        let dir = Vec3::new(0.0, 0.0, 1.0);
+        let red = Vec4::new(1.0, 0.0, 0.0, 1.0);
+        let blue = Vec4::new(0.0, 0.0, 1.0, 1.0);
+        let texture = Texture::new(2, 1, vec![red, blue]);
+        let sampler = Sampler::new(Filter::Nearest, Wrap::ClampToEdge);
+
+        let a = RasterVertex::new(Vec4::new(-1.0, -1.0, 0.5, 1.0), Vec3::new(0.0, 0.0, 0.0));
+        let b = RasterVertex::new(Vec4::new(1.0, -1.0, 0.5, 1.0), Vec3::new(1.0, 0.0, 0.0));
+        let c = RasterVertex::new(Vec4::new(-1.0, 1.0, 0.5, 1.0), Vec3::new(0.0, 1.0, 0.0));
+
+        let mut image = Image::new(4, 4);
+        image.draw_triangle_with(&a, &b, &c, &dir, |frag| {
+            let uv = frag.varying;
+            Some(Color::from(sampler.sample(&texture, uv.x, uv.y).xyz()))
+        });
+        assert_eq!(image.pixels[3][0], Color::new(255, 0, 0));
+        assert_eq!(image.pixels[3][2], Color::new(0, 0, 255));
+    }
+
+    #[test]
+    fn test_derivatives() {
+        let dir = Vec3::new(0.0, 0.0, 1.0);
+        let a = RasterVertex::new(Vec4::new(-1.0, -1.0, 0.5, 1.0), Vec3::new(0.0, 0.0, 0.0));
+        let b = RasterVertex::new(Vec4::new(1.0, -1.0, 0.5, 1.0), Vec3::new(1.0, 0.0, 0.0));
+        let c = RasterVertex::new(Vec4::new(-1.0, 1.0, 0.5, 1.0), Vec3::new(0.0, 1.0, 0.0));
+
+        let mut image = Image::new(8, 4);
+        let mut derivs = Vec::new();
+        image.draw_triangle_with(&a, &b, &c, &dir, |frag| {
+            derivs.push((frag.ddx, frag.ddy));
+            None
+        });
+
+        // u spans 8 pixels horizontally, v spans 4 pixels upwards
+        for (ddx, ddy) in derivs {
+            assert!((ddx - Vec3::new(0.125, 0.0, 0.0)).len() < 1e-5);
+            assert!((ddy - Vec3::new(0.0, -0.25, 0.0)).len() < 1e-5);
+        }
+    }
+}
@@ -0,0 +1,21 @@
+pub mod camera;
+pub mod clip;
+pub mod codec;
+pub mod color;
+pub mod gltf;
+pub mod image;
+pub mod mat44;
+pub mod mesh;
+pub mod mtl;
+pub mod normals;
+pub mod obj;
+pub mod ply;
+pub mod primitives;
+pub mod quat;
+pub mod stl;
+mod tangents;
+pub mod texture;
+pub mod triangulate;
+pub mod varying;
+pub mod vec3;
+pub mod vec4;
@@ -1,60 +1,106 @@
-use crate::color::Color;
-use crate::image::Image;
-use crate::mat44::Mat44;
-use crate::vec3::Vec3;
-use crate::obj::ObjModel;
 use rand::Rng;
-
-mod color;
-mod image;
-mod mat44;
-mod vec3;
-mod obj;
+use std::collections::HashMap;
+use renderer::camera::Camera;
+use renderer::color::Color;
+use renderer::image::{Image, RasterVertex};
+use renderer::mat44::Mat44;
+use renderer::mesh;
+use renderer::primitives;
+use renderer::quat::Quat;
+use renderer::texture::{Sampler, Texture, Wrap};
+use renderer::vec3::Vec3;
+use renderer::vec4::Vec4;
 
 fn main() {
-    // Try to load an OBJ file, fall back to sphere if not found
-    let mut model = if let Ok(obj_model) = ObjModel::load("models/deer.obj") {
-        obj_model
-    } else {
-        generate_sphere(20, 20)
-    };
-
-    // Calculate normals if not present
-    if model.normals.is_empty() {
-        model.calculate_normals();
-    }
+    // Try to load a model file, fall back to sphere if not found
+    let mut mesh = mesh::load("models/deer.obj").unwrap_or_else(|_| primitives::uv_sphere(1.5, 20, 20));
 
     // Center and scale the model
-    model.center_and_scale(10.0);
+    mesh.center_and_scale(10.0);
 
     let sun_dir = Vec3::new(0.1, 0.1, -1.0);
 
+    // Diffuse textures by material name; missing or unreadable ones are skipped
+    let textures: HashMap<&str, Texture> = mesh
+        .materials
+        .iter()
+        .filter_map(|m| {
+            let base_color_map = || m.pbr.as_ref()?.base_color_map.as_ref();
+            let path = m.diffuse_map.as_ref().or_else(base_color_map)?;
+            Some((m.name.as_str(), Texture::load(path).ok()?))
+        })
+        .collect();
+    let sampler = Sampler::trilinear(Wrap::Repeat);
+
     let mut image = Image::new(800, 600);
     let mut rng = rand::thread_rng();
-    let persp = Mat44::persp((45.0_f32).to_radians(), image.aspect(), 0.1, 100.0);
+    let camera = Camera::perspective(
+        Vec3::new(0.0, 0.0, 30.0),
+        Vec3::new(0.0, 0.0, 0.0),
+        (45.0_f32).to_radians(),
+        0.1,
+        100.0,
+    );
+    let view = camera.view();
+    let proj = camera.projection(image.aspect());
 
     for x in -1..=1 {
         for y in -1..=1 {
-            let trans = Mat44::trans(&Vec3::new(x as f32 * 8.0, y as f32 * 8.0, -30.0));
+            let trans = Mat44::trans(&Vec3::new(x as f32 * 8.0, y as f32 * 8.0, 0.0));
             let angle = rng.gen_range(-360.0_f32..360.0_f32);
-            let rotat = Mat44::rotat(&Vec3::new(1.0, 1.0, 1.0), angle.to_radians());
-
-            let t_vertices: Vec<Vec3> = model.vertices.iter().map(|v| persp * trans * rotat * v).collect();
-
-            for face in &model.faces {
-                if let Some((v0, v1, v2)) = model.get_triangle_vertices(face) {
-                    let e0 = v1 - v0;
-                    let e1 = v2 - v0;
-                    let n = e0.cross(&e1).norm();
-
-                    let lum = n.dot(&sun_dir.neg().norm()).clamp(0.0, 1.0);
-                    let color = Vec3::new(1.0, 1.0, 1.0) * (0.3 + 0.7 * lum);
-                    let color = Color::from(color);
-
-                    let t_v0 = &t_vertices[face.vertices[0]];
-                    let t_v1 = &t_vertices[face.vertices[1]];
-                    let t_v2 = &t_vertices[face.vertices[2]];
-
+            let rotat = Mat44::from(Quat::from_axis_angle(&Vec3::new(1.0, 1.0, 1.0), angle.to_radians()));
+
+            let model_view = view * trans * rotat;
+            let mvp = proj * model_view;
+            let normal_matrix = model_view.normal_matrix().expect("model-view is invertible");
+
+            let t_vertices: Vec<Vec4> = mesh.positions.iter().map(|v| mvp * v).collect();
+
+            for (t, [i0, i1, i2]) in mesh.triangles().enumerate() {
+                let (v0, v1, v2) = (mesh.positions[i0], mesh.positions[i1], mesh.positions[i2]);
+                let e0 = v1 - v0;
+                let e1 = v2 - v0;
+                let n = normal_matrix.transform_vector(&e0.cross(&e1)).norm();
+
+                let lum = n.dot(&sun_dir.neg().norm()).clamp(0.0, 1.0);
+                let material = mesh.triangle_material(t);
+                let diffuse = material.map_or(Vec3::new(1.0, 1.0, 1.0), |m| m.diffuse);
+                let texture = material.and_then(|m| textures.get(m.name.as_str()));
+                let shade = 0.3 + 0.7 * lum;
+                let color = Vec3::new(diffuse.x * shade, diffuse.y * shade, diffuse.z * shade);
+                let color = Color::from(color);
+
+                let t_v0 = &t_vertices[i0];
+                let t_v1 = &t_vertices[i1];
+                let t_v2 = &t_vertices[i2];
+
+                // Sample the diffuse texture when the model has UVs for it,
+                // otherwise interpolate vertex colors when it has them
+                if let (Some(texture), Some(tex_coords)) = (texture, &mesh.tex_coords) {
+                    image.draw_triangle_with(
+                        &RasterVertex::new(*t_v0, tex_coords[i0]),
+                        &RasterVertex::new(*t_v1, tex_coords[i1]),
+                        &RasterVertex::new(*t_v2, tex_coords[i2]),
+                        &Vec3::new(0.0, 0.0, 1.0),
+                        |fragment| {
+                            let uv = fragment.varying;
+                            let texel = sampler.sample_grad(texture, uv.x, uv.y, &fragment.ddx, &fragment.ddy);
+                            let c = texel.xyz() * shade;
+                            Some(Color::from(Vec3::new(c.x * diffuse.x, c.y * diffuse.y, c.z * diffuse.z)))
+                        },
+                    );
+                } else if let Some(colors) = &mesh.colors {
+                    image.draw_triangle_with(
+                        &RasterVertex::new(*t_v0, colors[i0]),
+                        &RasterVertex::new(*t_v1, colors[i1]),
+                        &RasterVertex::new(*t_v2, colors[i2]),
+                        &Vec3::new(0.0, 0.0, 1.0),
+                        |fragment| {
+                            let c = fragment.varying * shade;
+                            Some(Color::from(Vec3::new(c.x * diffuse.x, c.y * diffuse.y, c.z * diffuse.z)))
+                        },
+                    );
+                } else {
                     image.draw_triangle(
                         t_v0,
                         t_v1,
@@ -67,41 +113,9 @@ fn main() {
         }
     }
 
-    println!("{}", image);
-}
-
-fn generate_sphere(lat_segments: usize, lon_segments: usize) -> ObjModel {
-    let mut model = ObjModel::new();
-    
-    // Generate vertices
-    for i in 0..=lat_segments {
-        let theta = std::f32::consts::PI * (i as f32) / (lat_segments as f32);
-        let y = theta.cos();
-        let r = theta.sin();
-        for j in 0..=lon_segments {
-            let phi = 2.0 * std::f32::consts::PI * (j as f32) / (lon_segments as f32);
-            let x = r * phi.cos();
-            let z = r * phi.sin();
-            model.vertices.push(Vec3::new(x * 1.5, y * 1.5, z * 1.5));
-        }
-    }
-
-    // Generate faces
-    for i in 0..lat_segments {
-        for j in 0..lon_segments {
-            let a = i * (lon_segments + 1) + j;
-            let b = a + lon_segments + 1;
-            let c = a + 1;
-            let d = b + 1;
-            // Each quad is split into two triangles
-            model.faces.push(crate::obj::Face {
-                vertices: vec![a, b, c],
-            });
-            model.faces.push(crate::obj::Face {
-                vertices: vec![c, b, d],
-            });
-        }
+    // Save to the path given on the command line, or print ASCII PPM
+    match std::env::args().nth(1) {
+        Some(path) => image.save(path).expect("failed to save image"),
+        None => println!("{}", image),
     }
-
-    model
 }
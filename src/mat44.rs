@@ -1,18 +1,38 @@
 use crate::vec3::Vec3;
+use crate::vec4::Vec4;
+use std::error::Error;
+use std::fmt;
 use std::ops::Mul;
 
 #[derive(Copy, Clone, PartialEq, Debug)]
 pub struct Mat44([[f32; 4]; 4]);
 
+/// Returned when inverting a matrix whose determinant is zero.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct SingularMatrixError;
+
+impl fmt::Display for SingularMatrixError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "matrix is singular")
+    }
+}
+
+impl Error for SingularMatrixError {}
+
+/// Transforms a point into homogeneous coordinates without dividing by `w`.
 impl Mul<&Vec3> for Mat44 {
-    type Output = Vec3;
+    type Output = Vec4;
 
     fn mul(self, rhs: &Vec3) -> Self::Output {
-        let x = self.0[0][0] * rhs.x + self.0[0][1] * rhs.y + self.0[0][2] * rhs.z + self.0[0][3];
-        let y = self.0[1][0] * rhs.x + self.0[1][1] * rhs.y + self.0[1][2] * rhs.z + self.0[1][3];
-        let z = self.0[2][0] * rhs.x + self.0[2][1] * rhs.y + self.0[2][2] * rhs.z + self.0[2][3];
-        let w = self.0[3][0] * rhs.x + self.0[3][1] * rhs.y + self.0[3][2] * rhs.z + self.0[3][3];
-        Vec3::new(x / w, y / w, z / w)
+        self.transform_homogeneous(&Vec4::from_vec3(rhs, 1.0))
+    }
+}
+
+impl Mul<&Vec4> for Mat44 {
+    type Output = Vec4;
+
+    fn mul(self, rhs: &Vec4) -> Self::Output {
+        self.transform_homogeneous(rhs)
     }
 }
 
@@ -100,6 +120,15 @@ impl Mul for Mat44 {
 }
 
 impl Mat44 {
+    /// Builds a matrix from its rows.
+    pub fn new(rows: [[f32; 4]; 4]) -> Self {
+        Self(rows)
+    }
+
+    pub fn get(&self, row: usize, col: usize) -> f32 {
+        self.0[row][col]
+    }
+
     pub fn ident() -> Self {
         Self([
             [1.0, 0.0, 0.0, 0.0],
@@ -154,6 +183,187 @@ impl Mat44 {
         ])
     }
 
+    /// View matrix for an eye at `eye` looking towards `target`. Follows the
+    /// right-handed convention of `persp`: the camera looks down -z.
+    pub fn look_at(eye: &Vec3, target: &Vec3, up: &Vec3) -> Self {
+        let f = (target - eye).norm();
+        let r = f.cross(up).norm();
+        let u = r.cross(&f);
+
+        Self([
+            [r.x, r.y, r.z, -r.dot(eye)],
+            [u.x, u.y, u.z, -u.dot(eye)],
+            [-f.x, -f.y, -f.z, f.dot(eye)],
+            [0.0, 0.0, 0.0, 1.0],
+        ])
+    }
+
+    /// Parallel projection of the box `[l, r] x [b, t] x [-n, -f]`. Depth maps
+    /// to `[0, 1]` like `persp`.
+    pub fn ortho(l: f32, r: f32, b: f32, t: f32, n: f32, f: f32) -> Self {
+        Self([
+            [2.0 / (r - l), 0.0, 0.0, -(r + l) / (r - l)],
+            [0.0, 2.0 / (t - b), 0.0, -(t + b) / (t - b)],
+            [0.0, 0.0, -1.0 / (f - n), -n / (f - n)],
+            [0.0, 0.0, 0.0, 1.0],
+        ])
+    }
+
+    /// Perspective projection with arbitrary extents on the near plane, for
+    /// off-axis rendering. A symmetric frustum matches `persp`.
+    pub fn frustum(l: f32, r: f32, b: f32, t: f32, n: f32, f: f32) -> Self {
+        Self([
+            [2.0 * n / (r - l), 0.0, (r + l) / (r - l), 0.0],
+            [0.0, 2.0 * n / (t - b), (t + b) / (t - b), 0.0],
+            [0.0, 0.0, -f / (f - n), -f * n / (f - n)],
+            [0.0, 0.0, -1.0, 0.0],
+        ])
+    }
+
+    pub fn transpose(&self) -> Self {
+        let m = &self.0;
+        Self([
+            [m[0][0], m[1][0], m[2][0], m[3][0]],
+            [m[0][1], m[1][1], m[2][1], m[3][1]],
+            [m[0][2], m[1][2], m[2][2], m[3][2]],
+            [m[0][3], m[1][3], m[2][3], m[3][3]],
+        ])
+    }
+
+    // 2x2 minors of the top two and bottom two rows, shared by `determinant`
+    // and `inverse` (Laplace expansion along row pairs)
+    fn minors(&self) -> ([f32; 6], [f32; 6]) {
+        let m = &self.0;
+        let s = [
+            m[0][0] * m[1][1] - m[1][0] * m[0][1],
+            m[0][0] * m[1][2] - m[1][0] * m[0][2],
+            m[0][0] * m[1][3] - m[1][0] * m[0][3],
+            m[0][1] * m[1][2] - m[1][1] * m[0][2],
+            m[0][1] * m[1][3] - m[1][1] * m[0][3],
+            m[0][2] * m[1][3] - m[1][2] * m[0][3],
+        ];
+        let c = [
+            m[2][0] * m[3][1] - m[3][0] * m[2][1],
+            m[2][0] * m[3][2] - m[3][0] * m[2][2],
+            m[2][0] * m[3][3] - m[3][0] * m[2][3],
+            m[2][1] * m[3][2] - m[3][1] * m[2][2],
+            m[2][1] * m[3][3] - m[3][1] * m[2][3],
+            m[2][2] * m[3][3] - m[3][2] * m[2][3],
+        ];
+        (s, c)
+    }
+
+    pub fn determinant(&self) -> f32 {
+        let (s, c) = self.minors();
+        s[0] * c[5] - s[1] * c[4] + s[2] * c[3] + s[3] * c[2] - s[4] * c[1] + s[5] * c[0]
+    }
+
+    pub fn inverse(&self) -> Result<Self, SingularMatrixError> {
+        let m = &self.0;
+        let (s, c) = self.minors();
+
+        let det = s[0] * c[5] - s[1] * c[4] + s[2] * c[3] + s[3] * c[2] - s[4] * c[1] + s[5] * c[0];
+        if det == 0.0 || !det.is_finite() {
+            return Err(SingularMatrixError);
+        }
+
+        let inv = 1.0 / det;
+        Ok(Self([
+            [
+                (m[1][1] * c[5] - m[1][2] * c[4] + m[1][3] * c[3]) * inv,
+                (-m[0][1] * c[5] + m[0][2] * c[4] - m[0][3] * c[3]) * inv,
+                (m[3][1] * s[5] - m[3][2] * s[4] + m[3][3] * s[3]) * inv,
+                (-m[2][1] * s[5] + m[2][2] * s[4] - m[2][3] * s[3]) * inv,
+            ],
+            [
+                (-m[1][0] * c[5] + m[1][2] * c[2] - m[1][3] * c[1]) * inv,
+                (m[0][0] * c[5] - m[0][2] * c[2] + m[0][3] * c[1]) * inv,
+                (-m[3][0] * s[5] + m[3][2] * s[2] - m[3][3] * s[1]) * inv,
+                (m[2][0] * s[5] - m[2][2] * s[2] + m[2][3] * s[1]) * inv,
+            ],
+            [
+                (m[1][0] * c[4] - m[1][1] * c[2] + m[1][3] * c[0]) * inv,
+                (-m[0][0] * c[4] + m[0][1] * c[2] - m[0][3] * c[0]) * inv,
+                (m[3][0] * s[4] - m[3][1] * s[2] + m[3][3] * s[0]) * inv,
+                (-m[2][0] * s[4] + m[2][1] * s[2] - m[2][3] * s[0]) * inv,
+            ],
+            [
+                (-m[1][0] * c[3] + m[1][1] * c[1] - m[1][2] * c[0]) * inv,
+                (m[0][0] * c[3] - m[0][1] * c[1] + m[0][2] * c[0]) * inv,
+                (-m[3][0] * s[3] + m[3][1] * s[1] - m[3][2] * s[0]) * inv,
+                (m[2][0] * s[3] - m[2][1] * s[1] + m[2][2] * s[0]) * inv,
+            ],
+        ]))
+    }
+
+    /// The inverse-transpose of the upper 3x3 block, for transforming normals
+    /// with `transform_vector`. Translation and projection are dropped.
+    pub fn normal_matrix(&self) -> Result<Self, SingularMatrixError> {
+        let m = &self.0;
+
+        // The cofactor matrix equals det * inverse-transpose
+        let cof = [
+            [
+                m[1][1] * m[2][2] - m[1][2] * m[2][1],
+                m[1][2] * m[2][0] - m[1][0] * m[2][2],
+                m[1][0] * m[2][1] - m[1][1] * m[2][0],
+            ],
+            [
+                m[0][2] * m[2][1] - m[0][1] * m[2][2],
+                m[0][0] * m[2][2] - m[0][2] * m[2][0],
+                m[0][1] * m[2][0] - m[0][0] * m[2][1],
+            ],
+            [
+                m[0][1] * m[1][2] - m[0][2] * m[1][1],
+                m[0][2] * m[1][0] - m[0][0] * m[1][2],
+                m[0][0] * m[1][1] - m[0][1] * m[1][0],
+            ],
+        ];
+
+        let det = m[0][0] * cof[0][0] + m[0][1] * cof[0][1] + m[0][2] * cof[0][2];
+        if det == 0.0 || !det.is_finite() {
+            return Err(SingularMatrixError);
+        }
+
+        let inv = 1.0 / det;
+        Ok(Self([
+            [cof[0][0] * inv, cof[0][1] * inv, cof[0][2] * inv, 0.0],
+            [cof[1][0] * inv, cof[1][1] * inv, cof[1][2] * inv, 0.0],
+            [cof[2][0] * inv, cof[2][1] * inv, cof[2][2] * inv, 0.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ]))
+    }
+
+    pub fn transform_homogeneous(&self, v: &Vec4) -> Vec4 {
+        let m = &self.0;
+        Vec4::new(
+            m[0][0] * v.x + m[0][1] * v.y + m[0][2] * v.z + m[0][3] * v.w,
+            m[1][0] * v.x + m[1][1] * v.y + m[1][2] * v.z + m[1][3] * v.w,
+            m[2][0] * v.x + m[2][1] * v.y + m[2][2] * v.z + m[2][3] * v.w,
+            m[3][0] * v.x + m[3][1] * v.y + m[3][2] * v.z + m[3][3] * v.w,
+        )
+    }
+
+    /// Transforms a position: translation applies and the result is divided
+    /// by `w`.
+    pub fn transform_point(&self, v: &Vec3) -> Vec3 {
+        (*self * v).to_ndc()
+    }
+
+    /// Transforms a direction (`w = 0`): translation is ignored. Normals need
+    /// the inverse-transpose instead when the matrix scales non-uniformly.
+    pub fn transform_vector(&self, v: &Vec3) -> Vec3 {
+        self.transform_homogeneous(&Vec4::from_vec3(v, 0.0)).xyz()
+    }
+
+    /// Transforms a point and divides by `w`, returning the divided point
+    /// together with `w` itself. The rasterizer needs `w` for
+    /// perspective-correct interpolation.
+    pub fn project(&self, v: &Vec3) -> (Vec3, f32) {
+        let p = *self * v;
+        (p.to_ndc(), p.w)
+    }
+
     pub fn persp(fov: f32, aspect: f32, n: f32, f: f32) -> Self {
         let a = aspect;
         let s = (fov * 0.5).tan();
@@ -168,8 +378,17 @@ impl Mat44 {
 
 #[cfg(test)]
 mod tests {
-    use crate::mat44::Mat44;
+    use crate::mat44::{Mat44, SingularMatrixError};
     use crate::vec3::Vec3;
+    use crate::vec4::Vec4;
+
+    fn assert_close(a: &Mat44, b: &Mat44) {
+        for i in 0..4 {
+            for j in 0..4 {
+                assert!((a.0[i][j] - b.0[i][j]).abs() < 1e-5, "{:?} != {:?}", a, b);
+            }
+        }
+    }
 
     #[test]
     fn test_mul_ident() {
@@ -187,4 +406,117 @@ mod tests {
 
         assert_eq!(b, a * b);
     }
+
+    #[test]
+    fn test_project_keeps_w() {
+        let persp = Mat44::persp((90.0_f32).to_radians(), 1.0, 1.0, 10.0);
+        let (p, w) = persp.project(&Vec3::new(2.0, 1.0, -4.0));
+
+        assert_eq!(w, 4.0);
+        assert!((p.x - 0.5).abs() < 1e-6);
+        assert!((p.y - 0.25).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_transform_point_and_vector() {
+        let m = Mat44::trans(&Vec3::new(1.0, 2.0, 3.0)) * Mat44::scale(&Vec3::new(2.0, 2.0, 2.0));
+        let v = Vec3::new(1.0, 1.0, 1.0);
+
+        assert_eq!(m.transform_point(&v), Vec3::new(3.0, 4.0, 5.0));
+        assert_eq!(m.transform_vector(&v), Vec3::new(2.0, 2.0, 2.0));
+    }
+
+    #[test]
+    fn test_transform_homogeneous() {
+        let m = Mat44::trans(&Vec3::new(1.0, 2.0, 3.0));
+
+        assert_eq!(
+            m.transform_homogeneous(&Vec4::new(1.0, 1.0, 1.0, 0.0)),
+            Vec4::new(1.0, 1.0, 1.0, 0.0)
+        );
+        assert_eq!(
+            m * &Vec4::new(1.0, 1.0, 1.0, 2.0),
+            Vec4::new(3.0, 5.0, 7.0, 2.0)
+        );
+    }
+
+    #[test]
+    fn test_transpose() {
+        let m = Mat44::trans(&Vec3::new(1.0, 2.0, 3.0));
+        let t = m.transpose();
+
+        assert_eq!(t.0[3], [1.0, 2.0, 3.0, 1.0]);
+        assert_eq!(t.transpose(), m);
+    }
+
+    #[test]
+    fn test_determinant() {
+        assert_eq!(Mat44::ident().determinant(), 1.0);
+        assert_eq!(Mat44::scale(&Vec3::new(2.0, 3.0, 4.0)).determinant(), 24.0);
+
+        let r = Mat44::rotat(&Vec3::new(1.0, 1.0, 0.0), 0.7);
+        assert!((r.determinant() - 1.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_inverse() {
+        let m = Mat44::trans(&Vec3::new(1.0, -2.0, 3.0))
+            * Mat44::rotat(&Vec3::new(1.0, 1.0, 1.0), 1.2)
+            * Mat44::scale(&Vec3::new(2.0, 0.5, 3.0));
+
+        assert_close(&(m * m.inverse().unwrap()), &Mat44::ident());
+        assert_close(&(m.inverse().unwrap() * m), &Mat44::ident());
+    }
+
+    #[test]
+    fn test_inverse_singular() {
+        let m = Mat44::scale(&Vec3::new(1.0, 0.0, 1.0));
+
+        assert_eq!(m.inverse(), Err(SingularMatrixError));
+        assert_eq!(m.normal_matrix(), Err(SingularMatrixError));
+    }
+
+    #[test]
+    fn test_unproject() {
+        let persp = Mat44::persp((60.0_f32).to_radians(), 1.5, 0.1, 100.0);
+        let p = Vec3::new(1.0, -2.0, -10.0);
+
+        let ndc = persp.transform_point(&p);
+        let back = persp.inverse().unwrap().transform_point(&ndc);
+        assert!((back - p).len() < 1e-3);
+    }
+
+    #[test]
+    fn test_normal_matrix() {
+        let m = Mat44::trans(&Vec3::new(5.0, 5.0, 5.0)) * Mat44::scale(&Vec3::new(1.0, 2.0, 1.0));
+
+        // Normal of the plane x + y = 0 must stay perpendicular to it
+        let n = m
+            .normal_matrix()
+            .unwrap()
+            .transform_vector(&Vec3::new(1.0, 1.0, 0.0));
+        let t = m.transform_vector(&Vec3::new(1.0, -1.0, 0.0));
+        assert!(n.dot(&t).abs() < 1e-6);
+        assert_eq!(n, Vec3::new(1.0, 0.5, 0.0));
+    }
+
+    #[test]
+    fn test_look_at() {
+        let eye = Vec3::new(0.0, 0.0, 5.0);
+        let view = Mat44::look_at(&eye, &Vec3::new(0.0, 0.0, 0.0), &Vec3::new(0.0, 1.0, 0.0));
+
+        assert_eq!(view, Mat44::trans(&eye.neg()));
+    }
+
+    #[test]
+    fn test_symmetric_frustum_matches_persp() {
+        let (fov, aspect, n, f) = ((50.0_f32).to_radians(), 1.25, 0.5, 50.0);
+        let t = n * (fov * 0.5).tan();
+        let r = t * aspect;
+
+        assert_close(
+            &Mat44::frustum(-r, r, -t, t, n, f),
+            &Mat44::persp(fov, aspect, n, f),
+        );
+    }
 }
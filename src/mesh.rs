@@ -0,0 +1,570 @@
+//! A format-neutral indexed triangle mesh.
+//!
+//! Vertex attributes are stored as separate arrays (structure of arrays), all
+//! indexed by the same `u32` index buffer. Unlike `ObjModel`, where each face
+//! corner indexes positions, UVs and normals independently, a vertex here is
+//! one slot in every channel.
+
+use crate::gltf::{self, GltfError};
+use crate::mtl::Material;
+use crate::normals::{self, NormalOptions};
+use crate::obj::{Face, ObjError, ObjModel, Vertex};
+use crate::ply::{self, PlyError};
+use crate::stl::{self, StlError};
+use crate::tangents;
+use crate::triangulate::triangulate;
+use crate::vec3::Vec3;
+use crate::vec4::Vec4;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::hash::Hash;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+/// A run of triangles drawn with one material.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Submesh {
+    pub triangles: Range<usize>, // Triangle indices, not index buffer offsets
+    pub material: Option<usize>, // Index into materials
+}
+
+/// Indexed triangles with optional per-vertex channels. Every channel that is
+/// present has one entry per position.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Mesh {
+    pub positions: Vec<Vec3>,
+    pub normals: Option<Vec<Vec3>>,
+    pub tex_coords: Option<Vec<Vec3>>, // u, v; z is unused
+    pub colors: Option<Vec<Vec4>>,     // RGBA
+    pub tangents: Option<Vec<Vec4>>,   // xyz tangent, w the bitangent sign
+    pub indices: Vec<u32>,             // Three per triangle
+    pub materials: Vec<Material>,
+    pub submeshes: Vec<Submesh>,
+}
+
+/// Why a mesh file could not be loaded.
+#[derive(Debug)]
+pub enum MeshError {
+    Obj(ObjError),
+    Stl(StlError),
+    Ply(PlyError),
+    Gltf(GltfError),
+    /// The file extension names no supported format.
+    UnknownFormat(PathBuf),
+}
+
+impl fmt::Display for MeshError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MeshError::Obj(err) => write!(f, "{}", err),
+            MeshError::Stl(err) => write!(f, "{}", err),
+            MeshError::Ply(err) => write!(f, "{}", err),
+            MeshError::Gltf(err) => write!(f, "{}", err),
+            MeshError::UnknownFormat(path) => {
+                write!(f, "unknown mesh format: {}", path.display())
+            }
+        }
+    }
+}
+
+impl Error for MeshError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            MeshError::Obj(err) => Some(err),
+            MeshError::Stl(err) => Some(err),
+            MeshError::Ply(err) => Some(err),
+            MeshError::Gltf(err) => Some(err),
+            MeshError::UnknownFormat(_) => None,
+        }
+    }
+}
+
+/// Loads an OBJ, STL, PLY, glTF or GLB file, picked by extension.
+pub fn load<P: AsRef<Path>>(path: P) -> Result<Mesh, MeshError> {
+    let path = path.as_ref();
+    let extension = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(str::to_ascii_lowercase);
+
+    let model = match extension.as_deref() {
+        Some("obj") => ObjModel::load(path).map_err(MeshError::Obj)?,
+        Some("stl") => stl::load(path).map_err(MeshError::Stl)?,
+        Some("ply") => ply::load(path).map_err(MeshError::Ply)?,
+        Some("gltf" | "glb") => gltf::load(path).map_err(MeshError::Gltf)?,
+        _ => return Err(MeshError::UnknownFormat(path.to_path_buf())),
+    };
+    Ok(Mesh::from(&model))
+}
+
+impl Mesh {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn vertex_count(&self) -> usize {
+        self.positions.len()
+    }
+
+    pub fn triangle_count(&self) -> usize {
+        self.indices.len() / 3
+    }
+
+    /// Vertex indices of triangle `t`.
+    pub fn triangle(&self, t: usize) -> [usize; 3] {
+        let i = &self.indices[t * 3..t * 3 + 3];
+        [i[0] as usize, i[1] as usize, i[2] as usize]
+    }
+
+    pub fn triangles(&self) -> impl Iterator<Item = [usize; 3]> + '_ {
+        self.indices
+            .chunks_exact(3)
+            .map(|i| [i[0] as usize, i[1] as usize, i[2] as usize])
+    }
+
+    /// Appends a triangle of existing vertices.
+    pub fn push_triangle(&mut self, a: usize, b: usize, c: usize) {
+        self.indices.extend([a as u32, b as u32, c as u32]);
+    }
+
+    /// Material of triangle `t`, if a submesh covering it has one.
+    pub fn triangle_material(&self, t: usize) -> Option<&Material> {
+        let submesh = self.submeshes.iter().find(|s| s.triangles.contains(&t))?;
+        self.materials.get(submesh.material?)
+    }
+
+    /// Checks that every channel has one entry per position and every index
+    /// names a vertex.
+    pub fn is_valid(&self) -> bool {
+        let n = self.positions.len();
+        self.indices.len().is_multiple_of(3)
+            && self.indices.iter().all(|&i| (i as usize) < n)
+            && self.normals.as_ref().is_none_or(|c| c.len() == n)
+            && self.tex_coords.as_ref().is_none_or(|c| c.len() == n)
+            && self.colors.as_ref().is_none_or(|c| c.len() == n)
+            && self.tangents.as_ref().is_none_or(|c| c.len() == n)
+    }
+
+    /// Replaces the normals with generated ones. Vertices at the same
+    /// position are smoothed together even where UV seams split them, and
+    /// split further where the crease angle gives their corners different
+    /// normals. Tangents no longer match and are dropped.
+    pub fn calculate_normals(&mut self, options: &NormalOptions) {
+        // Triangles over distinct positions rather than vertices
+        let mut ids: HashMap<[u32; 3], usize> = HashMap::new();
+        let mut points = Vec::new();
+        let canonical: Vec<usize> = self
+            .positions
+            .iter()
+            .map(|p| {
+                *ids.entry(bits(p)).or_insert_with(|| {
+                    points.push(*p);
+                    points.len() - 1
+                })
+            })
+            .collect();
+        let triangles: Vec<[usize; 3]> =
+            self.triangles().map(|t| t.map(|i| canonical[i])).collect();
+
+        let corners: Vec<Vec3> = normals::corner_normals(&points, &triangles, &[], options)
+            .into_iter()
+            .flatten()
+            .collect();
+        let keys: Vec<[u32; 3]> = corners.iter().map(bits).collect();
+        let sources = self.split_vertices(&keys);
+
+        self.normals = Some(sources.iter().map(|&c| corners[c]).collect());
+        self.tangents = None;
+    }
+
+    /// Generates tangents from the normals and UVs with the MikkTSpace
+    /// algorithm, so normal maps baked by other tools line up. Vertices are
+    /// split where the corners sharing them end up with different frames,
+    /// as along UV mirror seams. Returns `false`, changing nothing, if the
+    /// mesh has no normals or no UVs.
+    pub fn generate_tangents(&mut self) -> bool {
+        let (Some(normals), Some(tex_coords)) = (&self.normals, &self.tex_coords) else {
+            return false;
+        };
+        let triangles: Vec<[usize; 3]> = self.triangles().collect();
+        let corners: Vec<Vec4> =
+            tangents::corner_tangents(&self.positions, normals, tex_coords, &triangles)
+                .into_iter()
+                .flatten()
+                .collect();
+
+        let keys: Vec<[u32; 4]> = corners
+            .iter()
+            .map(|t| [t.x, t.y, t.z, t.w].map(f32::to_bits))
+            .collect();
+        let sources = self.split_vertices(&keys);
+        self.tangents = Some(sources.iter().map(|&c| corners[c]).collect());
+        true
+    }
+
+    /// Rebuilds the vertex buffer so corners share a vertex only if they did
+    /// before and have equal `keys`, given one per index. Returns, for each
+    /// new vertex, the first corner that uses it. Unused vertices are
+    /// dropped.
+    fn split_vertices<K: Hash + Eq>(&mut self, keys: &[K]) -> Vec<usize> {
+        let mut split: HashMap<(u32, &K), u32> = HashMap::new();
+        let mut sources = Vec::new();
+        let mut old = Vec::new();
+        for (corner, (index, key)) in self.indices.iter_mut().zip(keys).enumerate() {
+            *index = *split.entry((*index, key)).or_insert_with(|| {
+                sources.push(corner);
+                old.push(*index as usize);
+                (old.len() - 1) as u32
+            });
+        }
+
+        fn pick<T: Copy>(channel: &[T], old: &[usize]) -> Vec<T> {
+            old.iter().map(|&i| channel[i]).collect()
+        }
+        self.positions = pick(&self.positions, &old);
+        self.normals = self.normals.as_deref().map(|c| pick(c, &old));
+        self.tex_coords = self.tex_coords.as_deref().map(|c| pick(c, &old));
+        self.colors = self.colors.as_deref().map(|c| pick(c, &old));
+        self.tangents = self.tangents.as_deref().map(|c| pick(c, &old));
+        sources
+    }
+
+    pub fn get_bounding_box(&self) -> (Vec3, Vec3) {
+        let Some(&first) = self.positions.first() else {
+            return (Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 0.0, 0.0));
+        };
+
+        let (mut min, mut max) = (first, first);
+        for p in &self.positions {
+            min = Vec3::new(min.x.min(p.x), min.y.min(p.y), min.z.min(p.z));
+            max = Vec3::new(max.x.max(p.x), max.y.max(p.y), max.z.max(p.z));
+        }
+        (min, max)
+    }
+
+    /// Moves the bounding box center to the origin and scales the diagonal
+    /// to `scale`. Normals and tangents keep their directions.
+    pub fn center_and_scale(&mut self, scale: f32) {
+        let (min, max) = self.get_bounding_box();
+        let center = (min + max) / 2.0;
+        let size = (max - min).len();
+        let scale_factor = if size > 0.0 { scale / size } else { 1.0 };
+
+        for p in &mut self.positions {
+            *p = (*p - center) * scale_factor;
+        }
+    }
+}
+
+/// Bit pattern of a vector, for use as a hash key. Negative zero is folded
+/// into zero.
+fn bits(v: &Vec3) -> [u32; 3] {
+    [v.x + 0.0, v.y + 0.0, v.z + 0.0].map(f32::to_bits)
+}
+
+impl From<&ObjModel> for Mesh {
+    /// Welds face corners with the same position, UV and normal into one
+    /// vertex. Polygons are triangulated; corners with out of range indices
+    /// drop their face. Channels are present if any corner uses them, with
+    /// zeros filling in for corners that don't.
+    fn from(model: &ObjModel) -> Self {
+        let mut mesh = Mesh {
+            materials: model.materials.clone(),
+            ..Mesh::default()
+        };
+        let has_tex_coords = model
+            .faces
+            .iter()
+            .any(|f| f.vertices.iter().any(|v| v.tex_coord.is_some()));
+        let has_normals = model
+            .faces
+            .iter()
+            .any(|f| f.vertices.iter().any(|v| v.normal.is_some()));
+        let has_colors = !model.colors.is_empty();
+
+        let mut tex_coords = Vec::new();
+        let mut normals = Vec::new();
+        let mut colors = Vec::new();
+        let mut welded: HashMap<Vertex, u32> = HashMap::new();
+
+        for face in &model.faces {
+            let in_range = face.vertices.iter().all(|v| {
+                v.position < model.vertices.len()
+                    && v.tex_coord.is_none_or(|t| t < model.tex_coords.len())
+                    && v.normal.is_none_or(|n| n < model.normals.len())
+            });
+            if !in_range || face.vertices.len() < 3 {
+                continue;
+            }
+
+            let corners: Vec<u32> = face
+                .vertices
+                .iter()
+                .map(|&v| {
+                    *welded.entry(v).or_insert_with(|| {
+                        let zero = Vec3::new(0.0, 0.0, 0.0);
+                        mesh.positions.push(model.vertices[v.position]);
+                        tex_coords.push(v.tex_coord.map_or(zero, |t| model.tex_coords[t]));
+                        normals.push(v.normal.map_or(zero, |n| model.normals[n]));
+                        colors.push(
+                            model
+                                .colors
+                                .get(v.position)
+                                .copied()
+                                .unwrap_or(Vec4::new(1.0, 1.0, 1.0, 1.0)),
+                        );
+                        (mesh.positions.len() - 1) as u32
+                    })
+                })
+                .collect();
+
+            let triangles = if corners.len() == 3 {
+                vec![[0, 1, 2]]
+            } else {
+                let points: Vec<Vec3> = face
+                    .vertices
+                    .iter()
+                    .map(|v| model.vertices[v.position])
+                    .collect();
+                triangulate(&points)
+            };
+
+            // Extend the last submesh while the material stays the same
+            let t = mesh.triangle_count();
+            match mesh.submeshes.last_mut() {
+                Some(last) if last.material == face.material && last.triangles.end == t => {
+                    last.triangles.end += triangles.len();
+                }
+                _ => mesh.submeshes.push(Submesh {
+                    triangles: t..t + triangles.len(),
+                    material: face.material,
+                }),
+            }
+            for [a, b, c] in triangles {
+                mesh.indices.extend([corners[a], corners[b], corners[c]]);
+            }
+        }
+
+        mesh.submeshes.retain(|s| !s.triangles.is_empty());
+        mesh.tex_coords = has_tex_coords.then_some(tex_coords);
+        mesh.normals = has_normals.then_some(normals);
+        mesh.colors = has_colors.then_some(colors);
+        mesh
+    }
+}
+
+impl From<&Mesh> for ObjModel {
+    /// Converts back for the OBJ, MTL and STL writers. Every channel shares
+    /// the vertex numbering; tangents are dropped.
+    fn from(mesh: &Mesh) -> Self {
+        let mut model = ObjModel::new();
+        model.vertices = mesh.positions.clone();
+        model.tex_coords = mesh.tex_coords.clone().unwrap_or_default();
+        model.normals = mesh.normals.clone().unwrap_or_default();
+        model.colors = mesh.colors.clone().unwrap_or_default();
+        model.materials = mesh.materials.clone();
+
+        for (t, triangle) in mesh.triangles().enumerate() {
+            let material = mesh
+                .submeshes
+                .iter()
+                .find(|s| s.triangles.contains(&t))
+                .and_then(|s| s.material);
+            model.faces.push(Face {
+                vertices: triangle
+                    .iter()
+                    .map(|&i| Vertex {
+                        position: i,
+                        tex_coord: mesh.tex_coords.as_ref().map(|_| i),
+                        normal: mesh.normals.as_ref().map(|_| i),
+                    })
+                    .collect(),
+                material,
+                smoothing_group: None,
+            });
+        }
+        model
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::mesh::{self, Mesh, MeshError, Submesh};
+    use crate::mtl::Material;
+    use crate::normals::{NormalOptions, NormalWeighting};
+    use crate::obj::ObjModel;
+    use crate::primitives;
+    use crate::vec3::Vec3;
+    use crate::vec4::Vec4;
+    use std::io::Cursor;
+
+    const FACES: &str = "\
+v 0 0 0
+v 1 0 0
+v 1 1 0
+v 0 1 0
+vt 0 0
+vt 1 0
+vt 1 1
+vt 0 1
+vn 0 0 1
+usemtl red
+f 1/1/1 2/2/1 3/3/1 4/4/1
+usemtl blue
+f 1/1/1 3/3/1 4/4/1
+f 3/1/1 2/2/1 1/3/1
+";
+
+    #[test]
+    fn test_from_obj() {
+        let model = ObjModel::from_reader(Cursor::new(FACES)).unwrap();
+        let mesh = Mesh::from(&model);
+        assert!(mesh.is_valid());
+
+        // The quad welds to four vertices; the last face reuses positions
+        // with different UVs, adding two vertices
+        assert_eq!(mesh.vertex_count(), 6);
+        assert_eq!(mesh.triangle_count(), 4);
+        assert_eq!(mesh.normals.as_ref().unwrap()[5], Vec3::new(0.0, 0.0, 1.0));
+        assert_eq!(
+            mesh.tex_coords.as_ref().unwrap()[5],
+            Vec3::new(1.0, 1.0, 0.0)
+        );
+        assert_eq!(mesh.colors, None);
+
+        assert_eq!(
+            mesh.submeshes,
+            vec![
+                Submesh {
+                    triangles: 0..2,
+                    material: Some(0)
+                },
+                Submesh {
+                    triangles: 2..4,
+                    material: Some(1)
+                },
+            ]
+        );
+        assert_eq!(mesh.triangle_material(3).unwrap().name, "blue");
+        let [a, b, c] = mesh.triangle(3);
+        assert_eq!((a, c), (4, 5));
+        assert_eq!(mesh.positions[b], Vec3::new(1.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let mut mesh = Mesh::new();
+        mesh.positions = vec![
+            Vec3::new(0.0, 0.0, 0.0),
+            Vec3::new(1.0, 0.0, 0.0),
+            Vec3::new(0.0, 1.0, 0.0),
+        ];
+        mesh.normals = Some(vec![Vec3::new(0.0, 0.0, 1.0); 3]);
+        mesh.push_triangle(0, 1, 2);
+        mesh.materials.push(Material::new("paint"));
+        mesh.submeshes.push(Submesh {
+            triangles: 0..1,
+            material: Some(0),
+        });
+
+        let model = ObjModel::from(&mesh);
+        assert!(model.tex_coords.is_empty());
+        assert_eq!(
+            model.get_triangle_material(&model.faces[0]).unwrap().name,
+            "paint"
+        );
+        assert_eq!(Mesh::from(&model), mesh);
+    }
+
+    #[test]
+    fn test_calculate_normals() {
+        // A cube with eight shared corners, six of them split by a second UV
+        let mut src = String::from("vt 0 0\nvt 1 1\n");
+        for i in 0..8 {
+            src += &format!("v {} {} {}\n", i & 1, (i >> 1) & 1, (i >> 2) & 1);
+        }
+        src += "f 1/1 3/1 4/1 2/1\nf 5/1 6/1 8/1 7/1\nf 1/1 2/1 6/1 5/1\n\
+                f 3/2 7/2 8/2 4/2\nf 1/1 5/1 7/1 3/1\nf 2/2 4/2 8/2 6/2\n";
+        let model = ObjModel::from_reader(Cursor::new(src)).unwrap();
+        let mut mesh = Mesh::from(&model);
+        assert_eq!(mesh.vertex_count(), 14);
+
+        // Smooth normals point out of the corners, across the UV seam too.
+        // Only angle weighting is blind to how the quads were split.
+        let angle = NormalOptions {
+            weighting: NormalWeighting::Angle,
+            ..NormalOptions::default()
+        };
+        mesh.calculate_normals(&angle);
+        assert_eq!(mesh.vertex_count(), 14);
+        for (p, n) in mesh.positions.iter().zip(mesh.normals.as_ref().unwrap()) {
+            let diagonal = (*p - Vec3::new(0.5, 0.5, 0.5)).norm();
+            assert!((*n - diagonal).len() < 1e-5, "{:?} at {:?}", n, p);
+        }
+
+        // A crease below 90 degrees splits every corner three ways
+        mesh.calculate_normals(&NormalOptions {
+            crease_angle: 1.0,
+            ..angle
+        });
+        assert!(mesh.is_valid());
+        assert_eq!(mesh.vertex_count(), 24);
+        for [a, b, c] in mesh.triangles() {
+            let normals = mesh.normals.as_ref().unwrap();
+            let (pa, pb, pc) = (mesh.positions[a], mesh.positions[b], mesh.positions[c]);
+            let face = (pb - pa).cross(&(pc - pa)).norm();
+            assert_eq!([normals[a], normals[b], normals[c]], [face; 3]);
+        }
+    }
+
+    #[test]
+    fn test_generate_tangents() {
+        // Torus tangents follow increasing u around the y axis. Vertices on
+        // the seam only see one side, so they lean by half a segment.
+        let mut mesh = primitives::torus(2.0, 0.5, 32, 16);
+        mesh.tangents = None;
+        let vertices = mesh.vertex_count();
+        assert!(mesh.generate_tangents());
+        assert!(mesh.is_valid());
+        assert_eq!(mesh.vertex_count(), vertices);
+
+        let tangents = mesh.tangents.as_ref().unwrap();
+        for (p, t) in mesh.positions.iter().zip(tangents) {
+            let phi = (-p.z).atan2(p.x);
+            let expected = Vec3::new(-phi.sin(), 0.0, -phi.cos());
+            assert!(t.xyz().dot(&expected) > 0.99, "{:?} at {:?}", t, p);
+            assert_eq!(t.w, 1.0);
+        }
+
+        // Normals and UVs carried over from an OBJ file are enough
+        let src = "v 0 0 0\nv 1 0 0\nv 0 1 0\nvt 1 0\nvt 0 0\nvt 1 1\nvn 0 0 1\n\
+                   f 1/1/1 2/2/1 3/3/1\n";
+        let mut mesh = Mesh::from(&ObjModel::from_reader(Cursor::new(src)).unwrap());
+        assert!(mesh.generate_tangents());
+        assert_eq!(
+            mesh.tangents.as_ref().unwrap()[0],
+            Vec4::new(-1.0, 0.0, 0.0, -1.0)
+        );
+
+        mesh.normals = None;
+        assert!(!mesh.generate_tangents());
+    }
+
+    #[test]
+    fn test_load_by_extension() {
+        let path = std::env::temp_dir().join("mesh_test_load.stl");
+        let model =
+            ObjModel::from_reader(Cursor::new("v 0 0 0\nv 1 0 0\nv 0 1 0\nf 1 2 3\n")).unwrap();
+        crate::stl::save(&model, &path, Default::default()).unwrap();
+        let mesh = mesh::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(mesh.triangle_count(), 1);
+        assert_eq!(mesh.vertex_count(), 3);
+        assert!(matches!(
+            mesh::load("mesh.xyz"),
+            Err(MeshError::UnknownFormat(_))
+        ));
+    }
+}
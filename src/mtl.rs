@@ -0,0 +1,217 @@
+use crate::vec3::Vec3;
+use crate::vec4::Vec4;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+/// A material from a Wavefront `.mtl` library.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Material {
+    pub name: String,
+    pub ambient: Vec3,  // Ka
+    pub diffuse: Vec3,  // Kd
+    pub specular: Vec3, // Ks
+    pub shininess: f32, // Ns
+    pub dissolve: f32,  // d, or 1 - Tr
+    pub illum: u32,
+    pub diffuse_map: Option<PathBuf>,  // map_Kd
+    pub bump_map: Option<PathBuf>,     // map_Bump or bump
+    pub specular_map: Option<PathBuf>, // map_Ks
+    pub alpha_map: Option<PathBuf>,    // map_d
+    pub pbr: Option<Pbr>,              // glTF materials only
+}
+
+/// Metallic-roughness parameters of a glTF material. Texture paths are only
+/// set for images stored in separate files.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Pbr {
+    pub base_color: Vec4,
+    pub metallic: f32,
+    pub roughness: f32,
+    pub emissive: Vec3,
+    pub base_color_map: Option<PathBuf>,
+    pub metallic_roughness_map: Option<PathBuf>,
+    pub normal_map: Option<PathBuf>,
+    pub occlusion_map: Option<PathBuf>,
+    pub emissive_map: Option<PathBuf>,
+}
+
+impl Default for Pbr {
+    fn default() -> Self {
+        Self {
+            base_color: Vec4::new(1.0, 1.0, 1.0, 1.0),
+            metallic: 1.0,
+            roughness: 1.0,
+            emissive: Vec3::new(0.0, 0.0, 0.0),
+            base_color_map: None,
+            metallic_roughness_map: None,
+            normal_map: None,
+            occlusion_map: None,
+            emissive_map: None,
+        }
+    }
+}
+
+impl Material {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            ambient: Vec3::new(0.0, 0.0, 0.0),
+            diffuse: Vec3::new(0.8, 0.8, 0.8),
+            specular: Vec3::new(0.0, 0.0, 0.0),
+            shininess: 0.0,
+            dissolve: 1.0,
+            illum: 1,
+            diffuse_map: None,
+            bump_map: None,
+            specular_map: None,
+            alpha_map: None,
+            pbr: None,
+        }
+    }
+}
+
+/// Loads a material library. Texture paths are resolved against the
+/// library's directory.
+pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Vec<Material>> {
+    let path = path.as_ref();
+    let file = File::open(path)?;
+    let base = path.parent().unwrap_or(Path::new(""));
+    from_reader(BufReader::new(file), base)
+}
+
+/// Parses a material library, resolving texture paths against `base`.
+pub fn from_reader<R: BufRead>(reader: R, base: &Path) -> io::Result<Vec<Material>> {
+    let mut materials: Vec<Material> = Vec::new();
+
+    for line in reader.lines() {
+        let line = line?;
+        let line = line.trim();
+
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let parts: Vec<&str> = line.split_whitespace().collect();
+        if parts[0] == "newmtl" {
+            materials.push(Material::new(parts.get(1).copied().unwrap_or("")));
+            continue;
+        }
+
+        // Statements before the first newmtl have nothing to apply to
+        let Some(material) = materials.last_mut() else {
+            continue;
+        };
+
+        let float = |i: usize| parts.get(i).and_then(|p| p.parse::<f32>().ok());
+        let color = || {
+            let r = float(1)?;
+            // A single value sets all three channels
+            Some(Vec3::new(r, float(2).unwrap_or(r), float(3).unwrap_or(r)))
+        };
+        // Map statements may carry options such as "-bm 1.0" before the
+        // file name, which always comes last
+        let map = || (parts.len() >= 2).then(|| base.join(parts[parts.len() - 1]));
+
+        match parts[0] {
+            "Ka" => material.ambient = color().unwrap_or(material.ambient),
+            "Kd" => material.diffuse = color().unwrap_or(material.diffuse),
+            "Ks" => material.specular = color().unwrap_or(material.specular),
+            "Ns" => material.shininess = float(1).unwrap_or(material.shininess),
+            "d" => material.dissolve = float(1).unwrap_or(material.dissolve),
+            "Tr" => material.dissolve = float(1).map_or(material.dissolve, |t| 1.0 - t),
+            "illum" => {
+                material.illum = parts
+                    .get(1)
+                    .and_then(|p| p.parse().ok())
+                    .unwrap_or(material.illum)
+            }
+            "map_Kd" => material.diffuse_map = map(),
+            "map_Bump" | "map_bump" | "bump" => material.bump_map = map(),
+            "map_Ks" => material.specular_map = map(),
+            "map_d" => material.alpha_map = map(),
+            _ => {}
+        }
+    }
+
+    Ok(materials)
+}
+
+/// Writes a material library. Texture paths under the library's directory
+/// are written relative to it.
+pub fn save<P: AsRef<Path>>(materials: &[Material], path: P) -> io::Result<()> {
+    let path = path.as_ref();
+    let base = path.parent().unwrap_or(Path::new(""));
+    write_to(materials, BufWriter::new(File::create(path)?), base)
+}
+
+pub fn write_to<W: Write>(materials: &[Material], mut w: W, base: &Path) -> io::Result<()> {
+    for (i, m) in materials.iter().enumerate() {
+        if i > 0 {
+            writeln!(w)?;
+        }
+        writeln!(w, "newmtl {}", m.name)?;
+        writeln!(w, "Ka {} {} {}", m.ambient.x, m.ambient.y, m.ambient.z)?;
+        writeln!(w, "Kd {} {} {}", m.diffuse.x, m.diffuse.y, m.diffuse.z)?;
+        writeln!(w, "Ks {} {} {}", m.specular.x, m.specular.y, m.specular.z)?;
+        writeln!(w, "Ns {}", m.shininess)?;
+        writeln!(w, "d {}", m.dissolve)?;
+        writeln!(w, "illum {}", m.illum)?;
+
+        let maps = [
+            ("map_Kd", &m.diffuse_map),
+            ("map_Bump", &m.bump_map),
+            ("map_Ks", &m.specular_map),
+            ("map_d", &m.alpha_map),
+        ];
+        for (statement, map) in maps {
+            if let Some(map) = map {
+                let map = map.strip_prefix(base).unwrap_or(map);
+                writeln!(w, "{} {}", statement, map.display())?;
+            }
+        }
+    }
+
+    w.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::mtl::{self, Material};
+    use crate::vec3::Vec3;
+    use std::path::{Path, PathBuf};
+
+    #[test]
+    fn test_parse_materials() {
+        let src = "# two materials\n\
+                   newmtl red\n\
+                   Ka 0.1 0.1 0.1\nKd 1 0 0\nKs 0.5\nNs 32\nd 0.5\nillum 2\n\
+                   map_Kd red.png\nmap_Bump -bm 0.5 bump.tga\n\
+                   newmtl glass\nTr 0.75\nmap_Ks spec.png\nmap_d alpha.png\n";
+        let materials = mtl::from_reader(src.as_bytes(), Path::new("models")).unwrap();
+
+        assert_eq!(materials.len(), 2);
+        let red = &materials[0];
+        assert_eq!(red.name, "red");
+        assert_eq!(red.ambient, Vec3::new(0.1, 0.1, 0.1));
+        assert_eq!(red.diffuse, Vec3::new(1.0, 0.0, 0.0));
+        assert_eq!(red.specular, Vec3::new(0.5, 0.5, 0.5));
+        assert_eq!(red.shininess, 32.0);
+        assert_eq!(red.dissolve, 0.5);
+        assert_eq!(red.illum, 2);
+        assert_eq!(red.diffuse_map, Some(PathBuf::from("models/red.png")));
+        assert_eq!(red.bump_map, Some(PathBuf::from("models/bump.tga")));
+
+        let glass = &materials[1];
+        assert_eq!(glass.dissolve, 0.25);
+        assert_eq!(glass.diffuse, Material::new("").diffuse);
+        assert_eq!(glass.specular_map, Some(PathBuf::from("models/spec.png")));
+        assert_eq!(glass.alpha_map, Some(PathBuf::from("models/alpha.png")));
+
+        let mut saved = Vec::new();
+        mtl::write_to(&materials, &mut saved, Path::new("models")).unwrap();
+        assert!(String::from_utf8_lossy(&saved).contains("map_Bump bump.tga\n"));
+        let reloaded = mtl::from_reader(saved.as_slice(), Path::new("models")).unwrap();
+        assert_eq!(reloaded, materials);
+    }
+}
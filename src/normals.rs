@@ -0,0 +1,198 @@
+//! Smooth vertex normals from triangle geometry, shared by `ObjModel` and
+//! `Mesh`.
+
+use crate::vec3::Vec3;
+
+/// How the normals of the faces around a vertex are weighted.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub enum NormalWeighting {
+    /// Every face counts the same.
+    Uniform,
+    /// Faces count by their area.
+    #[default]
+    Area,
+    /// Faces count by their angle at the vertex, which doesn't change when
+    /// the surface is triangulated differently.
+    Angle,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct NormalOptions {
+    pub weighting: NormalWeighting,
+    /// Faces bent further apart than this, in radians, keep separate
+    /// normals along their shared edges. `PI` smooths across every edge.
+    pub crease_angle: f32,
+}
+
+impl Default for NormalOptions {
+    fn default() -> Self {
+        Self {
+            weighting: NormalWeighting::Area,
+            crease_angle: std::f32::consts::PI,
+        }
+    }
+}
+
+/// Unit normal for every corner of `triangles`, which index `positions`.
+///
+/// Each corner averages the faces around its position that share its
+/// smoothing group and lie within the crease angle of its own face. A
+/// `groups` entry of `Some(0)` shades that triangle flat; an empty `groups`
+/// puts every triangle in one group. Zero-area faces add nothing, and a
+/// corner with nothing to average falls back to +z rather than NaN.
+pub(crate) fn corner_normals(
+    positions: &[Vec3],
+    triangles: &[[usize; 3]],
+    groups: &[Option<u32>],
+    options: &NormalOptions,
+) -> Vec<[Vec3; 3]> {
+    let group = |t: usize| groups.get(t).copied().flatten();
+    let flat = |t: usize| group(t) == Some(0);
+
+    let areas: Vec<Vec3> = triangles
+        .iter()
+        .map(|&[a, b, c]| (positions[b] - positions[a]).cross(&(positions[c] - positions[a])))
+        .collect();
+    let faces: Vec<Option<Vec3>> = areas.iter().map(Vec3::try_norm).collect();
+
+    let weight = |t: usize, k: usize| match options.weighting {
+        NormalWeighting::Uniform => 1.0,
+        NormalWeighting::Area => areas[t].len(),
+        NormalWeighting::Angle => {
+            let corner = triangles[t];
+            let p = positions[corner[k]];
+            let e1 = positions[corner[(k + 1) % 3]] - p;
+            let e2 = positions[corner[(k + 2) % 3]] - p;
+            e1.cross(&e2).len().atan2(e1.dot(&e2))
+        }
+    };
+
+    // Corners around each position
+    let mut incident: Vec<Vec<(usize, usize)>> = vec![Vec::new(); positions.len()];
+    for (t, triangle) in triangles.iter().enumerate() {
+        for (k, &p) in triangle.iter().enumerate() {
+            incident[p].push((t, k));
+        }
+    }
+
+    // Slightly lenient so faces exactly at the crease angle are smoothed
+    let min_cos = options.crease_angle.cos() - 1e-6;
+
+    (0..triangles.len())
+        .map(|t| {
+            let own = faces[t];
+            std::array::from_fn(|k| {
+                let mut sum = Vec3::new(0.0, 0.0, 0.0);
+                if !flat(t) {
+                    for &(u, j) in &incident[triangles[t][k]] {
+                        let Some(normal) = faces[u] else { continue };
+                        let smooth = own.is_none_or(|own| own.dot(&normal) >= min_cos);
+                        if smooth && !flat(u) && group(u) == group(t) {
+                            sum = sum + normal * weight(u, j);
+                        }
+                    }
+                }
+                // Opposite faces can cancel out; fall back to the face itself
+                sum.try_norm().or(own).unwrap_or(Vec3::new(0.0, 0.0, 1.0))
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::normals::{corner_normals, NormalOptions, NormalWeighting};
+    use crate::vec3::Vec3;
+    use std::f32::consts::{FRAC_1_SQRT_2, FRAC_PI_4};
+
+    fn assert_close(a: Vec3, b: Vec3) {
+        assert!((a - b).len() < 1e-5, "{:?} != {:?}", a, b);
+    }
+
+    // A triangle in the z = 0 plane and a long wall of two triangles in the
+    // x = 0 plane, folded along the y axis. Position 0 is in all three.
+    fn fold() -> (Vec<Vec3>, Vec<[usize; 3]>) {
+        let positions = vec![
+            Vec3::new(0.0, 0.0, 0.0),
+            Vec3::new(0.0, 1.0, 0.0),
+            Vec3::new(1.0, 0.0, 0.0),
+            Vec3::new(0.0, 0.0, 4.0),
+            Vec3::new(0.0, 1.0, 4.0),
+        ];
+        let triangles = vec![[0, 2, 1], [0, 1, 4], [0, 4, 3]];
+        (positions, triangles)
+    }
+
+    #[test]
+    fn test_weighting() {
+        let (positions, triangles) = fold();
+        let normal = |weighting| {
+            let options = NormalOptions {
+                weighting,
+                ..NormalOptions::default()
+            };
+            corner_normals(&positions, &triangles, &[], &options)[0][0]
+        };
+
+        // Floor faces +z, wall faces +x. By count the wall wins two to one
+        let uniform = normal(NormalWeighting::Uniform);
+        assert_close(uniform, Vec3::new(2.0, 0.0, 1.0).norm());
+
+        // The floor triangle has area 0.5, the wall triangles 2 each
+        let area = normal(NormalWeighting::Area);
+        assert_close(area, Vec3::new(4.0, 0.0, 0.5).norm());
+
+        // The floor corner spans 90 degrees and the wall corners add up to
+        // 90 as well
+        let angle = normal(NormalWeighting::Angle);
+        assert_close(angle, Vec3::new(FRAC_1_SQRT_2, 0.0, FRAC_1_SQRT_2));
+    }
+
+    #[test]
+    fn test_crease_and_groups() {
+        let (positions, triangles) = fold();
+        let options = NormalOptions {
+            weighting: NormalWeighting::Angle,
+            crease_angle: FRAC_PI_4,
+        };
+        let normals = corner_normals(&positions, &triangles, &[], &options);
+        assert_eq!(normals[0][0], Vec3::new(0.0, 0.0, 1.0));
+        assert_eq!(normals[1][0], Vec3::new(1.0, 0.0, 0.0));
+
+        // Exactly at the crease angle still smooths
+        let options = NormalOptions {
+            crease_angle: 2.0 * FRAC_PI_4,
+            ..options
+        };
+        let normals = corner_normals(&positions, &triangles, &[], &options);
+        assert_close(normals[0][0], Vec3::new(FRAC_1_SQRT_2, 0.0, FRAC_1_SQRT_2));
+
+        // Groups separate faces like a crease would; group 0 is flat
+        let groups = [Some(1), Some(2), Some(2)];
+        let normals = corner_normals(&positions, &triangles, &groups, &options);
+        assert_eq!(normals[0][0], Vec3::new(0.0, 0.0, 1.0));
+        let groups = [Some(1), Some(0), Some(1)];
+        let normals = corner_normals(&positions, &triangles, &groups, &options);
+        assert_eq!(normals[1][0], Vec3::new(1.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn test_degenerate() {
+        // A collapsed triangle next to a real one, and one on its own
+        let positions = vec![
+            Vec3::new(0.0, 0.0, 0.0),
+            Vec3::new(1.0, 0.0, 0.0),
+            Vec3::new(0.0, 1.0, 0.0),
+            Vec3::new(2.0, 0.0, 0.0),
+            Vec3::new(5.0, 5.0, 5.0),
+        ];
+        let triangles = [[0, 1, 2], [0, 1, 3], [4, 4, 4]];
+        let normals = corner_normals(&positions, &triangles, &[], &NormalOptions::default());
+
+        assert_eq!(normals[1], [Vec3::new(0.0, 0.0, 1.0); 3]);
+        assert_eq!(normals[2], [Vec3::new(0.0, 0.0, 1.0); 3]);
+        for n in normals.iter().flatten() {
+            assert!((n.len() - 1.0).abs() < 1e-6);
+        }
+    }
+}
@@ -1,140 +1,587 @@
+use crate::mtl::{self, Material};
+use crate::normals::{self, NormalOptions};
+use crate::triangulate::triangulate;
 use crate::vec3::Vec3;
+use crate::vec4::Vec4;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
 use std::fs::File;
-use std::io::{self, BufRead, BufReader};
-use std::path::Path;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
 
-#[derive(Debug, Clone)]
+/// One face corner: a `v/vt/vn` tuple of 0-based indices.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
 pub struct Vertex {
-    pub position: Vec3,
-    pub tex_coord: Option<Vec3>, // Using Vec3 for simplicity, z is usually 0
-    pub normal: Option<Vec3>,
+    pub position: usize,          // Index into vertices
+    pub tex_coord: Option<usize>, // Index into tex_coords
+    pub normal: Option<usize>,    // Index into normals
 }
 
-#[derive(Debug, Clone)]
+impl Vertex {
+    pub fn new(position: usize) -> Self {
+        Self {
+            position,
+            tex_coord: None,
+            normal: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub struct Face {
-    pub vertices: Vec<usize>, // Indices into the vertex list
+    pub vertices: Vec<Vertex>,
+    pub material: Option<usize>,      // Index into materials
+    pub smoothing_group: Option<u32>, // From `s`; 0 means off, None if unset or after a bare `s`
 }
 
-#[derive(Debug, Clone)]
+/// A named object (`o`) or group (`g`) covering a run of faces. Groups can
+/// overlap, since one `g` statement may name several.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Group {
+    pub name: String,
+    pub faces: Range<usize>, // Indices into faces
+}
+
+/// Makes `names` the open groups from face `at` on. Groups are open, with an
+/// end of `usize::MAX`, while being parsed. Open groups that are named again
+/// carry on, the rest end at `at`, and no names, as in a bare `g`, ends them
+/// all.
+fn start_groups(groups: &mut Vec<Group>, names: &[&str], at: usize) {
+    for group in groups.iter_mut().filter(|g| g.faces.end == usize::MAX) {
+        if !names.contains(&group.name.as_str()) {
+            group.faces.end = at;
+        }
+    }
+    for &name in names {
+        if !groups.iter().any(|g| g.faces.end == usize::MAX && g.name == name) {
+            groups.push(Group { name: name.to_string(), faces: at..usize::MAX });
+        }
+    }
+}
+
+/// Ends the open groups at `at` and drops groups without faces.
+fn finish_groups(groups: &mut Vec<Group>, at: usize) {
+    start_groups(groups, &[], at);
+    groups.retain(|g| !g.faces.is_empty());
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub struct ObjModel {
     pub vertices: Vec<Vec3>,
     pub tex_coords: Vec<Vec3>,
     pub normals: Vec<Vec3>,
+    pub colors: Vec<Vec4>, // RGBA per position, or empty
     pub faces: Vec<Face>,
+    pub materials: Vec<Material>,
+    pub objects: Vec<Group>,
+    pub groups: Vec<Group>,
 }
 
-impl ObjModel {
-    pub fn new() -> Self {
+/// How malformed OBJ input is handled.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub enum ParseMode {
+    /// Fail on the first problem.
+    Strict,
+    /// Keep going and report each problem as a warning. Unparsable numbers
+    /// become 0, statements missing arguments and faces with bad position
+    /// indices are skipped, and bad UV or normal indices are dropped.
+    #[default]
+    Lenient,
+}
+
+/// A problem found while loading an OBJ file. Lines and columns are 1-based;
+/// columns count bytes.
+#[derive(Debug)]
+pub enum ObjError {
+    Io(io::Error),
+    InvalidNumber { line: usize, column: usize, token: String },
+    MissingArgument { line: usize, column: usize, statement: String },
+    IndexOutOfRange { line: usize, column: usize, index: isize },
+    MaterialLibrary { line: usize, column: usize, path: PathBuf, source: io::Error },
+}
+
+impl ObjError {
+    pub fn line(&self) -> Option<usize> {
+        match *self {
+            ObjError::Io(_) => None,
+            ObjError::InvalidNumber { line, .. }
+            | ObjError::MissingArgument { line, .. }
+            | ObjError::IndexOutOfRange { line, .. }
+            | ObjError::MaterialLibrary { line, .. } => Some(line),
+        }
+    }
+
+    pub fn column(&self) -> Option<usize> {
+        match *self {
+            ObjError::Io(_) => None,
+            ObjError::InvalidNumber { column, .. }
+            | ObjError::MissingArgument { column, .. }
+            | ObjError::IndexOutOfRange { column, .. }
+            | ObjError::MaterialLibrary { column, .. } => Some(column),
+        }
+    }
+}
+
+impl fmt::Display for ObjError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let (Some(line), Some(column)) = (self.line(), self.column()) {
+            write!(f, "line {}, column {}: ", line, column)?;
+        }
+        match self {
+            ObjError::Io(e) => write!(f, "{}", e),
+            ObjError::InvalidNumber { token, .. } => write!(f, "invalid number `{}`", token),
+            ObjError::MissingArgument { statement, .. } => write!(f, "missing argument to `{}`", statement),
+            ObjError::IndexOutOfRange { index, .. } => write!(f, "index {} out of range", index),
+            ObjError::MaterialLibrary { path, source, .. } => {
+                write!(f, "cannot read material library {}: {}", path.display(), source)
+            }
+        }
+    }
+}
+
+impl Error for ObjError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ObjError::Io(e) | ObjError::MaterialLibrary { source: e, .. } => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for ObjError {
+    fn from(e: io::Error) -> Self {
+        ObjError::Io(e)
+    }
+}
+
+/// Settings for `ObjModel::save`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SaveOptions {
+    /// Digits after the decimal point, or `None` for the shortest text that
+    /// reads back as the same `f32`.
+    pub precision: Option<usize>,
+    /// Library named in a `mtllib` statement; see `mtl::save`.
+    pub mtllib: Option<String>,
+    /// Write `usemtl` statements for face materials.
+    pub materials: bool,
+    /// Write `o`, `g` and `s` statements.
+    pub groups: bool,
+}
+
+impl Default for SaveOptions {
+    fn default() -> Self {
         Self {
-            vertices: Vec::new(),
-            tex_coords: Vec::new(),
-            normals: Vec::new(),
-            faces: Vec::new(),
+            precision: None,
+            mtllib: None,
+            materials: true,
+            groups: true,
         }
     }
+}
 
-    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Self> {
-        let file = File::open(path)?;
-        let reader = BufReader::new(file);
+/// A whitespace-separated token and its column.
+type Token<'a> = (usize, &'a str);
+
+/// Line-by-line OBJ reader that applies a `ParseMode` to every problem.
+struct Parser {
+    mode: ParseMode,
+    warnings: Vec<ObjError>,
+    line: usize,
+}
+
+impl Parser {
+    fn new(mode: ParseMode) -> Self {
+        Self { mode, warnings: Vec::new(), line: 0 }
+    }
+
+    /// Fails in strict mode, records a warning otherwise.
+    fn report(&mut self, error: ObjError) -> Result<(), ObjError> {
+        match self.mode {
+            ParseMode::Strict => Err(error),
+            ParseMode::Lenient => {
+                self.warnings.push(error);
+                Ok(())
+            }
+        }
+    }
+
+    /// Checks that `parts` holds a statement and at least `count` arguments.
+    fn arguments(&mut self, parts: &[Token], count: usize, end: usize) -> Result<bool, ObjError> {
+        if parts.len() > count {
+            return Ok(true);
+        }
+        self.report(ObjError::MissingArgument {
+            line: self.line,
+            column: end,
+            statement: parts[0].1.to_string(),
+        })?;
+        Ok(false)
+    }
+
+    fn float(&mut self, (column, token): Token) -> Result<f32, ObjError> {
+        match token.parse() {
+            Ok(value) => Ok(value),
+            Err(_) => {
+                self.report(ObjError::InvalidNumber { line: self.line, column, token: token.to_string() })?;
+                Ok(0.0)
+            }
+        }
+    }
+
+    /// Converts an index into `0..count`, or `None` if it is invalid.
+    /// Positive indices are 1-based; negative ones count back from the end of
+    /// the list as read so far, so -1 is the latest element.
+    fn index(&mut self, (column, token): Token, count: usize) -> Result<Option<usize>, ObjError> {
+        let Ok(index) = token.parse::<isize>() else {
+            self.report(ObjError::InvalidNumber { line: self.line, column, token: token.to_string() })?;
+            return Ok(None);
+        };
+
+        let resolved = if index < 0 {
+            count.checked_add_signed(index)
+        } else {
+            (index as usize).checked_sub(1)
+        };
+        match resolved.filter(|&i| i < count) {
+            Some(i) => Ok(Some(i)),
+            None => {
+                self.report(ObjError::IndexOutOfRange { line: self.line, column, index })?;
+                Ok(None)
+            }
+        }
+    }
+
+    /// Parses a `v`, `v/vt`, `v//vn` or `v/vt/vn` face corner. Returns `None`
+    /// if the position index is invalid.
+    fn corner(&mut self, model: &ObjModel, (column, token): Token) -> Result<Option<Vertex>, ObjError> {
+        let mut fields = Vec::new();
+        let mut offset = 0;
+        for field in token.split('/') {
+            fields.push((column + offset, field));
+            offset += field.len() + 1;
+        }
+
+        let Some(position) = self.index(fields[0], model.vertices.len())? else {
+            return Ok(None);
+        };
+        let mut vertex = Vertex::new(position);
+
+        // Texture coordinate and normal indices are optional
+        if let Some(&field) = fields.get(1).filter(|f| !f.1.is_empty()) {
+            vertex.tex_coord = self.index(field, model.tex_coords.len())?;
+        }
+        if let Some(&field) = fields.get(2).filter(|f| !f.1.is_empty()) {
+            vertex.normal = self.index(field, model.normals.len())?;
+        }
+
+        Ok(Some(vertex))
+    }
+
+    fn parse<R: BufRead>(mut self, reader: R, base: Option<&Path>) -> Result<(ObjModel, Vec<ObjError>), ObjError> {
         let mut model = ObjModel::new();
+        let mut material = None;
+        let mut smoothing_group = None;
 
         for line in reader.lines() {
             let line = line?;
-            let line = line.trim();
+            self.line += 1;
             
             // Skip empty lines and comments
-            if line.is_empty() || line.starts_with('#') {
+            let trimmed = line.trim();
+            if trimmed.is_empty() || trimmed.starts_with('#') {
                 continue;
             }
 
-            let parts: Vec<&str> = line.split_whitespace().collect();
-            if parts.is_empty() {
-                continue;
-            }
+            let parts: Vec<Token> = line
+                .split_whitespace()
+                .map(|t| (t.as_ptr() as usize - line.as_ptr() as usize + 1, t))
+                .collect();
+            let end = line.trim_end().len() + 1;
 
-            match parts[0] {
-                "v" => {
+            match parts[0].1 {
+                "v" if self.arguments(&parts, 3, end)? => {
                     // Vertex position: v x y z [w]
-                    if parts.len() >= 4 {
-                        let x: f32 = parts[1].parse().unwrap_or(0.0);
-                        let y: f32 = parts[2].parse().unwrap_or(0.0);
-                        let z: f32 = parts[3].parse().unwrap_or(0.0);
-                        model.vertices.push(Vec3::new(x, y, z));
-                    }
+                    let x = self.float(parts[1])?;
+                    let y = self.float(parts[2])?;
+                    let z = self.float(parts[3])?;
+                    model.vertices.push(Vec3::new(x, y, z));
                 }
-                "vt" => {
-                    // Texture coordinate: vt u v [w]
-                    if parts.len() >= 3 {
-                        let u: f32 = parts[1].parse().unwrap_or(0.0);
-                        let v: f32 = parts[2].parse().unwrap_or(0.0);
-                        let w: f32 = if parts.len() >= 4 {
-                            parts[3].parse().unwrap_or(0.0)
-                        } else {
-                            0.0
-                        };
-                        model.tex_coords.push(Vec3::new(u, v, w));
-                    }
+                "vt" if self.arguments(&parts, 1, end)? => {
+                    // Texture coordinate: vt u [v [w]]
+                    let u = self.float(parts[1])?;
+                    let v = parts.get(2).map_or(Ok(0.0), |&p| self.float(p))?;
+                    let w = parts.get(3).map_or(Ok(0.0), |&p| self.float(p))?;
+                    model.tex_coords.push(Vec3::new(u, v, w));
                 }
-                "vn" => {
+                "vn" if self.arguments(&parts, 3, end)? => {
                     // Normal: vn x y z
-                    if parts.len() >= 4 {
-                        let x: f32 = parts[1].parse().unwrap_or(0.0);
-                        let y: f32 = parts[2].parse().unwrap_or(0.0);
-                        let z: f32 = parts[3].parse().unwrap_or(0.0);
-                        model.normals.push(Vec3::new(x, y, z));
-                    }
+                    let x = self.float(parts[1])?;
+                    let y = self.float(parts[2])?;
+                    let z = self.float(parts[3])?;
+                    model.normals.push(Vec3::new(x, y, z));
                 }
                 "f" => {
                     // Face: f v1/vt1/vn1 v2/vt2/vn2 v3/vt3/vn3 ...
-                    if parts.len() >= 4 {
-                        let mut face_vertices = Vec::new();
-                        
-                        for i in 1..parts.len() {
-                            let vertex_data: Vec<&str> = parts[i].split('/').collect();
-                            
-                            // Parse vertex index (required)
-                            let vertex_idx: usize = if !vertex_data.is_empty() {
-                                vertex_data[0].parse().unwrap_or(1)
-                            } else {
-                                1
-                            };
-                            
-                            // OBJ indices are 1-based, convert to 0-based
-                            let vertex_idx = if vertex_idx > 0 {
-                                vertex_idx - 1
-                            } else {
-                                model.vertices.len() + vertex_idx
-                            };
-                            
-                            face_vertices.push(vertex_idx);
+                    if !self.arguments(&parts, 3, end)? {
+                        continue;
+                    }
+
+                    let mut face_vertices = Vec::new();
+                    let mut valid = true;
+                    for &part in &parts[1..] {
+                        match self.corner(&model, part)? {
+                            Some(vertex) => face_vertices.push(vertex),
+                            None => valid = false,
                         }
-                        
-                        // Triangulate the face if it has more than 3 vertices
-                        if face_vertices.len() == 3 {
+                    }
+                    if !valid {
+                        continue;
+                    }
+                    
+                    // Triangulate the face if it has more than 3 vertices
+                    if face_vertices.len() == 3 {
+                        model.faces.push(Face { vertices: face_vertices, material, smoothing_group });
+                    } else {
+                        // Ear clipping copes with concave polygons
+                        let points: Vec<Vec3> = face_vertices.iter().map(|v| model.vertices[v.position]).collect();
+                        for [a, b, c] in triangulate(&points) {
                             model.faces.push(Face {
-                                vertices: face_vertices,
+                                vertices: vec![face_vertices[a], face_vertices[b], face_vertices[c]],
+                                material,
+                                smoothing_group,
                             });
-                        } else if face_vertices.len() > 3 {
-                            // Simple triangulation: fan triangulation
-                            for i in 1..face_vertices.len() - 1 {
-                                model.faces.push(Face {
-                                    vertices: vec![
-                                        face_vertices[0],
-                                        face_vertices[i],
-                                        face_vertices[i + 1],
-                                    ],
-                                });
+                        }
+                    }
+                }
+                "mtllib" => {
+                    let Some(base) = base else { continue };
+                    for &(column, name) in &parts[1..] {
+                        let path = base.join(name);
+                        match mtl::load(&path) {
+                            Ok(materials) => {
+                                for m in materials {
+                                    model.add_material(m);
+                                }
+                            }
+                            // Without the library usemtl falls back to default
+                            // materials
+                            Err(source) => {
+                                self.report(ObjError::MaterialLibrary { line: self.line, column, path, source })?
                             }
                         }
                     }
                 }
+                // A bare `s` goes back to no smoothing group, as `write_to`
+                // writes it
+                "s" if parts.len() == 1 => smoothing_group = None,
+                "usemtl" if self.arguments(&parts, 1, end)? => {
+                    let name = parts[1].1;
+                    let index = match model.material_index(name) {
+                        Some(index) => index,
+                        None => model.add_material(Material::new(name)),
+                    };
+                    material = Some(index);
+                }
+                "o" => {
+                    // Object names may contain spaces
+                    let name = parts[1..].iter().map(|p| p.1).collect::<Vec<_>>().join(" ");
+                    let names = if name.is_empty() { vec![] } else { vec![name.as_str()] };
+                    start_groups(&mut model.objects, &names, model.faces.len());
+                }
+                "g" => {
+                    // Faces belong to every group named
+                    let names: Vec<&str> = parts[1..].iter().map(|p| p.1).collect();
+                    start_groups(&mut model.groups, &names, model.faces.len());
+                }
+                "s" if self.arguments(&parts, 1, end)? => {
+                    // "s off" is the same as "s 0"
+                    smoothing_group = match parts[1] {
+                        (_, "off") => Some(0),
+                        (column, token) => match token.parse() {
+                            Ok(group) => Some(group),
+                            Err(_) => {
+                                let token = token.to_string();
+                                self.report(ObjError::InvalidNumber { line: self.line, column, token })?;
+                                Some(0)
+                            }
+                        },
+                    };
+                }
                 _ => {
-                    // Skip other commands (mtl, g, s, etc.)
+                    // Skip other commands
+                }
+            }
+        }
+
+        finish_groups(&mut model.objects, model.faces.len());
+        finish_groups(&mut model.groups, model.faces.len());
+
+        Ok((model, self.warnings))
+    }
+}
+
+impl Default for ObjModel {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ObjModel {
+    pub fn new() -> Self {
+        Self {
+            vertices: Vec::new(),
+            tex_coords: Vec::new(),
+            normals: Vec::new(),
+            colors: Vec::new(),
+            faces: Vec::new(),
+            materials: Vec::new(),
+            objects: Vec::new(),
+            groups: Vec::new(),
+        }
+    }
+
+    /// Loads a model along with any `mtllib` libraries next to it. Malformed
+    /// input is handled as in `ParseMode::Lenient`, without the warnings.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, ObjError> {
+        Ok(Self::load_with(path, ParseMode::Lenient)?.0)
+    }
+
+    /// Loads a model, returning it with the problems skipped in lenient mode.
+    pub fn load_with<P: AsRef<Path>>(path: P, mode: ParseMode) -> Result<(Self, Vec<ObjError>), ObjError> {
+        let path = path.as_ref();
+        let file = File::open(path)?;
+        Parser::new(mode).parse(BufReader::new(file), path.parent())
+    }
+
+    /// Parses a model from memory. `mtllib` statements are skipped as there is
+    /// no directory to resolve them against, but `usemtl` still assigns
+    /// materials by name.
+    pub fn from_reader<R: BufRead>(reader: R) -> Result<Self, ObjError> {
+        Ok(Self::from_reader_with(reader, ParseMode::Lenient)?.0)
+    }
+
+    pub fn from_reader_with<R: BufRead>(reader: R, mode: ParseMode) -> Result<(Self, Vec<ObjError>), ObjError> {
+        Parser::new(mode).parse(reader, None)
+    }
+
+    /// Writes the model as OBJ text. Faces without a material are written
+    /// before the others, as OBJ cannot switch back to no material.
+    pub fn save<P: AsRef<Path>>(&self, path: P, options: &SaveOptions) -> io::Result<()> {
+        self.write_to(BufWriter::new(File::create(path)?), options)
+    }
+
+    pub fn write_to<W: Write>(&self, mut w: W, options: &SaveOptions) -> io::Result<()> {
+        let float = |x: f32| match options.precision {
+            Some(precision) => format!("{:.*}", precision, x),
+            None => format!("{}", x),
+        };
+
+        if let Some(mtllib) = &options.mtllib {
+            writeln!(w, "mtllib {}", mtllib)?;
+        }
+
+        for v in &self.vertices {
+            writeln!(w, "v {} {} {}", float(v.x), float(v.y), float(v.z))?;
+        }
+        for t in &self.tex_coords {
+            if t.z == 0.0 {
+                writeln!(w, "vt {} {}", float(t.x), float(t.y))?;
+            } else {
+                writeln!(w, "vt {} {} {}", float(t.x), float(t.y), float(t.z))?;
+            }
+        }
+        for n in &self.normals {
+            writeln!(w, "vn {} {} {}", float(n.x), float(n.y), float(n.z))?;
+        }
+
+        // OBJ has no statement to go back to no material, so faces without
+        // one go first, before any `usemtl`
+        let mut order: Vec<usize> = (0..self.faces.len()).collect();
+        if options.materials {
+            order.sort_by_key(|&i| self.get_triangle_material(&self.faces[i]).is_some());
+        }
+
+        let mut material = None;
+        let mut smoothing_group = None;
+        let mut names = [Vec::new(), Vec::new()];
+        for i in order {
+            let face = &self.faces[i];
+            if options.groups {
+                let statements = [("o", &self.objects), ("g", &self.groups)];
+                for ((statement, groups), names) in statements.into_iter().zip(&mut names) {
+                    // Name every group the face is in; a bare statement ends
+                    // them all
+                    let face_names: Vec<&str> = groups
+                        .iter()
+                        .filter(|g| g.faces.contains(&i))
+                        .map(|g| g.name.as_str())
+                        .collect();
+                    if face_names != *names {
+                        write!(w, "{}", statement)?;
+                        for name in &face_names {
+                            write!(w, " {}", name)?;
+                        }
+                        writeln!(w)?;
+                        *names = face_names;
+                    }
                 }
+
+                if face.smoothing_group != smoothing_group {
+                    match face.smoothing_group {
+                        Some(0) => writeln!(w, "s off")?,
+                        Some(group) => writeln!(w, "s {}", group)?,
+                        None => writeln!(w, "s")?,
+                    }
+                    smoothing_group = face.smoothing_group;
+                }
+            }
+
+            if options.materials && face.material != material {
+                if let Some(m) = self.get_triangle_material(face) {
+                    writeln!(w, "usemtl {}", m.name)?;
+                }
+                material = face.material;
+            }
+
+            write!(w, "f")?;
+            for vertex in &face.vertices {
+                // OBJ indices are 1-based
+                write!(w, " {}", vertex.position + 1)?;
+                match (vertex.tex_coord, vertex.normal) {
+                    (Some(t), Some(n)) => write!(w, "/{}/{}", t + 1, n + 1)?,
+                    (Some(t), None) => write!(w, "/{}", t + 1)?,
+                    (None, Some(n)) => write!(w, "//{}", n + 1)?,
+                    (None, None) => {}
+                }
+            }
+            writeln!(w)?;
+        }
+
+        w.flush()
+    }
+
+    pub fn material_index(&self, name: &str) -> Option<usize> {
+        self.materials.iter().position(|m| m.name == name)
+    }
+
+    /// Adds a material, replacing any with the same name, and returns its index.
+    pub fn add_material(&mut self, material: Material) -> usize {
+        match self.material_index(&material.name) {
+            Some(index) => {
+                self.materials[index] = material;
+                index
+            }
+            None => {
+                self.materials.push(material);
+                self.materials.len() - 1
             }
         }
+    }
 
-        Ok(model)
+    pub fn get_triangle_material(&self, face: &Face) -> Option<&Material> {
+        self.materials.get(face.material?)
     }
 
     pub fn get_triangle_vertices(&self, face: &Face) -> Option<(Vec3, Vec3, Vec3)> {
@@ -142,51 +589,106 @@ impl ObjModel {
             return None;
         }
 
-        let v0 = self.vertices.get(face.vertices[0])?;
-        let v1 = self.vertices.get(face.vertices[1])?;
-        let v2 = self.vertices.get(face.vertices[2])?;
+        let v0 = self.vertices.get(face.vertices[0].position)?;
+        let v1 = self.vertices.get(face.vertices[1].position)?;
+        let v2 = self.vertices.get(face.vertices[2].position)?;
 
         Some((*v0, *v1, *v2))
     }
 
+    /// UVs of a triangle, or `None` unless every corner references one.
+    pub fn get_triangle_tex_coords(&self, face: &Face) -> Option<(Vec3, Vec3, Vec3)> {
+        if face.vertices.len() != 3 {
+            return None;
+        }
+
+        let t0 = self.tex_coords.get(face.vertices[0].tex_coord?)?;
+        let t1 = self.tex_coords.get(face.vertices[1].tex_coord?)?;
+        let t2 = self.tex_coords.get(face.vertices[2].tex_coord?)?;
+
+        Some((*t0, *t1, *t2))
+    }
+
+    /// Normals of a triangle, or `None` unless every corner references one.
     pub fn get_triangle_normals(&self, face: &Face) -> Option<(Vec3, Vec3, Vec3)> {
-        if face.vertices.len() != 3 || self.normals.is_empty() {
+        if face.vertices.len() != 3 {
             return None;
         }
 
-        let default_normal = Vec3::new(0.0, 0.0, 1.0);
-        let v0 = self.normals.get(face.vertices[0]).unwrap_or(&default_normal);
-        let v1 = self.normals.get(face.vertices[1]).unwrap_or(&default_normal);
-        let v2 = self.normals.get(face.vertices[2]).unwrap_or(&default_normal);
+        let n0 = self.normals.get(face.vertices[0].normal?)?;
+        let n1 = self.normals.get(face.vertices[1].normal?)?;
+        let n2 = self.normals.get(face.vertices[2].normal?)?;
 
-        Some((*v0, *v1, *v2))
+        Some((*n0, *n1, *n2))
     }
 
+    /// Vertex colors of a triangle, or `None` if the model has none.
+    pub fn get_triangle_colors(&self, face: &Face) -> Option<(Vec4, Vec4, Vec4)> {
+        if face.vertices.len() != 3 {
+            return None;
+        }
+
+        let c0 = self.colors.get(face.vertices[0].position)?;
+        let c1 = self.colors.get(face.vertices[1].position)?;
+        let c2 = self.colors.get(face.vertices[2].position)?;
+
+        Some((*c0, *c1, *c2))
+    }
+
+    /// Replaces the normals with area-weighted averages of the face
+    /// normals, smoothing across every edge. See `calculate_normals_with`.
     pub fn calculate_normals(&mut self) {
-        // Calculate normals for each face and accumulate them per vertex
-        let mut vertex_normals: Vec<Vec3> = vec![Vec3::new(0.0, 0.0, 0.0); self.vertices.len()];
-        let mut vertex_counts: Vec<usize> = vec![0; self.vertices.len()];
-
-        for face in &self.faces {
-            if let Some((v0, v1, v2)) = self.get_triangle_vertices(face) {
-                let e0 = v1 - v0;
-                let e1 = v2 - v0;
-                let normal = e0.cross(&e1).norm();
-
-                for &vertex_idx in &face.vertices {
-                    vertex_normals[vertex_idx] = vertex_normals[vertex_idx] + normal;
-                    vertex_counts[vertex_idx] += 1;
-                }
+        self.calculate_normals_with(&NormalOptions::default());
+    }
+
+    /// Replaces the normals with unit-length averages of the face normals.
+    /// Corners of faces in the same smoothing group share a normal per
+    /// position unless the faces meet at more than the crease angle; faces
+    /// with smoothing turned off (`s off` or `s 0`) are shaded flat.
+    /// Polygons are triangulated first; a corner shared by several of their
+    /// triangles takes the normal from the first. Faces referencing missing
+    /// positions, and those with nothing left after triangulation, are left
+    /// without normals.
+    pub fn calculate_normals_with(&mut self, options: &NormalOptions) {
+        // Triangles as a face and three of its corners
+        let mut corners: Vec<(usize, [usize; 3])> = Vec::new();
+        for (f, face) in self.faces.iter().enumerate() {
+            let points: Option<Vec<Vec3>> =
+                face.vertices.iter().map(|v| self.vertices.get(v.position).copied()).collect();
+            match points {
+                Some(points) if points.len() == 3 => corners.push((f, [0, 1, 2])),
+                Some(points) => corners.extend(triangulate(&points).into_iter().map(|t| (f, t))),
+                None => {}
             }
         }
+        let triangles: Vec<[usize; 3]> = corners
+            .iter()
+            .map(|&(f, t)| t.map(|k| self.faces[f].vertices[k].position))
+            .collect();
+        let groups: Vec<Option<u32>> =
+            corners.iter().map(|&(f, _)| self.faces[f].smoothing_group).collect();
+        let normals = normals::corner_normals(&self.vertices, &triangles, &groups, options);
 
-        // Average the normals for each vertex
+        // Corners at the same position with the same normal share a slot
+        let mut slots: HashMap<(usize, [u32; 3]), usize> = HashMap::new();
         self.normals.clear();
-        for i in 0..self.vertices.len() {
-            if vertex_counts[i] > 0 {
-                self.normals.push(vertex_normals[i] / vertex_counts[i] as f32);
-            } else {
-                self.normals.push(Vec3::new(0.0, 0.0, 1.0));
+        for face in &mut self.faces {
+            for vertex in &mut face.vertices {
+                vertex.normal = None;
+            }
+        }
+        for (&(f, t), normals) in corners.iter().zip(&normals) {
+            for (k, n) in t.into_iter().zip(normals) {
+                let vertex = &mut self.faces[f].vertices[k];
+                if vertex.normal.is_some() {
+                    continue;
+                }
+                let key = (vertex.position, [n.x.to_bits(), n.y.to_bits(), n.z.to_bits()]);
+                let slot = *slots.entry(key).or_insert_with(|| {
+                    self.normals.push(*n);
+                    self.normals.len() - 1
+                });
+                vertex.normal = Some(slot);
             }
         }
     }
@@ -258,14 +760,338 @@ mod tests {
         
         if parts[0] == "f" && parts.len() >= 4 {
             let mut face_vertices = Vec::new();
-            for i in 1..parts.len() {
-                let vertex_idx: usize = parts[i].parse().unwrap();
-                face_vertices.push(vertex_idx - 1); // Convert to 0-based
+            for part in &parts[1..] {
+                let vertex_idx: usize = part.parse().unwrap();
+                face_vertices.push(Vertex::new(vertex_idx - 1)); // Convert to 0-based
             }
-            model.faces.push(Face { vertices: face_vertices });
+            model.faces.push(Face { vertices: face_vertices, material: None, smoothing_group: None });
         }
         
         assert_eq!(model.faces.len(), 1);
-        assert_eq!(model.faces[0].vertices, vec![0, 1, 2]);
+        assert_eq!(model.faces[0].vertices, vec![Vertex::new(0), Vertex::new(1), Vertex::new(2)]);
+    }
+
+    #[test]
+    fn test_load_tex_coords() {
+        let src = "v 0 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 0\n\
+                   vt 0 0\nvt 1 0\nvt 1 1\nvt 0 1\n\
+                   f 1/1 2/2 3/3 4/4\nf 1 2 3\n";
+        let model = ObjModel::from_reader(src.as_bytes()).unwrap();
+
+        assert_eq!(model.faces.len(), 3);
+        let tex_coords: Vec<_> = model.faces[1].vertices.iter().map(|v| v.tex_coord).collect();
+        assert_eq!(tex_coords, vec![Some(1), Some(2), Some(3)]);
+        assert!(model.get_triangle_tex_coords(&model.faces[2]).is_none());
+
+        let (_, t1, _) = model.get_triangle_tex_coords(&model.faces[1]).unwrap();
+        assert_eq!(t1, Vec3::new(1.0, 1.0, 0.0));
+    }
+
+    #[test]
+    fn test_load_face_tuples() {
+        // A quad split along a UV seam with hard normals: positions are shared
+        // but UV and normal indices differ per corner
+        let src = "v 0 0 0\nv 1 0 0\nv 1 1 0\n\
+                   vt 0 0\nvt 1 0\nvt 1 1\nvt 0.5 0.5\n\
+                   vn 0 0 1\nvn 0 1 0\n\
+                   f 1/1/1 2/2/1 3/3/1\nf 3/4/2 2//2 1/1/2\n";
+        let model = ObjModel::from_reader(src.as_bytes()).unwrap();
+
+        assert_eq!(model.faces.len(), 2);
+        assert_eq!(
+            model.faces[1].vertices[1],
+            Vertex { position: 1, tex_coord: None, normal: Some(1) }
+        );
+
+        let (n0, _, _) = model.get_triangle_normals(&model.faces[0]).unwrap();
+        let (m0, _, _) = model.get_triangle_normals(&model.faces[1]).unwrap();
+        assert_eq!(n0, Vec3::new(0.0, 0.0, 1.0));
+        assert_eq!(m0, Vec3::new(0.0, 1.0, 0.0));
+
+        assert!(model.get_triangle_tex_coords(&model.faces[0]).is_some());
+        assert!(model.get_triangle_tex_coords(&model.faces[1]).is_none());
+    }
+
+    #[test]
+    fn test_load_materials() {
+        let dir = std::env::temp_dir().join("renderer_test_load_materials");
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("scene.mtl"), "newmtl red\nKd 1 0 0\nmap_Kd red.png\n").unwrap();
+        std::fs::write(
+            dir.join("scene.obj"),
+            "mtllib scene.mtl\nv 0 0 0\nv 1 0 0\nv 1 1 0\n\
+             f 1 2 3\nusemtl red\nf 1 2 3\nusemtl missing\nf 3 2 1\n",
+        )
+        .unwrap();
+
+        let model = ObjModel::load(dir.join("scene.obj")).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(model.materials.len(), 2);
+        let materials: Vec<_> = model.faces.iter().map(|f| f.material).collect();
+        assert_eq!(materials, vec![None, Some(0), Some(1)]);
+
+        let red = model.get_triangle_material(&model.faces[1]).unwrap();
+        assert_eq!(red.diffuse, Vec3::new(1.0, 0.0, 0.0));
+        assert_eq!(red.diffuse_map, Some(dir.join("red.png")));
+        assert_eq!(model.materials[1], Material::new("missing"));
+    }
+
+    #[test]
+    fn test_load_groups() {
+        let src = "v 0 0 0\nv 1 0 0\nv 1 1 0\n\
+                   f 1 2 3\n\
+                   o body part\ng front left\nf 1 2 3\nf 1 2 3\n\
+                   g left back\nf 3 2 1\n\
+                   o wheel\ng\ng rim\nf 1 2 3\n";
+        let model = ObjModel::from_reader(src.as_bytes()).unwrap();
+
+        assert_eq!(
+            model.objects,
+            vec![
+                Group { name: "body part".to_string(), faces: 1..4 },
+                Group { name: "wheel".to_string(), faces: 4..5 },
+            ]
+        );
+        // Naming a group again carries it on
+        assert_eq!(
+            model.groups,
+            vec![
+                Group { name: "front".to_string(), faces: 1..3 },
+                Group { name: "left".to_string(), faces: 1..4 },
+                Group { name: "back".to_string(), faces: 3..4 },
+                Group { name: "rim".to_string(), faces: 4..5 },
+            ]
+        );
+
+        let mut saved = Vec::new();
+        model.write_to(&mut saved, &SaveOptions::default()).unwrap();
+        assert_eq!(ObjModel::from_reader(saved.as_slice()).unwrap(), model);
+    }
+
+    #[test]
+    fn test_smoothing_groups() {
+        // Pairs of faces folded at a right angle along the edge 1-2
+        let src = "v 0 0 0\nv 1 0 0\nv 0 1 0\nv 0 0 1\n\
+                   s 1\nf 1 2 3\ns 2\nf 2 1 4\n\
+                   s 3\nf 1 2 3\nf 2 1 4\n\
+                   s off\nf 1 2 3\nf 2 1 4\n";
+        let mut model = ObjModel::from_reader(src.as_bytes()).unwrap();
+        model.calculate_normals();
+        let normals: Vec<_> =
+            model.faces.iter().map(|f| model.get_triangle_normals(f).unwrap()).collect();
+
+        // Different groups keep hard edges
+        assert_eq!(normals[0].0, Vec3::new(0.0, 0.0, 1.0));
+        assert_eq!(normals[1].1, Vec3::new(0.0, 1.0, 0.0));
+
+        // Within a group the normals along the shared edge are averaged
+        let average = Vec3::new(0.0, 1.0, 1.0).norm();
+        assert_eq!(normals[2].0, average);
+        assert_eq!(normals[3].1, average);
+        assert!((average.len() - 1.0).abs() < 1e-6);
+        assert_eq!(normals[2].2, Vec3::new(0.0, 0.0, 1.0));
+
+        // Smoothing off is flat
+        assert_eq!(normals[4].0, Vec3::new(0.0, 0.0, 1.0));
+        assert_eq!(normals[5].1, Vec3::new(0.0, 1.0, 0.0));
+    }
+
+    #[test]
+    fn test_polygon_normals() {
+        // Faces built in code can be polygons; the parser only makes triangles
+        let mut model = ObjModel::new();
+        model.vertices = vec![
+            Vec3::new(0.0, 0.0, 0.0),
+            Vec3::new(1.0, 0.0, 0.0),
+            Vec3::new(1.0, 1.0, 0.0),
+            Vec3::new(0.0, 1.0, 0.0),
+        ];
+        for positions in [vec![0, 1, 2, 3], vec![0, 1]] {
+            let vertices = positions.into_iter().map(Vertex::new).collect();
+            model.faces.push(Face { vertices, material: None, smoothing_group: None });
+        }
+        model.calculate_normals();
+
+        assert_eq!(model.normals, vec![Vec3::new(0.0, 0.0, 1.0); 4]);
+        assert!(model.faces[0].vertices.iter().all(|v| v.normal.is_some()));
+        assert!(model.faces[1].vertices.iter().all(|v| v.normal.is_none()));
+    }
+
+    #[test]
+    fn test_strict_errors() {
+        let parse =
+            |src: &str| ObjModel::from_reader_with(src.as_bytes(), ParseMode::Strict).unwrap_err();
+
+        let err = parse("v 0 0 0\nv 1 x 0\n");
+        assert!(
+            matches!(err, ObjError::InvalidNumber { line: 2, column: 5, ref token } if token == "x")
+        );
+        assert_eq!(err.to_string(), "line 2, column 5: invalid number `x`");
+
+        let err = parse("v 0 0 0\n  vn 0 1\n");
+        assert!(matches!(err, ObjError::MissingArgument { line: 2, column: 9, .. }));
+
+        let err = parse("v 0 0 0\nv 1 0 0\nv 0 1 0\nvt 0 0\nf 1/1 2/1 3/2\n");
+        assert!(matches!(err, ObjError::IndexOutOfRange { line: 5, column: 13, index: 2 }));
+
+        let err = parse("v 0 0 0\nf 1 2 0\n");
+        assert!(matches!(err, ObjError::IndexOutOfRange { line: 2, column: 5, index: 2 }));
+
+        let src = "v 0 0 0\nv 1 0 0\nv 0 1 0\nvn 0 0 1\nf 1//1 2//1 3//1\n";
+        assert!(ObjModel::from_reader_with(src.as_bytes(), ParseMode::Strict).is_ok());
+    }
+
+    #[test]
+    fn test_lenient_warnings() {
+        let src = "v 0 0 0\nv 1 0 0\nv 0 1 zero\nv 1 1\n\
+                   f 1 2 3\nf 1 2 9\nf 1/5 2 3\n";
+        let (model, warnings) = ObjModel::from_reader_with(src.as_bytes(), ParseMode::Lenient).unwrap();
+
+        assert_eq!(model.vertices.len(), 3);
+        assert_eq!(model.vertices[2], Vec3::new(0.0, 1.0, 0.0));
+
+        // The face with a bad position is dropped, the bad UV index is not used
+        assert_eq!(model.faces.len(), 2);
+        assert_eq!(model.faces[1].vertices[0], Vertex::new(0));
+
+        let lines: Vec<_> = warnings.iter().map(|w| w.line().unwrap()).collect();
+        assert_eq!(lines, vec![3, 4, 6, 7]);
+
+        // Out of range faces no longer reach calculate_normals
+        let mut model = model;
+        model.calculate_normals();
+        assert_eq!(model.normals, vec![Vec3::new(0.0, 0.0, 1.0); 3]);
+        for face in &model.faces {
+            assert!(model.get_triangle_normals(face).is_some());
+        }
     }
-} 
\ No newline at end of file
+
+    #[test]
+    fn test_relative_indices() {
+        // Each face refers back to the attributes declared just before it
+        let src = "v 0 0 0\nv 1 0 0\nv 0 1 0\nvt 0 0\nvt 1 0\nvt 0 1\nvn 0 0 1\n\
+                   f -3/-3/-1 -2/-2/-1 -1/-1/-1\n\
+                   v 5 5 5\nvt 0.5 0.5\n\
+                   f -4/1 2/-1 -1/-2\n";
+        let (model, warnings) = ObjModel::from_reader_with(src.as_bytes(), ParseMode::Strict).unwrap();
+        assert!(warnings.is_empty());
+
+        let face = &model.faces[0].vertices;
+        assert_eq!(face[0], Vertex { position: 0, tex_coord: Some(0), normal: Some(0) });
+        assert_eq!(face[2], Vertex { position: 2, tex_coord: Some(2), normal: Some(0) });
+
+        let positions: Vec<_> = model.faces[1].vertices.iter().map(|v| v.position).collect();
+        let tex_coords: Vec<_> = model.faces[1].vertices.iter().map(|v| v.tex_coord).collect();
+        assert_eq!(positions, vec![0, 1, 3]);
+        assert_eq!(tex_coords, vec![Some(0), Some(3), Some(2)]);
+
+        let err = ObjModel::from_reader_with("v 0 0 0\nf -1 -1 -2\n".as_bytes(), ParseMode::Strict).unwrap_err();
+        assert!(matches!(err, ObjError::IndexOutOfRange { line: 2, column: 9, index: -2 }));
+    }
+
+    #[test]
+    fn test_save_round_trip() {
+        let src = "v 0 0 0\nv 1 0 0\nv 0.1 0.7 0\nv 0 0 1\n\
+                   vt 0 0\nvt 1 0 0.5\nvn 0 0 1\n\
+                   f 1 2 3\n\
+                   o body\ng front\ns 1\nusemtl red\nf 1/1/1 2/2/1 3//1\n\
+                   g\ns off\nusemtl blue\nf 2/1 1/2 4/1\n";
+        let mut model = ObjModel::from_reader(src.as_bytes()).unwrap();
+        model.center_and_scale(3.0);
+
+        let mut saved = Vec::new();
+        model.write_to(&mut saved, &SaveOptions::default()).unwrap();
+        let text = String::from_utf8(saved).unwrap();
+        let reloaded = ObjModel::from_reader(text.as_bytes()).unwrap();
+        assert_eq!(reloaded, model);
+
+        // Normals from calculate_normals survive too
+        model.calculate_normals();
+        let mut saved = Vec::new();
+        model.write_to(&mut saved, &SaveOptions::default()).unwrap();
+        assert_eq!(ObjModel::from_reader(saved.as_slice()).unwrap(), model);
+    }
+
+    #[test]
+    fn test_save_round_trip_resets() {
+        // Faces going back to no smoothing group keep that
+        let src = "v 0 0 0\nv 1 0 0\nv 0 1 0\n\
+                   s 2\nusemtl red\nf 1 2 3\n\
+                   s\nf 1 2 3\n\
+                   s off\nf 1 2 3\n";
+        let model = ObjModel::from_reader(src.as_bytes()).unwrap();
+        let groups: Vec<_> = model.faces.iter().map(|f| f.smoothing_group).collect();
+        assert_eq!(groups, vec![Some(2), None, Some(0)]);
+
+        let mut saved = Vec::new();
+        model.write_to(&mut saved, &SaveOptions::default()).unwrap();
+        assert_eq!(ObjModel::from_reader(saved.as_slice()).unwrap(), model);
+    }
+
+    #[test]
+    fn test_save_without_material() {
+        // Faces built in code can drop their material after others have one;
+        // they are written first, as a bare `usemtl` is not valid OBJ
+        let src = "v 0 0 0\nv 1 0 0\nv 0 1 0\n\
+                   g a\nusemtl red\nf 1 2 3\nf 2 1 3\ng b\ns 1\nf 3 2 1\n";
+        let mut model = ObjModel::from_reader(src.as_bytes()).unwrap();
+        model.faces[1].material = None;
+
+        let mut saved = Vec::new();
+        model.write_to(&mut saved, &SaveOptions::default()).unwrap();
+        let (loaded, warnings) = ObjModel::from_reader_with(saved.as_slice(), ParseMode::Strict).unwrap();
+        assert!(warnings.is_empty());
+
+        let material = |f: &Face| loaded.get_triangle_material(f).map(|m| m.name.as_str());
+        let faces: Vec<_> = loaded
+            .faces
+            .iter()
+            .map(|f| (f.vertices[0].position, material(f), f.smoothing_group))
+            .collect();
+        assert_eq!(faces, vec![(1, None, None), (0, Some("red"), None), (2, Some("red"), Some(1))]);
+
+        let groups: Vec<_> = loaded.groups.iter().map(|g| (g.name.as_str(), g.faces.clone())).collect();
+        assert_eq!(groups, vec![("a", 0..2), ("b", 2..3)]);
+    }
+
+    #[test]
+    fn test_save_options() {
+        let src = "v 0.123456 1 2\nv 0 0 0\nv 1 1 1\ng part\nusemtl red\nf 1 2 3\n";
+        let model = ObjModel::from_reader(src.as_bytes()).unwrap();
+
+        let options = SaveOptions {
+            precision: Some(2),
+            mtllib: Some("scene.mtl".to_string()),
+            materials: false,
+            groups: false,
+        };
+        let mut saved = Vec::new();
+        model.write_to(&mut saved, &options).unwrap();
+        let text = String::from_utf8(saved).unwrap();
+
+        assert_eq!(
+            text,
+            "mtllib scene.mtl\nv 0.12 1.00 2.00\nv 0.00 0.00 0.00\nv 1.00 1.00 1.00\nf 1 2 3\n"
+        );
+    }
+
+    #[test]
+    fn test_concave_face() {
+        // Arrowhead whose reflex vertex 2 would make a fan from vertex 1 overlap
+        let src = "v 0 0 0\nv 2 1 0\nv 4 0 0\nv 2 3 0\nvt 0 0\n\
+                   f 2/1 3/1 4/1 1/1\n";
+        let model = ObjModel::from_reader(src.as_bytes()).unwrap();
+
+        assert_eq!(model.faces.len(), 2);
+        let mut area = 0.0;
+        for face in &model.faces {
+            assert!(face.vertices.iter().all(|v| v.tex_coord == Some(0)));
+            let (v0, v1, v2) = model.get_triangle_vertices(face).unwrap();
+            let n = (v1 - v0).cross(&(v2 - v0));
+            assert!(n.z > 0.0);
+            area += n.z / 2.0;
+        }
+        assert_eq!(area, 4.0);
+    }
+}
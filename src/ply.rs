@@ -0,0 +1,530 @@
+use crate::obj::{Face, ObjModel, Vertex};
+use crate::triangulate::triangulate;
+use crate::vec3::Vec3;
+use crate::vec4::Vec4;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// Why a PLY file could not be loaded.
+#[derive(Debug)]
+pub enum PlyError {
+    Io(io::Error),
+    /// The data ends before the header says it should.
+    UnexpectedEof,
+    /// Malformed header, at a 1-based line.
+    Header {
+        line: usize,
+        message: &'static str,
+    },
+    /// Malformed element data.
+    Invalid(&'static str),
+}
+
+impl fmt::Display for PlyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PlyError::Io(err) => write!(f, "{}", err),
+            PlyError::UnexpectedEof => write!(f, "unexpected end of PLY data"),
+            PlyError::Header { line, message } => write!(f, "line {}: {}", line, message),
+            PlyError::Invalid(what) => write!(f, "invalid PLY: {}", what),
+        }
+    }
+}
+
+impl Error for PlyError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            PlyError::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for PlyError {
+    fn from(err: io::Error) -> Self {
+        PlyError::Io(err)
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Encoding {
+    Ascii,
+    LittleEndian,
+    BigEndian,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Scalar {
+    I8,
+    U8,
+    I16,
+    U16,
+    I32,
+    U32,
+    F32,
+    F64,
+}
+
+impl Scalar {
+    fn parse(name: &str) -> Option<Self> {
+        Some(match name {
+            "char" | "int8" => Scalar::I8,
+            "uchar" | "uint8" => Scalar::U8,
+            "short" | "int16" => Scalar::I16,
+            "ushort" | "uint16" => Scalar::U16,
+            "int" | "int32" => Scalar::I32,
+            "uint" | "uint32" => Scalar::U32,
+            "float" | "float32" => Scalar::F32,
+            "double" | "float64" => Scalar::F64,
+            _ => return None,
+        })
+    }
+
+    fn size(self) -> usize {
+        match self {
+            Scalar::I8 | Scalar::U8 => 1,
+            Scalar::I16 | Scalar::U16 => 2,
+            Scalar::I32 | Scalar::U32 | Scalar::F32 => 4,
+            Scalar::F64 => 8,
+        }
+    }
+
+    /// Scale taking a color channel of this type into `[0, 1]`.
+    fn color_scale(self) -> f64 {
+        match self {
+            Scalar::U8 => 1.0 / 255.0,
+            Scalar::U16 => 1.0 / 65535.0,
+            _ => 1.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Property {
+    name: String,
+    scalar: Scalar,
+    /// Count type of a list property.
+    list: Option<Scalar>,
+}
+
+#[derive(Debug, Clone)]
+struct Element {
+    name: String,
+    count: usize,
+    properties: Vec<Property>,
+}
+
+impl Element {
+    fn property(&self, name: &str) -> Option<usize> {
+        self.properties.iter().position(|p| p.name == name)
+    }
+}
+
+/// Reads element data one value at a time in any of the encodings.
+struct Values<'a> {
+    encoding: Encoding,
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl Values<'_> {
+    fn read(&mut self, scalar: Scalar) -> Result<f64, PlyError> {
+        if self.encoding == Encoding::Ascii {
+            // Skip to the next whitespace-separated token
+            while self.data.get(self.pos).is_some_and(u8::is_ascii_whitespace) {
+                self.pos += 1;
+            }
+            let start = self.pos;
+            while self
+                .data
+                .get(self.pos)
+                .is_some_and(|b| !b.is_ascii_whitespace())
+            {
+                self.pos += 1;
+            }
+            if start == self.pos {
+                return Err(PlyError::UnexpectedEof);
+            }
+            return std::str::from_utf8(&self.data[start..self.pos])
+                .ok()
+                .and_then(|t| t.parse().ok())
+                .ok_or(PlyError::Invalid("bad number"));
+        }
+
+        let size = scalar.size();
+        let bytes = self
+            .data
+            .get(self.pos..self.pos + size)
+            .ok_or(PlyError::UnexpectedEof)?;
+        self.pos += size;
+
+        let mut buf = [0u8; 8];
+        buf[..size].copy_from_slice(bytes);
+        if self.encoding == Encoding::BigEndian {
+            buf[..size].reverse();
+        }
+
+        let b2 = [buf[0], buf[1]];
+        let b4 = [buf[0], buf[1], buf[2], buf[3]];
+        Ok(match scalar {
+            Scalar::I8 => buf[0] as i8 as f64,
+            Scalar::U8 => buf[0] as f64,
+            Scalar::I16 => i16::from_le_bytes(b2) as f64,
+            Scalar::U16 => u16::from_le_bytes(b2) as f64,
+            Scalar::I32 => i32::from_le_bytes(b4) as f64,
+            Scalar::U32 => u32::from_le_bytes(b4) as f64,
+            Scalar::F32 => f32::from_le_bytes(b4) as f64,
+            Scalar::F64 => f64::from_le_bytes(buf),
+        })
+    }
+
+    /// Reads one element as a row of values, lists expanded in place.
+    fn row(&mut self, element: &Element, row: &mut Vec<Vec<f64>>) -> Result<(), PlyError> {
+        row.clear();
+        for property in &element.properties {
+            let count = match property.list {
+                Some(count) => self.read(count)? as usize,
+                None => 1,
+            };
+            let values = (0..count)
+                .map(|_| self.read(property.scalar))
+                .collect::<Result<_, _>>()?;
+            row.push(values);
+        }
+        Ok(())
+    }
+}
+
+/// Splits the header from the element data, returning the encoding, the
+/// elements and the offset of the data.
+fn parse_header(data: &[u8]) -> Result<(Encoding, Vec<Element>, usize), PlyError> {
+    let mut encoding = None;
+    let mut elements: Vec<Element> = Vec::new();
+    let mut pos = 0;
+
+    for line_number in 1.. {
+        let end = data[pos..]
+            .iter()
+            .position(|&b| b == b'\n')
+            .ok_or(PlyError::UnexpectedEof)?;
+        let line = String::from_utf8_lossy(&data[pos..pos + end]);
+        pos += end + 1;
+
+        let header = |message| PlyError::Header {
+            line: line_number,
+            message,
+        };
+        let parts: Vec<&str> = line.split_whitespace().collect();
+
+        if line_number == 1 {
+            if parts != ["ply"] {
+                return Err(header("missing ply magic"));
+            }
+            continue;
+        }
+
+        match parts.first().copied() {
+            Some("format") => {
+                encoding = Some(match parts.get(1).copied() {
+                    Some("ascii") => Encoding::Ascii,
+                    Some("binary_little_endian") => Encoding::LittleEndian,
+                    Some("binary_big_endian") => Encoding::BigEndian,
+                    _ => return Err(header("unknown format")),
+                });
+            }
+            Some("element") => {
+                let (Some(name), Some(count)) = (parts.get(1), parts.get(2)) else {
+                    return Err(header("element needs a name and a count"));
+                };
+                elements.push(Element {
+                    name: name.to_string(),
+                    count: count.parse().map_err(|_| header("bad element count"))?,
+                    properties: Vec::new(),
+                });
+            }
+            Some("property") => {
+                let element = elements
+                    .last_mut()
+                    .ok_or_else(|| header("property outside element"))?;
+                let scalar = |i: usize| {
+                    parts
+                        .get(i)
+                        .and_then(|t| Scalar::parse(t))
+                        .ok_or_else(|| header("unknown property type"))
+                };
+                let property = if parts.get(1) == Some(&"list") {
+                    Property {
+                        name: parts
+                            .get(4)
+                            .ok_or_else(|| header("missing name"))?
+                            .to_string(),
+                        scalar: scalar(3)?,
+                        list: Some(scalar(2)?),
+                    }
+                } else {
+                    Property {
+                        name: parts
+                            .get(2)
+                            .ok_or_else(|| header("missing name"))?
+                            .to_string(),
+                        scalar: scalar(1)?,
+                        list: None,
+                    }
+                };
+                element.properties.push(property);
+            }
+            Some("end_header") => break,
+            _ => {
+                // comment, obj_info and blank lines
+            }
+        }
+    }
+
+    let encoding = encoding.ok_or(PlyError::Header {
+        line: 1,
+        message: "missing format",
+    })?;
+    Ok((encoding, elements, pos))
+}
+
+/// Loads a PLY file.
+pub fn load<P: AsRef<Path>>(path: P) -> Result<ObjModel, PlyError> {
+    decode(&fs::read(path)?)
+}
+
+/// Decodes PLY data in any encoding. Vertex positions, normals, texture
+/// coordinates and colors are read from the `vertex` element and polygons
+/// from the `vertex_indices` list of `face`. Other elements are skipped.
+pub fn decode(data: &[u8]) -> Result<ObjModel, PlyError> {
+    let (encoding, elements, start) = parse_header(data)?;
+    let mut values = Values {
+        encoding,
+        data,
+        pos: start,
+    };
+
+    let mut model = ObjModel::new();
+    let mut polygons: Vec<Vec<usize>> = Vec::new();
+    let mut row = Vec::new();
+
+    for element in &elements {
+        let find = |names: &[&str]| names.iter().find_map(|n| element.property(n));
+        let position = [find(&["x"]), find(&["y"]), find(&["z"])];
+        let normal = [find(&["nx"]), find(&["ny"]), find(&["nz"])];
+        let tex_coord = [
+            find(&["u", "s", "texture_u", "texture_s"]),
+            find(&["v", "t", "texture_v", "texture_t"]),
+        ];
+        let color = [
+            find(&["red", "r", "diffuse_red"]),
+            find(&["green", "g", "diffuse_green"]),
+            find(&["blue", "b", "diffuse_blue"]),
+            find(&["alpha", "a"]),
+        ];
+        let indices = find(&["vertex_indices", "vertex_index"]);
+
+        for _ in 0..element.count {
+            values.row(element, &mut row)?;
+            let get = |i: Option<usize>| i.map(|i| row[i].first().copied().unwrap_or(0.0) as f32);
+
+            match element.name.as_str() {
+                "vertex" => {
+                    let vec3 =
+                        |[x, y, z]: [Option<usize>; 3]| Some(Vec3::new(get(x)?, get(y)?, get(z)?));
+                    model
+                        .vertices
+                        .push(vec3(position).ok_or(PlyError::Invalid("vertex without x, y, z"))?);
+                    if let Some(n) = vec3(normal) {
+                        model.normals.push(n);
+                    }
+                    if let (Some(u), Some(v)) = (get(tex_coord[0]), get(tex_coord[1])) {
+                        model.tex_coords.push(Vec3::new(u, v, 0.0));
+                    }
+
+                    let channel = |i: Option<usize>| {
+                        let i = i?;
+                        let scale = element.properties[i].scalar.color_scale();
+                        Some((row[i].first().copied().unwrap_or(0.0) * scale) as f32)
+                    };
+                    if let (Some(r), Some(g), Some(b)) =
+                        (channel(color[0]), channel(color[1]), channel(color[2]))
+                    {
+                        let a = channel(color[3]).unwrap_or(1.0);
+                        model.colors.push(Vec4::new(r, g, b, a));
+                    }
+                }
+                "face" => {
+                    if let Some(i) = indices {
+                        // Indices may be stored as floats; only whole,
+                        // non-negative values are valid
+                        let polygon: Option<Vec<usize>> = row[i]
+                            .iter()
+                            .map(|&v| (v >= 0.0 && v.fract() == 0.0).then_some(v as usize))
+                            .collect();
+                        polygons.push(polygon.ok_or(PlyError::Invalid("bad vertex index"))?);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    // Attributes are per vertex, so faces reuse the position index
+    let has_normals = model.normals.len() == model.vertices.len();
+    let has_tex_coords = model.tex_coords.len() == model.vertices.len();
+    let vertex = |position| Vertex {
+        position,
+        tex_coord: has_tex_coords.then_some(position),
+        normal: has_normals.then_some(position),
+    };
+
+    for polygon in polygons {
+        if polygon.iter().any(|&i| i >= model.vertices.len()) {
+            return Err(PlyError::Invalid("vertex index out of range"));
+        }
+
+        let triangles = if polygon.len() == 3 {
+            vec![[0, 1, 2]]
+        } else {
+            let points: Vec<Vec3> = polygon.iter().map(|&i| model.vertices[i]).collect();
+            triangulate(&points)
+        };
+        for [a, b, c] in triangles {
+            model.faces.push(Face {
+                vertices: vec![vertex(polygon[a]), vertex(polygon[b]), vertex(polygon[c])],
+                material: None,
+                smoothing_group: None,
+            });
+        }
+    }
+
+    Ok(model)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ply::{self, PlyError};
+    use crate::vec3::Vec3;
+    use crate::vec4::Vec4;
+
+    const HEADER: &str = "element vertex 4\n\
+                          property float x\nproperty float y\nproperty float z\n\
+                          property float nx\nproperty float ny\nproperty float nz\n\
+                          property uchar red\nproperty uchar green\nproperty uchar blue\n\
+                          element edge 1\nproperty int vertex1\nproperty int vertex2\n\
+                          element face 1\nproperty list uchar int vertex_indices\n\
+                          end_header\n";
+
+    fn vertices() -> [([f32; 6], [u8; 3]); 4] {
+        [
+            ([0.0, 0.0, 0.0, 0.0, 0.0, 1.0], [255, 0, 0]),
+            ([1.0, 0.0, 0.0, 0.0, 0.0, 1.0], [0, 255, 0]),
+            ([1.0, 1.0, 0.0, 0.0, 0.0, 1.0], [0, 0, 255]),
+            ([0.0, 1.0, 0.0, 0.0, 0.0, 1.0], [51, 102, 153]),
+        ]
+    }
+
+    fn binary(big_endian: bool) -> Vec<u8> {
+        let format = if big_endian {
+            "binary_big_endian"
+        } else {
+            "binary_little_endian"
+        };
+        let mut data = format!("ply\nformat {} 1.0\ncomment test\n{}", format, HEADER).into_bytes();
+        let mut push = |bytes: &[u8]| {
+            let mut bytes = bytes.to_vec();
+            if big_endian {
+                bytes.reverse();
+            }
+            data.extend_from_slice(&bytes);
+        };
+
+        for (floats, color) in vertices() {
+            for f in floats {
+                push(&f.to_le_bytes());
+            }
+            for c in color {
+                push(&[c]);
+            }
+        }
+        push(&0i32.to_le_bytes());
+        push(&1i32.to_le_bytes());
+        push(&[4]);
+        for i in 0..4i32 {
+            push(&i.to_le_bytes());
+        }
+        data
+    }
+
+    fn ascii() -> Vec<u8> {
+        let mut text = format!("ply\nformat ascii 1.0\n{}", HEADER);
+        for (floats, color) in vertices() {
+            let floats: Vec<_> = floats.iter().map(|f| f.to_string()).collect();
+            text += &format!(
+                "{} {} {} {}\n",
+                floats.join(" "),
+                color[0],
+                color[1],
+                color[2]
+            );
+        }
+        text += "0 1\n4 0 1 2 3\n";
+        text.into_bytes()
+    }
+
+    #[test]
+    fn test_encodings() {
+        for data in [ascii(), binary(false), binary(true)] {
+            let model = ply::decode(&data).unwrap();
+
+            assert_eq!(model.vertices.len(), 4);
+            assert_eq!(model.vertices[2], Vec3::new(1.0, 1.0, 0.0));
+            assert_eq!(model.normals.len(), 4);
+            assert_eq!(model.colors[0], Vec4::new(1.0, 0.0, 0.0, 1.0));
+            assert_eq!(model.colors[3], Vec4::new(0.2, 0.4, 0.6, 1.0));
+
+            // The quad is split in two, with colors and normals per corner
+            assert_eq!(model.faces.len(), 2);
+            let (_, c1, _) = model.get_triangle_colors(&model.faces[1]).unwrap();
+            let corner = model.faces[1].vertices[1].position;
+            assert_eq!(c1, model.colors[corner]);
+            let (n0, _, _) = model.get_triangle_normals(&model.faces[1]).unwrap();
+            assert_eq!(n0, Vec3::new(0.0, 0.0, 1.0));
+        }
+    }
+
+    #[test]
+    fn test_errors() {
+        let err = ply::decode(
+            b"ply\nformat ascii 1.0\nelement vertex 1\nproperty float32 x\nproperty blob y\n",
+        )
+        .unwrap_err();
+        assert!(matches!(err, PlyError::Header { line: 5, .. }));
+
+        let mut data = binary(false);
+        data.truncate(data.len() - 2);
+        assert!(matches!(ply::decode(&data), Err(PlyError::UnexpectedEof)));
+
+        let data = b"ply\nformat ascii 1.0\nelement vertex 1\nproperty float x\nproperty float y\n\
+                     property float z\nelement face 1\nproperty list uchar int vertex_indices\n\
+                     end_header\n0 0 0\n3 0 0 1\n";
+        assert!(matches!(ply::decode(data), Err(PlyError::Invalid(_))));
+
+        // Float indices must be whole and non-negative
+        let header =
+            "ply\nformat ascii 1.0\nelement vertex 3\nproperty float x\nproperty float y\n\
+                      property float z\nelement face 1\nproperty list uchar float vertex_indices\n\
+                      end_header\n0 0 0\n1 0 0\n0 1 0\n";
+        assert!(ply::decode(format!("{}3 0 1 2\n", header).as_bytes()).is_ok());
+        for face in ["3 0 1 -1", "3 0 1 1.5", "3 0 1 nan"] {
+            let data = format!("{}{}\n", header, face);
+            assert!(matches!(
+                ply::decode(data.as_bytes()),
+                Err(PlyError::Invalid(_))
+            ));
+        }
+    }
+}
@@ -0,0 +1,634 @@
+//! Procedural meshes with positions, normals, UVs and tangents.
+//!
+//! Y is up. Triangles wind counterclockwise seen from the side their normals
+//! point to, which is the side `Image::draw_triangle` keeps. Texture `u`
+//! grows along the tangent and `v` along the bitangent, with `v = 0` at the
+//! bottom of the image.
+
+use crate::mesh::Mesh;
+use crate::vec3::Vec3;
+use crate::vec4::Vec4;
+use std::collections::HashMap;
+use std::f32::consts::{FRAC_PI_2, PI, TAU};
+
+/// A vertex with its full tangent frame.
+struct Point {
+    position: Vec3,
+    normal: Vec3,
+    tex_coord: (f32, f32),
+    tangent: Vec3,   // Direction of increasing u
+    bitangent: Vec3, // Direction of increasing v
+}
+
+#[derive(Default)]
+struct Builder {
+    positions: Vec<Vec3>,
+    normals: Vec<Vec3>,
+    tex_coords: Vec<Vec3>,
+    tangents: Vec<Vec4>,
+    indices: Vec<u32>,
+}
+
+impl Builder {
+    fn push(&mut self, p: Point) -> usize {
+        let n = p.normal.norm();
+        // Gram-Schmidt keeps the tangent in the surface plane
+        let t = p.tangent - n * n.dot(&p.tangent);
+        let t = if t.len_sqd() > 0.0 { t.norm() } else { t };
+        let w = if n.cross(&t).dot(&p.bitangent) < 0.0 {
+            -1.0
+        } else {
+            1.0
+        };
+
+        self.positions.push(p.position);
+        self.normals.push(n);
+        self.tex_coords
+            .push(Vec3::new(p.tex_coord.0, p.tex_coord.1, 0.0));
+        self.tangents.push(Vec4::from_vec3(&t, w));
+        self.positions.len() - 1
+    }
+
+    /// Adds a triangle unless it has collapsed, as at the poles of a sphere.
+    fn triangle(&mut self, a: usize, b: usize, c: usize) {
+        let (pa, pb, pc) = (self.positions[a], self.positions[b], self.positions[c]);
+        let extent = (pb - pa)
+            .len_sqd()
+            .max((pc - pb).len_sqd())
+            .max((pa - pc).len_sqd());
+        if (pb - pa).cross(&(pc - pa)).len() > extent * 1e-6 {
+            self.indices.extend([a as u32, b as u32, c as u32]);
+        }
+    }
+
+    /// Adds a `cols` by `rows` quad grid. `point(i, j)` gives the vertex in
+    /// column `i` and row `j`; columns should follow the tangent and rows
+    /// the bitangent for the quads to face along the normal.
+    fn grid(&mut self, cols: usize, rows: usize, mut point: impl FnMut(usize, usize) -> Point) {
+        let start = self.positions.len();
+        for j in 0..=rows {
+            for i in 0..=cols {
+                self.push(point(i, j));
+            }
+        }
+
+        for j in 0..rows {
+            for i in 0..cols {
+                let a = start + j * (cols + 1) + i;
+                let b = a + 1;
+                let d = a + cols + 1;
+                let c = d + 1;
+                self.triangle(a, b, c);
+                self.triangle(a, c, d);
+            }
+        }
+    }
+
+    /// Adds a flat disk at height `y`, facing up or down.
+    fn disk(&mut self, radius: f32, y: f32, segments: usize, up: bool) {
+        let side = if up { 1.0 } else { -1.0 };
+        let normal = Vec3::new(0.0, side, 0.0);
+        let tangent = Vec3::new(1.0, 0.0, 0.0);
+        let bitangent = Vec3::new(0.0, 0.0, -side);
+
+        let center = self.push(Point {
+            position: Vec3::new(0.0, y, 0.0),
+            normal,
+            tex_coord: (0.5, 0.5),
+            tangent,
+            bitangent,
+        });
+        for i in 0..=segments {
+            let phi = TAU * i as f32 / segments as f32;
+            let (x, z) = (phi.cos(), -phi.sin());
+            self.push(Point {
+                position: Vec3::new(x * radius, y, z * radius),
+                normal,
+                tex_coord: (0.5 + 0.5 * x, 0.5 - 0.5 * side * z),
+                tangent,
+                bitangent,
+            });
+        }
+
+        for i in 0..segments {
+            let (a, b) = (center + 1 + i, center + 2 + i);
+            if up {
+                self.triangle(center, a, b);
+            } else {
+                self.triangle(center, b, a);
+            }
+        }
+    }
+
+    fn finish(self) -> Mesh {
+        Mesh {
+            positions: self.positions,
+            normals: Some(self.normals),
+            tex_coords: Some(self.tex_coords),
+            tangents: Some(self.tangents),
+            indices: self.indices,
+            ..Mesh::default()
+        }
+    }
+}
+
+/// Direction around the y axis at angle `phi`, counterclockwise seen from
+/// above, starting at +x.
+fn around(phi: f32) -> Vec3 {
+    Vec3::new(phi.cos(), 0.0, -phi.sin())
+}
+
+/// Tangent of a surface of revolution at angle `phi`.
+fn around_tangent(phi: f32) -> Vec3 {
+    Vec3::new(-phi.sin(), 0.0, -phi.cos())
+}
+
+/// A sphere of `segments` columns around the y axis and `rings` rows from
+/// pole to pole. The texture wraps once around, seam at +x.
+pub fn uv_sphere(radius: f32, segments: usize, rings: usize) -> Mesh {
+    let (segments, rings) = (segments.max(3), rings.max(2));
+    let mut b = Builder::default();
+
+    b.grid(segments, rings, |i, j| {
+        let (u, v) = (i as f32 / segments as f32, j as f32 / rings as f32);
+        let (phi, theta) = (TAU * u, PI * v);
+        let normal = around(phi) * theta.sin() + Vec3::new(0.0, -theta.cos(), 0.0);
+        Point {
+            position: normal * radius,
+            normal,
+            tex_coord: (u, v),
+            tangent: around_tangent(phi),
+            bitangent: around(phi) * theta.cos() + Vec3::new(0.0, theta.sin(), 0.0),
+        }
+    });
+    b.finish()
+}
+
+/// A sphere made by splitting each face of an icosahedron into four,
+/// `subdivisions` times. Triangles are more even than on a UV sphere. UVs
+/// follow the same equirectangular layout, with vertices split along the
+/// seam and at the poles.
+pub fn icosphere(radius: f32, subdivisions: usize) -> Mesh {
+    let t = (1.0 + 5.0_f32.sqrt()) / 2.0;
+    let mut points: Vec<Vec3> = [
+        (-1.0, t, 0.0),
+        (1.0, t, 0.0),
+        (-1.0, -t, 0.0),
+        (1.0, -t, 0.0),
+        (0.0, -1.0, t),
+        (0.0, 1.0, t),
+        (0.0, -1.0, -t),
+        (0.0, 1.0, -t),
+        (t, 0.0, -1.0),
+        (t, 0.0, 1.0),
+        (-t, 0.0, -1.0),
+        (-t, 0.0, 1.0),
+    ]
+    .iter()
+    .map(|&(x, y, z)| Vec3::new(x, y, z).norm())
+    .collect();
+    let mut faces: Vec<[usize; 3]> = vec![
+        [0, 11, 5],
+        [0, 5, 1],
+        [0, 1, 7],
+        [0, 7, 10],
+        [0, 10, 11],
+        [1, 5, 9],
+        [5, 11, 4],
+        [11, 10, 2],
+        [10, 7, 6],
+        [7, 1, 8],
+        [3, 9, 4],
+        [3, 4, 2],
+        [3, 2, 6],
+        [3, 6, 8],
+        [3, 8, 9],
+        [4, 9, 5],
+        [2, 4, 11],
+        [6, 2, 10],
+        [8, 6, 7],
+        [9, 8, 1],
+    ];
+
+    for _ in 0..subdivisions {
+        let mut midpoints: HashMap<(usize, usize), usize> = HashMap::new();
+        let mut midpoint = |a: usize, b: usize| {
+            *midpoints.entry((a.min(b), a.max(b))).or_insert_with(|| {
+                points.push(((points[a] + points[b]) / 2.0).norm());
+                points.len() - 1
+            })
+        };
+        faces = faces
+            .iter()
+            .flat_map(|&[a, b, c]| {
+                let (ab, bc, ca) = (midpoint(a, b), midpoint(b, c), midpoint(c, a));
+                [[a, ab, ca], [b, bc, ab], [c, ca, bc], [ab, bc, ca]]
+            })
+            .collect();
+    }
+
+    let mut b = Builder::default();
+    let mut split: HashMap<(usize, u32), usize> = HashMap::new();
+    for face in faces {
+        // Face outward, whatever order the table lists the corners in
+        let [p0, p1, p2] = face.map(|i| points[i]);
+        let face = if (p1 - p0).cross(&(p2 - p0)).dot(&(p0 + p1 + p2)) < 0.0 {
+            [face[0], face[2], face[1]]
+        } else {
+            face
+        };
+
+        let mut u = face.map(|i| {
+            let p = points[i];
+            let phi = (-p.z).atan2(p.x);
+            if phi < 0.0 {
+                phi / TAU + 1.0
+            } else {
+                phi / TAU
+            }
+        });
+        let poles = face.map(|i| points[i].x.hypot(points[i].z) < 1e-6);
+        // Faces straddling the seam continue past u = 1 instead of wrapping
+        let side = |test: fn(f32) -> bool| (0..3).any(|k| !poles[k] && test(u[k]));
+        if side(|u| u > 0.75) && side(|u| u < 0.25) {
+            for u in u.iter_mut().filter(|u| **u < 0.5) {
+                *u += 1.0;
+            }
+        }
+        // A pole takes the u of the face around it
+        for k in 0..3 {
+            if poles[k] {
+                u[k] = (u[(k + 1) % 3] + u[(k + 2) % 3]) / 2.0;
+            }
+        }
+
+        let corners: Vec<usize> = (0..3)
+            .map(|k| {
+                let i = face[k];
+                *split.entry((i, u[k].to_bits())).or_insert_with(|| {
+                    let normal = points[i];
+                    let phi = TAU * u[k];
+                    b.push(Point {
+                        position: normal * radius,
+                        normal,
+                        tex_coord: (u[k], (-normal.y).clamp(-1.0, 1.0).acos() / PI),
+                        tangent: around_tangent(phi),
+                        bitangent: normal.cross(&around_tangent(phi)),
+                    })
+                })
+            })
+            .collect();
+        b.triangle(corners[0], corners[1], corners[2]);
+    }
+    b.finish()
+}
+
+/// An axis-aligned cube centered on the origin. Each face is mapped to the
+/// full texture, upright when seen with +y up (+z for the top and bottom).
+pub fn cube(size: f32) -> Mesh {
+    let x = Vec3::new(1.0, 0.0, 0.0);
+    let y = Vec3::new(0.0, 1.0, 0.0);
+    let z = Vec3::new(0.0, 0.0, 1.0);
+    // Normal and tangent of each face; the bitangent is normal x tangent
+    let faces = [
+        (x, z.neg()),
+        (x.neg(), z),
+        (y, x),
+        (y.neg(), x),
+        (z, x),
+        (z.neg(), x.neg()),
+    ];
+
+    let mut b = Builder::default();
+    for (normal, tangent) in faces {
+        let bitangent = normal.cross(&tangent);
+        b.grid(1, 1, |i, j| {
+            let (u, v) = (i as f32, j as f32);
+            Point {
+                position: (normal * 0.5 + tangent * (u - 0.5) + bitangent * (v - 0.5)) * size,
+                normal,
+                tex_coord: (u, v),
+                tangent,
+                bitangent,
+            }
+        });
+    }
+    b.finish()
+}
+
+/// A `width` by `depth` plane in the xz plane facing +y, split into
+/// `x_segments` by `z_segments` quads. `v` grows toward -z.
+pub fn plane(width: f32, depth: f32, x_segments: usize, z_segments: usize) -> Mesh {
+    let (x_segments, z_segments) = (x_segments.max(1), z_segments.max(1));
+    let mut b = Builder::default();
+
+    b.grid(x_segments, z_segments, |i, j| {
+        let (u, v) = (i as f32 / x_segments as f32, j as f32 / z_segments as f32);
+        Point {
+            position: Vec3::new(width * (u - 0.5), 0.0, depth * (0.5 - v)),
+            normal: Vec3::new(0.0, 1.0, 0.0),
+            tex_coord: (u, v),
+            tangent: Vec3::new(1.0, 0.0, 0.0),
+            bitangent: Vec3::new(0.0, 0.0, -1.0),
+        }
+    });
+    b.finish()
+}
+
+/// A closed cylinder along the y axis, centered on the origin.
+pub fn cylinder(radius: f32, height: f32, segments: usize) -> Mesh {
+    let segments = segments.max(3);
+    let mut b = Builder::default();
+
+    b.grid(segments, 1, |i, j| {
+        let (u, v) = (i as f32 / segments as f32, j as f32);
+        let phi = TAU * u;
+        Point {
+            position: around(phi) * radius + Vec3::new(0.0, height * (v - 0.5), 0.0),
+            normal: around(phi),
+            tex_coord: (u, v),
+            tangent: around_tangent(phi),
+            bitangent: Vec3::new(0.0, 1.0, 0.0),
+        }
+    });
+    b.disk(radius, height / 2.0, segments, true);
+    b.disk(radius, -height / 2.0, segments, false);
+    b.finish()
+}
+
+/// A closed cone along the y axis with its base at `-height / 2` and its
+/// apex at `height / 2`.
+pub fn cone(radius: f32, height: f32, segments: usize) -> Mesh {
+    let segments = segments.max(3);
+    let mut b = Builder::default();
+
+    b.grid(segments, 1, |i, j| {
+        let (u, v) = (i as f32 / segments as f32, j as f32);
+        let phi = TAU * u;
+        Point {
+            position: around(phi) * (radius * (1.0 - v)) + Vec3::new(0.0, height * (v - 0.5), 0.0),
+            // The apex gets one normal per column, so shading stays smooth
+            normal: around(phi) * height + Vec3::new(0.0, radius, 0.0),
+            tex_coord: (u, v),
+            tangent: around_tangent(phi),
+            bitangent: Vec3::new(0.0, height, 0.0) - around(phi) * radius,
+        }
+    });
+    b.disk(radius, -height / 2.0, segments, false);
+    b.finish()
+}
+
+/// A torus around the y axis. `segments` columns follow the ring of radius
+/// `major`; `sides` rows go around the tube of radius `minor`, starting on
+/// the outer equator.
+pub fn torus(major: f32, minor: f32, segments: usize, sides: usize) -> Mesh {
+    let (segments, sides) = (segments.max(3), sides.max(3));
+    let mut b = Builder::default();
+
+    b.grid(segments, sides, |i, j| {
+        let (u, v) = (i as f32 / segments as f32, j as f32 / sides as f32);
+        let (phi, theta) = (TAU * u, TAU * v);
+        let normal = around(phi) * theta.cos() + Vec3::new(0.0, theta.sin(), 0.0);
+        Point {
+            position: around(phi) * major + normal * minor,
+            normal,
+            tex_coord: (u, v),
+            tangent: around_tangent(phi),
+            bitangent: around(phi) * -theta.sin() + Vec3::new(0.0, theta.cos(), 0.0),
+        }
+    });
+    b.finish()
+}
+
+/// A cylinder of `height` along the y axis capped by hemispheres, each with
+/// `rings` rows. `v` is proportional to the distance along the profile, so
+/// the texture isn't stretched between the caps and the body.
+pub fn capsule(radius: f32, height: f32, segments: usize, rings: usize) -> Mesh {
+    let (segments, rings) = (segments.max(3), rings.max(1));
+    let length = PI * radius + height;
+    let mut b = Builder::default();
+
+    // Rows 0..=rings cover the bottom cap and the rest the top cap; the
+    // body lies between the last row of one and the first of the other
+    b.grid(segments, 2 * rings + 1, |i, j| {
+        let (theta, y, distance) = if j <= rings {
+            let theta = FRAC_PI_2 * j as f32 / rings as f32;
+            (theta, -height / 2.0, theta * radius)
+        } else {
+            let theta = FRAC_PI_2 * (1.0 + (j - rings - 1) as f32 / rings as f32);
+            (theta, height / 2.0, theta * radius + height)
+        };
+        let u = i as f32 / segments as f32;
+        let phi = TAU * u;
+        let normal = around(phi) * theta.sin() + Vec3::new(0.0, -theta.cos(), 0.0);
+        Point {
+            position: normal * radius + Vec3::new(0.0, y, 0.0),
+            normal,
+            tex_coord: (u, distance / length),
+            tangent: around_tangent(phi),
+            bitangent: around(phi) * theta.cos() + Vec3::new(0.0, theta.sin(), 0.0),
+        }
+    });
+    b.finish()
+}
+
+/// Bicubic Bezier patches, as in the Utah teapot, each tessellated into
+/// `divisions` by `divisions` quads. Control points are indexed `[v][u]`;
+/// the patch faces along `dP/du x dP/dv` and covers the whole texture.
+pub fn bezier_patches(patches: &[[[Vec3; 4]; 4]], divisions: usize) -> Mesh {
+    let divisions = divisions.max(1);
+    let mut b = Builder::default();
+
+    for patch in patches {
+        b.grid(divisions, divisions, |i, j| {
+            let (u, v) = (i as f32 / divisions as f32, j as f32 / divisions as f32);
+            let (position, mut du, mut dv) = bezier_point(patch, u, v);
+            // Collapsed edges, like the tip of a lid, have a zero derivative;
+            // borrow it from just inside the patch
+            if du.cross(&dv).len_sqd() <= f32::EPSILON * f32::EPSILON {
+                let (_, du2, dv2) = bezier_point(patch, nudge(u), nudge(v));
+                (du, dv) = (du2, dv2);
+            }
+            Point {
+                position,
+                normal: du.cross(&dv),
+                tex_coord: (u, v),
+                tangent: du,
+                bitangent: dv,
+            }
+        });
+    }
+    b.finish()
+}
+
+/// Moves a patch parameter slightly toward the middle.
+fn nudge(t: f32) -> f32 {
+    t + (0.5 - t) * 1e-3
+}
+
+/// Bernstein basis of degree 3 and its derivative.
+fn bernstein(t: f32) -> ([f32; 4], [f32; 4]) {
+    let s = 1.0 - t;
+    (
+        [s * s * s, 3.0 * t * s * s, 3.0 * t * t * s, t * t * t],
+        [
+            -3.0 * s * s,
+            3.0 * s * s - 6.0 * t * s,
+            6.0 * t * s - 3.0 * t * t,
+            3.0 * t * t,
+        ],
+    )
+}
+
+/// Position and partial derivatives of a patch.
+fn bezier_point(patch: &[[Vec3; 4]; 4], u: f32, v: f32) -> (Vec3, Vec3, Vec3) {
+    let (bu, dbu) = bernstein(u);
+    let (bv, dbv) = bernstein(v);
+    let zero = Vec3::new(0.0, 0.0, 0.0);
+    let (mut p, mut du, mut dv) = (zero, zero, zero);
+    for (j, row) in patch.iter().enumerate() {
+        for (i, &c) in row.iter().enumerate() {
+            p = p + c * (bu[i] * bv[j]);
+            du = du + c * (dbu[i] * bv[j]);
+            dv = dv + c * (bu[i] * dbv[j]);
+        }
+    }
+    (p, du, dv)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::mesh::Mesh;
+    use crate::primitives::*;
+
+    // Checks the invariants every generator promises: unit normals, unit
+    // tangents in the surface plane, triangles facing along their normals
+    // and UVs growing along the tangent frame
+    fn check(mesh: &Mesh) {
+        assert!(mesh.is_valid());
+        assert!(mesh.triangle_count() > 0);
+        let normals = mesh.normals.as_ref().unwrap();
+        let tex_coords = mesh.tex_coords.as_ref().unwrap();
+        let tangents = mesh.tangents.as_ref().unwrap();
+
+        for (n, t) in normals.iter().zip(tangents) {
+            assert!((n.len() - 1.0).abs() < 1e-4, "normal {:?}", n);
+            assert!((t.xyz().len() - 1.0).abs() < 1e-4, "tangent {:?}", t);
+            assert!(n.dot(&t.xyz()).abs() < 1e-4);
+            assert!(t.w == 1.0 || t.w == -1.0);
+        }
+
+        for [a, b, c] in mesh.triangles() {
+            let (pa, pb, pc) = (mesh.positions[a], mesh.positions[b], mesh.positions[c]);
+            let (e1, e2) = (pb - pa, pc - pa);
+            let face = e1.cross(&e2);
+            let n = normals[a] + normals[b] + normals[c];
+            assert!(face.dot(&n) > 0.0, "triangle {:?} faces away", [a, b, c]);
+
+            // Direction of increasing u and v across the triangle
+            let (ta, tb, tc) = (tex_coords[a], tex_coords[b], tex_coords[c]);
+            let (s1, t1, s2, t2) = (tb.x - ta.x, tb.y - ta.y, tc.x - ta.x, tc.y - ta.y);
+            let r = s1 * t2 - s2 * t1;
+            assert!(r > 0.0, "UVs of {:?} are mirrored", [a, b, c]);
+            let sdir = (e1 * t2 - e2 * t1) / r;
+            let tdir = (e2 * s1 - e1 * s2) / r;
+            for i in [a, b, c] {
+                let bitangent = normals[i].cross(&tangents[i].xyz()) * tangents[i].w;
+                assert!(sdir.dot(&tangents[i].xyz()) > 0.0);
+                assert!(tdir.dot(&bitangent) > 0.0);
+            }
+        }
+    }
+
+    // Signed volume enclosed by the triangles
+    fn volume(mesh: &Mesh) -> f32 {
+        mesh.triangles()
+            .map(|[a, b, c]| {
+                let (pa, pb, pc) = (mesh.positions[a], mesh.positions[b], mesh.positions[c]);
+                pa.dot(&pb.cross(&pc)) / 6.0
+            })
+            .sum()
+    }
+
+    fn assert_volume(mesh: &Mesh, expected: f32) {
+        let v = volume(mesh);
+        assert!(
+            (v - expected).abs() < expected * 0.02,
+            "{} vs {}",
+            v,
+            expected
+        );
+    }
+
+    #[test]
+    fn test_spheres() {
+        let sphere = uv_sphere(2.0, 48, 24);
+        check(&sphere);
+        assert_volume(&sphere, 4.0 / 3.0 * PI * 8.0);
+        // Collapsed pole triangles are dropped
+        assert_eq!(sphere.triangle_count(), 2 * 48 * 24 - 2 * 48);
+
+        let ico = icosphere(2.0, 3);
+        check(&ico);
+        assert_volume(&ico, 4.0 / 3.0 * PI * 8.0);
+        assert_eq!(ico.triangle_count(), 20 * 4 * 4 * 4);
+        assert!(ico.positions.iter().all(|p| (p.len() - 2.0).abs() < 1e-5));
+    }
+
+    #[test]
+    fn test_flat_shapes() {
+        let cube = cube(2.0);
+        check(&cube);
+        assert_eq!((cube.vertex_count(), cube.triangle_count()), (24, 12));
+        assert_volume(&cube, 8.0);
+
+        let plane = plane(4.0, 2.0, 4, 3);
+        check(&plane);
+        assert_eq!(plane.triangle_count(), 24);
+        assert_eq!(
+            plane.get_bounding_box(),
+            (Vec3::new(-2.0, 0.0, -1.0), Vec3::new(2.0, 0.0, 1.0))
+        );
+    }
+
+    #[test]
+    fn test_solids_of_revolution() {
+        let cylinder = cylinder(1.0, 3.0, 64);
+        check(&cylinder);
+        assert_volume(&cylinder, PI * 3.0);
+
+        let cone = cone(1.0, 3.0, 64);
+        check(&cone);
+        assert_volume(&cone, PI * 3.0 / 3.0);
+
+        let torus = torus(2.0, 0.5, 64, 32);
+        check(&torus);
+        assert_volume(&torus, 2.0 * PI * PI * 2.0 * 0.25);
+
+        let capsule = capsule(1.0, 2.0, 48, 12);
+        check(&capsule);
+        assert_volume(&capsule, PI * 2.0 + 4.0 / 3.0 * PI);
+    }
+
+    #[test]
+    fn test_bezier_patches() {
+        // A flat patch facing +z and a dome whose top edge collapses to a
+        // point, like the teapot lid
+        let flat: [[Vec3; 4]; 4] =
+            std::array::from_fn(|j| std::array::from_fn(|i| Vec3::new(i as f32, j as f32, 0.0)));
+        let dome: [[Vec3; 4]; 4] = std::array::from_fn(|j| {
+            let r = 1.0 - j as f32 / 3.0;
+            std::array::from_fn(|i| {
+                let phi = FRAC_PI_2 * i as f32 / 3.0;
+                Vec3::new(r * phi.cos(), j as f32 / 3.0, -r * phi.sin())
+            })
+        });
+
+        let mesh = bezier_patches(&[flat, dome], 8);
+        check(&mesh);
+        assert_eq!(mesh.normals.as_ref().unwrap()[0], Vec3::new(0.0, 0.0, 1.0));
+        assert_eq!(mesh.positions[80], Vec3::new(3.0, 3.0, 0.0));
+        let apex = mesh.normals.as_ref().unwrap()[161];
+        assert!(apex.y > 0.5, "{:?}", apex);
+    }
+}
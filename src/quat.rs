@@ -0,0 +1,290 @@
+use crate::mat44::Mat44;
+use crate::vec3::Vec3;
+use std::ops::Mul;
+
+/// A rotation stored as a unit quaternion `w + xi + yj + zk`.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Quat {
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+    pub w: f32,
+}
+
+impl Default for Quat {
+    fn default() -> Self {
+        Self::ident()
+    }
+}
+
+impl Quat {
+    pub fn new(x: f32, y: f32, z: f32, w: f32) -> Self {
+        Self { x, y, z, w }
+    }
+
+    pub fn ident() -> Self {
+        Self::new(0.0, 0.0, 0.0, 1.0)
+    }
+
+    /// Rotation by `alpha` radians around `axis`, matching `Mat44::rotat`.
+    pub fn from_axis_angle(axis: &Vec3, alpha: f32) -> Self {
+        let (sin, cos) = (alpha * 0.5).sin_cos();
+        let a = axis.norm() * sin;
+        Self::new(a.x, a.y, a.z, cos)
+    }
+
+    /// Rotation by `x` around the X axis, then `y` around Y, then `z` around Z
+    /// (all in radians, fixed axes).
+    pub fn from_euler(x: f32, y: f32, z: f32) -> Self {
+        let qx = Self::from_axis_angle(&Vec3::new(1.0, 0.0, 0.0), x);
+        let qy = Self::from_axis_angle(&Vec3::new(0.0, 1.0, 0.0), y);
+        let qz = Self::from_axis_angle(&Vec3::new(0.0, 0.0, 1.0), z);
+        qz * qy * qx
+    }
+
+    /// Rotation that turns -z towards `forward` while keeping +y as close to
+    /// `up` as possible, the same frame `Mat44::look_at` uses for cameras.
+    pub fn look_rotation(forward: &Vec3, up: &Vec3) -> Self {
+        let f = forward.norm();
+        let r = f.cross(up).norm();
+        let u = r.cross(&f);
+
+        Self::from_mat44(&Mat44::new([
+            [r.x, u.x, -f.x, 0.0],
+            [r.y, u.y, -f.y, 0.0],
+            [r.z, u.z, -f.z, 0.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ]))
+    }
+
+    /// Extracts the rotation from the upper 3x3 block, which must be
+    /// orthonormal.
+    pub fn from_mat44(m: &Mat44) -> Self {
+        let (m00, m01, m02) = (m.get(0, 0), m.get(0, 1), m.get(0, 2));
+        let (m10, m11, m12) = (m.get(1, 0), m.get(1, 1), m.get(1, 2));
+        let (m20, m21, m22) = (m.get(2, 0), m.get(2, 1), m.get(2, 2));
+
+        let trace = m00 + m11 + m22;
+        let q = if trace > 0.0 {
+            let s = (trace + 1.0).sqrt() * 2.0;
+            Self::new((m21 - m12) / s, (m02 - m20) / s, (m10 - m01) / s, 0.25 * s)
+        } else if m00 > m11 && m00 > m22 {
+            let s = (1.0 + m00 - m11 - m22).sqrt() * 2.0;
+            Self::new(0.25 * s, (m01 + m10) / s, (m02 + m20) / s, (m21 - m12) / s)
+        } else if m11 > m22 {
+            let s = (1.0 + m11 - m00 - m22).sqrt() * 2.0;
+            Self::new((m01 + m10) / s, 0.25 * s, (m12 + m21) / s, (m02 - m20) / s)
+        } else {
+            let s = (1.0 + m22 - m00 - m11).sqrt() * 2.0;
+            Self::new((m02 + m20) / s, (m12 + m21) / s, 0.25 * s, (m10 - m01) / s)
+        };
+
+        q.norm()
+    }
+
+    pub fn dot(&self, other: &Self) -> f32 {
+        self.x * other.x + self.y * other.y + self.z * other.z + self.w * other.w
+    }
+
+    pub fn len(&self) -> f32 {
+        self.dot(self).sqrt()
+    }
+
+    pub fn norm(&self) -> Self {
+        let len = self.len();
+        Self::new(self.x / len, self.y / len, self.z / len, self.w / len)
+    }
+
+    /// The inverse rotation (for unit quaternions).
+    pub fn conj(&self) -> Self {
+        Self::new(-self.x, -self.y, -self.z, self.w)
+    }
+
+    /// Normalized linear interpolation: cheaper than `slerp` but not constant
+    /// speed.
+    pub fn nlerp(&self, other: &Self, t: f32) -> Self {
+        // Take the short way around
+        let other = if self.dot(other) < 0.0 {
+            Self::new(-other.x, -other.y, -other.z, -other.w)
+        } else {
+            *other
+        };
+
+        Self::new(
+            self.x + (other.x - self.x) * t,
+            self.y + (other.y - self.y) * t,
+            self.z + (other.z - self.z) * t,
+            self.w + (other.w - self.w) * t,
+        )
+        .norm()
+    }
+
+    /// Spherical linear interpolation along the shortest arc.
+    pub fn slerp(&self, other: &Self, t: f32) -> Self {
+        let mut cos = self.dot(other);
+        let mut other = *other;
+        if cos < 0.0 {
+            cos = -cos;
+            other = Self::new(-other.x, -other.y, -other.z, -other.w);
+        }
+
+        // Nearly parallel: the sine below would vanish
+        if cos > 0.9995 {
+            return self.nlerp(&other, t);
+        }
+
+        let theta = cos.acos();
+        let sin = theta.sin();
+        let a = ((1.0 - t) * theta).sin() / sin;
+        let b = (t * theta).sin() / sin;
+
+        Self::new(
+            a * self.x + b * other.x,
+            a * self.y + b * other.y,
+            a * self.z + b * other.z,
+            a * self.w + b * other.w,
+        )
+    }
+
+    pub fn rotate(&self, v: &Vec3) -> Vec3 {
+        // v' = v + 2w(q x v) + 2q x (q x v)
+        let q = Vec3::new(self.x, self.y, self.z);
+        let t = q.cross(v) * 2.0;
+        *v + t * self.w + q.cross(&t)
+    }
+}
+
+impl Mul for Quat {
+    type Output = Quat;
+
+    /// Composes rotations: `a * b` applies `b` first.
+    fn mul(self, rhs: Quat) -> Self::Output {
+        let (a, b) = (self, rhs);
+        Quat::new(
+            a.w * b.x + a.x * b.w + a.y * b.z - a.z * b.y,
+            a.w * b.y - a.x * b.z + a.y * b.w + a.z * b.x,
+            a.w * b.z + a.x * b.y - a.y * b.x + a.z * b.w,
+            a.w * b.w - a.x * b.x - a.y * b.y - a.z * b.z,
+        )
+    }
+}
+
+impl Mul<&Vec3> for Quat {
+    type Output = Vec3;
+
+    fn mul(self, rhs: &Vec3) -> Self::Output {
+        self.rotate(rhs)
+    }
+}
+
+impl From<Quat> for Mat44 {
+    fn from(q: Quat) -> Self {
+        let (x, y, z, w) = (q.x, q.y, q.z, q.w);
+        Mat44::new([
+            [
+                1.0 - 2.0 * (y * y + z * z),
+                2.0 * (x * y - z * w),
+                2.0 * (x * z + y * w),
+                0.0,
+            ],
+            [
+                2.0 * (x * y + z * w),
+                1.0 - 2.0 * (x * x + z * z),
+                2.0 * (y * z - x * w),
+                0.0,
+            ],
+            [
+                2.0 * (x * z - y * w),
+                2.0 * (y * z + x * w),
+                1.0 - 2.0 * (x * x + y * y),
+                0.0,
+            ],
+            [0.0, 0.0, 0.0, 1.0],
+        ])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::mat44::Mat44;
+    use crate::quat::Quat;
+    use crate::vec3::Vec3;
+
+    fn assert_close(a: &Vec3, b: &Vec3) {
+        assert!((*a - *b).len() < 1e-5, "{:?} != {:?}", a, b);
+    }
+
+    #[test]
+    fn test_matches_rotat() {
+        let axis = Vec3::new(1.0, 1.0, 1.0);
+        let q = Quat::from_axis_angle(&axis, 1.1);
+        let m = Mat44::rotat(&axis, 1.1);
+        let v = Vec3::new(0.3, -2.0, 5.0);
+
+        assert_close(&q.rotate(&v), &m.transform_vector(&v));
+        assert_close(
+            &Mat44::from(q).transform_vector(&v),
+            &m.transform_vector(&v),
+        );
+    }
+
+    #[test]
+    fn test_mat44_round_trip() {
+        for q in [
+            Quat::from_axis_angle(&Vec3::new(0.0, 1.0, 0.0), 3.0),
+            Quat::from_euler(0.4, -1.2, 2.9),
+            Quat::from_axis_angle(&Vec3::new(1.0, 0.0, 0.0), -3.1),
+        ] {
+            let r = Quat::from_mat44(&Mat44::from(q));
+            assert!((r.dot(&q).abs() - 1.0).abs() < 1e-5);
+        }
+    }
+
+    #[test]
+    fn test_euler_order() {
+        let q = Quat::from_euler(
+            std::f32::consts::FRAC_PI_2,
+            std::f32::consts::FRAC_PI_2,
+            0.0,
+        );
+
+        // X first takes +y to +z, then Y takes +z to +x
+        assert_close(&(q * &Vec3::new(0.0, 1.0, 0.0)), &Vec3::new(1.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn test_look_rotation() {
+        let forward = Vec3::new(1.0, 0.0, -1.0);
+        let q = Quat::look_rotation(&forward, &Vec3::new(0.0, 1.0, 0.0));
+
+        assert_close(&q.rotate(&Vec3::new(0.0, 0.0, -1.0)), &forward.norm());
+        assert_close(
+            &q.rotate(&Vec3::new(0.0, 1.0, 0.0)),
+            &Vec3::new(0.0, 1.0, 0.0),
+        );
+    }
+
+    #[test]
+    fn test_slerp() {
+        let axis = Vec3::new(0.0, 0.0, 1.0);
+        let a = Quat::ident();
+        let b = Quat::from_axis_angle(&axis, 2.0);
+
+        let half = a.slerp(&b, 0.5);
+        let expected = Quat::from_axis_angle(&axis, 1.0);
+        assert!((half.dot(&expected) - 1.0).abs() < 1e-5);
+
+        let v = Vec3::new(1.0, 0.0, 0.0);
+        assert_close(&a.nlerp(&b, 0.5).rotate(&v), &expected.rotate(&v));
+    }
+
+    #[test]
+    fn test_compose() {
+        let qa = Quat::from_axis_angle(&Vec3::new(0.0, 1.0, 0.0), 0.5);
+        let qb = Quat::from_axis_angle(&Vec3::new(1.0, 0.0, 0.0), 0.8);
+        let v = Vec3::new(1.0, 2.0, 3.0);
+
+        assert_close(&(qa * qb).rotate(&v), &qa.rotate(&qb.rotate(&v)));
+        assert_close(&qa.conj().rotate(&qa.rotate(&v)), &v);
+    }
+}
@@ -0,0 +1,421 @@
+use crate::obj::{Face, Group, ObjModel, Vertex};
+use crate::vec3::Vec3;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+/// STL flavour written by `save`.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub enum StlFormat {
+    #[default]
+    Binary,
+    Ascii,
+}
+
+/// Why an STL file could not be loaded.
+#[derive(Debug)]
+pub enum StlError {
+    Io(io::Error),
+    /// A binary file shorter than its triangle count says.
+    UnexpectedEof,
+    /// Malformed ASCII, at a 1-based line.
+    Syntax {
+        line: usize,
+        message: &'static str,
+    },
+}
+
+impl fmt::Display for StlError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StlError::Io(err) => write!(f, "{}", err),
+            StlError::UnexpectedEof => write!(f, "unexpected end of STL data"),
+            StlError::Syntax { line, message } => write!(f, "line {}: {}", line, message),
+        }
+    }
+}
+
+impl Error for StlError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            StlError::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for StlError {
+    fn from(err: io::Error) -> Self {
+        StlError::Io(err)
+    }
+}
+
+/// Deduplicates bitwise-equal vectors, handing out indices into `values`.
+struct Welder {
+    indices: HashMap<[u32; 3], usize>,
+}
+
+impl Welder {
+    fn new() -> Self {
+        Self {
+            indices: HashMap::new(),
+        }
+    }
+
+    fn index(&mut self, values: &mut Vec<Vec3>, v: Vec3) -> usize {
+        // Adding zero folds -0.0 into 0.0
+        let key = [
+            (v.x + 0.0).to_bits(),
+            (v.y + 0.0).to_bits(),
+            (v.z + 0.0).to_bits(),
+        ];
+        *self.indices.entry(key).or_insert_with(|| {
+            values.push(v);
+            values.len() - 1
+        })
+    }
+}
+
+/// Accumulates facets into an indexed model.
+struct Builder {
+    model: ObjModel,
+    positions: Welder,
+    normals: Welder,
+}
+
+impl Builder {
+    fn new() -> Self {
+        Self {
+            model: ObjModel::new(),
+            positions: Welder::new(),
+            normals: Welder::new(),
+        }
+    }
+
+    /// Adds a facet. A zero normal, which many exporters write, is replaced
+    /// by the geometric one.
+    fn facet(&mut self, normal: Vec3, corners: [Vec3; 3]) {
+        let normal = if normal.len_sqd() > 0.0 {
+            normal
+        } else {
+            let n = (corners[1] - corners[0]).cross(&(corners[2] - corners[0]));
+            if n.len_sqd() > 0.0 {
+                n.norm()
+            } else {
+                n
+            }
+        };
+
+        let normal = self.normals.index(&mut self.model.normals, normal);
+        let vertices = corners
+            .iter()
+            .map(|&c| Vertex {
+                position: self.positions.index(&mut self.model.vertices, c),
+                tex_coord: None,
+                normal: Some(normal),
+            })
+            .collect();
+
+        self.model.faces.push(Face {
+            vertices,
+            material: None,
+            smoothing_group: None,
+        });
+    }
+}
+
+/// Loads a binary or ASCII STL file.
+pub fn load<P: AsRef<Path>>(path: P) -> Result<ObjModel, StlError> {
+    decode(&fs::read(path)?)
+}
+
+/// Decodes STL data, telling binary from ASCII by the size the binary header
+/// implies, as binary files may also start with "solid".
+pub fn decode(data: &[u8]) -> Result<ObjModel, StlError> {
+    if data.len() >= 84 {
+        let count = u32::from_le_bytes(data[80..84].try_into().unwrap()) as usize;
+        if count.checked_mul(50).and_then(|n| n.checked_add(84)) == Some(data.len()) {
+            return decode_binary(data);
+        }
+    }
+
+    // Binary data is unlikely to be all printable
+    let text = data.trim_ascii_start();
+    let printable = data
+        .iter()
+        .all(|&b| b.is_ascii_graphic() || b.is_ascii_whitespace());
+    if text.starts_with(b"solid") && printable {
+        decode_ascii(&String::from_utf8_lossy(data))
+    } else {
+        decode_binary(data)
+    }
+}
+
+fn decode_binary(data: &[u8]) -> Result<ObjModel, StlError> {
+    let header = data.get(..84).ok_or(StlError::UnexpectedEof)?;
+    let count = u32::from_le_bytes(header[80..84].try_into().unwrap()) as usize;
+    let facets = data.get(84..).unwrap_or_default();
+    if facets.len() / 50 < count {
+        return Err(StlError::UnexpectedEof);
+    }
+
+    let vec3 = |b: &[u8]| {
+        let f = |i: usize| f32::from_le_bytes(b[i..i + 4].try_into().unwrap());
+        Vec3::new(f(0), f(4), f(8))
+    };
+
+    let mut builder = Builder::new();
+    for facet in facets.chunks_exact(50).take(count) {
+        // Normal, three corners and a 16-bit attribute we ignore
+        let corners = [vec3(&facet[12..]), vec3(&facet[24..]), vec3(&facet[36..])];
+        builder.facet(vec3(facet), corners);
+    }
+    Ok(builder.model)
+}
+
+fn decode_ascii(text: &str) -> Result<ObjModel, StlError> {
+    let mut builder = Builder::new();
+    let mut normal = None;
+    let mut corners = Vec::new();
+
+    for (i, line) in text.lines().enumerate() {
+        let syntax = |message| StlError::Syntax {
+            line: i + 1,
+            message,
+        };
+        let parts: Vec<&str> = line.split_whitespace().collect();
+        let vec3 = |parts: &[&str]| -> Result<Vec3, StlError> {
+            let mut v = [0.0; 3];
+            for (value, part) in v.iter_mut().zip(parts) {
+                *value = part.parse().map_err(|_| syntax("invalid number"))?;
+            }
+            match parts.len() {
+                3 => Ok(Vec3::new(v[0], v[1], v[2])),
+                _ => Err(syntax("expected three coordinates")),
+            }
+        };
+
+        match parts.first().copied() {
+            Some("solid") => {
+                // Each solid becomes a named object
+                let faces = builder.model.faces.len();
+                builder.model.objects.push(Group {
+                    name: parts[1..].join(" "),
+                    faces: faces..faces,
+                });
+            }
+            Some("endsolid") => {
+                let faces = builder.model.faces.len();
+                if let Some(object) = builder.model.objects.last_mut() {
+                    object.faces.end = faces;
+                }
+            }
+            Some("facet") => {
+                if parts.get(1) != Some(&"normal") {
+                    return Err(syntax("expected facet normal"));
+                }
+                normal = Some(vec3(&parts[2..])?);
+                corners.clear();
+            }
+            Some("vertex") => corners.push(vec3(&parts[1..])?),
+            Some("endfacet") => {
+                let normal = normal
+                    .take()
+                    .ok_or_else(|| syntax("endfacet outside facet"))?;
+                let corners: [Vec3; 3] = corners
+                    .as_slice()
+                    .try_into()
+                    .map_err(|_| syntax("facet needs three vertices"))?;
+                builder.facet(normal, corners);
+            }
+            _ => {
+                // "outer loop", "endloop" and blank lines carry no data
+            }
+        }
+    }
+
+    let mut model = builder.model;
+    model
+        .objects
+        .retain(|o| !o.name.is_empty() && !o.faces.is_empty());
+    Ok(model)
+}
+
+/// The facet normal: the stored normal when all three corners share one, as
+/// `load` gives them, else the geometric normal. Smooth corner normals do
+/// not describe the facet.
+fn facet_normal(model: &ObjModel, face: &Face) -> Option<(Vec3, [Vec3; 3])> {
+    let (v0, v1, v2) = model.get_triangle_vertices(face)?;
+    let shared = match face.vertices[..] {
+        [a, b, c] if a.normal == b.normal && b.normal == c.normal => a.normal,
+        _ => None,
+    };
+    let normal = match shared.and_then(|n| model.normals.get(n)) {
+        Some(&n) => n,
+        None => {
+            let n = (v1 - v0).cross(&(v2 - v0));
+            if n.len_sqd() > 0.0 {
+                n.norm()
+            } else {
+                n
+            }
+        }
+    };
+    Some((normal, [v0, v1, v2]))
+}
+
+/// Writes the triangles of `model` as STL.
+pub fn save<P: AsRef<Path>>(model: &ObjModel, path: P, format: StlFormat) -> io::Result<()> {
+    write_to(model, BufWriter::new(File::create(path)?), format)
+}
+
+pub fn write_to<W: Write>(model: &ObjModel, mut w: W, format: StlFormat) -> io::Result<()> {
+    let facets: Vec<_> = model
+        .faces
+        .iter()
+        .filter_map(|face| facet_normal(model, face))
+        .collect();
+
+    match format {
+        StlFormat::Binary => {
+            let mut header = [0u8; 80];
+            let title = b"binary STL";
+            header[..title.len()].copy_from_slice(title);
+            w.write_all(&header)?;
+            w.write_all(&(facets.len() as u32).to_le_bytes())?;
+
+            for (normal, corners) in &facets {
+                for v in [normal].into_iter().chain(corners) {
+                    for c in [v.x, v.y, v.z] {
+                        w.write_all(&c.to_le_bytes())?;
+                    }
+                }
+                w.write_all(&[0, 0])?;
+            }
+        }
+        StlFormat::Ascii => {
+            writeln!(w, "solid")?;
+            for (n, corners) in &facets {
+                writeln!(w, "facet normal {} {} {}", n.x, n.y, n.z)?;
+                writeln!(w, "  outer loop")?;
+                for v in corners {
+                    writeln!(w, "    vertex {} {} {}", v.x, v.y, v.z)?;
+                }
+                writeln!(w, "  endloop")?;
+                writeln!(w, "endfacet")?;
+            }
+            writeln!(w, "endsolid")?;
+        }
+    }
+
+    w.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::obj::ObjModel;
+    use crate::stl::{self, StlError, StlFormat};
+    use crate::vec3::Vec3;
+
+    // Unit cube with outward facing, counterclockwise triangles
+    fn cube() -> ObjModel {
+        let src = "v 0 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 0\nv 0 0 1\nv 1 0 1\nv 1 1 1\nv 0 1 1\n\
+                   f 1 4 3 2\nf 5 6 7 8\nf 1 2 6 5\nf 3 4 8 7\nf 2 3 7 6\nf 1 5 8 4\n";
+        ObjModel::from_reader(src.as_bytes()).unwrap()
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let model = cube();
+        for format in [StlFormat::Binary, StlFormat::Ascii] {
+            let mut data = Vec::new();
+            stl::write_to(&model, &mut data, format).unwrap();
+            let loaded = stl::decode(&data).unwrap();
+
+            // Corners are welded back into the eight cube vertices, with one
+            // normal per side
+            assert_eq!(loaded.vertices.len(), 8, "{:?}", format);
+            assert_eq!(loaded.normals.len(), 6);
+            assert_eq!(loaded.faces.len(), 12);
+            for (a, b) in model.faces.iter().zip(&loaded.faces) {
+                assert_eq!(
+                    model.get_triangle_vertices(a),
+                    loaded.get_triangle_vertices(b)
+                );
+            }
+
+            let (n, _, _) = loaded.get_triangle_normals(&loaded.faces[0]).unwrap();
+            assert_eq!(n, Vec3::new(0.0, 0.0, -1.0));
+        }
+    }
+
+    #[test]
+    fn test_smooth_normals() {
+        // Smooth corner normals are not written as the facet normal
+        let mut model = cube();
+        model.calculate_normals();
+        for format in [StlFormat::Binary, StlFormat::Ascii] {
+            let mut data = Vec::new();
+            stl::write_to(&model, &mut data, format).unwrap();
+            let loaded = stl::decode(&data).unwrap();
+
+            assert_eq!(loaded.faces.len(), 12, "{:?}", format);
+            for face in &loaded.faces {
+                let (v0, v1, v2) = loaded.get_triangle_vertices(face).unwrap();
+                let (n, _, _) = loaded.get_triangle_normals(face).unwrap();
+                assert_eq!(n, (v1 - v0).cross(&(v2 - v0)).norm());
+            }
+        }
+    }
+
+    #[test]
+    fn test_ascii() {
+        let src = "solid part one\n\
+                   facet normal 0 0 0\n  outer loop\n\
+                   vertex 0 0 0\n  vertex 1 0 0\n  vertex 0 1 0\n\
+                   endloop\nendfacet\n\
+                   facet normal 0 0 -1\nouter loop\nvertex 0 0 0\nvertex -0 1 0\nvertex 1 0 0\n\
+                   endloop\nendfacet\nendsolid part one\n";
+        let model = stl::decode(src.as_bytes()).unwrap();
+
+        assert_eq!(model.vertices.len(), 3);
+        assert_eq!(model.objects[0].name, "part one");
+        assert_eq!(model.objects[0].faces, 0..2);
+
+        // The zero normal is recomputed from the winding
+        assert_eq!(
+            model.normals,
+            vec![Vec3::new(0.0, 0.0, 1.0), Vec3::new(0.0, 0.0, -1.0)]
+        );
+
+        let err = stl::decode(b"solid\nfacet normal 0 0 1\nvertex 0 x 0\n").unwrap_err();
+        assert!(matches!(err, StlError::Syntax { line: 3, .. }));
+    }
+
+    #[test]
+    fn test_binary_detection() {
+        // A binary file whose header starts with "solid"
+        let mut data = b"solid but binary".to_vec();
+        data.resize(80, b' ');
+        data.extend_from_slice(&1u32.to_le_bytes());
+        for c in [
+            0.0f32, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0,
+        ] {
+            data.extend_from_slice(&c.to_le_bytes());
+        }
+        data.extend_from_slice(&[0, 0]);
+
+        let model = stl::decode(&data).unwrap();
+        assert_eq!(model.faces.len(), 1);
+        assert_eq!(model.vertices[2], Vec3::new(0.0, 1.0, 0.0));
+
+        data[80] = 2;
+        assert!(matches!(
+            stl::decode(&data[..]),
+            Err(StlError::UnexpectedEof)
+        ));
+    }
+}
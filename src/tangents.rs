@@ -0,0 +1,255 @@
+//! Tangent frames for normal mapping, following the MikkTSpace algorithm
+//! that most bakers use, so baked normal maps line up.
+//!
+//! The frames are per triangle corner, and only make sense once vertices
+//! are split wherever corners disagree, so the module is crate-internal and
+//! reached through `Mesh::generate_tangents`, which does that split.
+
+use crate::vec3::Vec3;
+use crate::vec4::Vec4;
+use std::collections::HashMap;
+
+/// Per-triangle data shared by its three corners.
+struct Face {
+    /// Direction of increasing u, or `None` if the UVs or positions are
+    /// degenerate.
+    tangent: Option<Vec3>,
+    /// Whether the UVs keep the winding of the positions.
+    preserving: bool,
+}
+
+/// Angle at a corner between its edges, measured in the plane of `normal`.
+fn corner_angle(p: Vec3, prev: Vec3, next: Vec3, normal: Vec3) -> f32 {
+    let flatten = |v: Vec3| (v - normal * normal.dot(&v)).try_norm();
+    match (flatten(prev - p), flatten(next - p)) {
+        (Some(a), Some(b)) => a.dot(&b).clamp(-1.0, 1.0).acos(),
+        _ => 0.0,
+    }
+}
+
+/// Any unit vector perpendicular to `normal`.
+fn perpendicular(normal: Vec3) -> Vec3 {
+    let axis = if normal.x.abs() < 0.9 {
+        Vec3::new(1.0, 0.0, 0.0)
+    } else {
+        Vec3::new(0.0, 1.0, 0.0)
+    };
+    normal
+        .cross(&axis)
+        .try_norm()
+        .unwrap_or(Vec3::new(0.0, 0.0, 1.0))
+}
+
+fn find(parent: &mut [usize], mut i: usize) -> usize {
+    while parent[i] != i {
+        parent[i] = parent[parent[i]];
+        i = parent[i];
+    }
+    i
+}
+
+/// Tangent and bitangent sign for every corner of `triangles`, which index
+/// the per-vertex `positions`, `normals` and `tex_coords`.
+///
+/// As in MikkTSpace, vertices with identical attributes are treated as one.
+/// The triangles around a vertex fall into groups that are connected across
+/// shared edges and have the same UV orientation. Each group averages the
+/// tangents of its triangles, projected into the vertex normal's plane and
+/// weighted by the corner angle, and mirrored groups get a sign of -1. The
+/// bitangent is `sign * normal x tangent`. Triangles with degenerate UVs or
+/// positions take the frame of another group at the same vertex.
+pub(crate) fn corner_tangents(
+    positions: &[Vec3],
+    normals: &[Vec3],
+    tex_coords: &[Vec3],
+    triangles: &[[usize; 3]],
+) -> Vec<[Vec4; 3]> {
+    // Weld vertices whose attributes match bit for bit
+    let mut ids: HashMap<[u32; 8], usize> = HashMap::new();
+    let welded: Vec<usize> = (0..positions.len())
+        .map(|i| {
+            let (p, n, t) = (positions[i], normals[i], tex_coords[i]);
+            let key = [p.x, p.y, p.z, n.x, n.y, n.z, t.x, t.y].map(|f| (f + 0.0).to_bits());
+            let next = ids.len();
+            *ids.entry(key).or_insert(next)
+        })
+        .collect();
+    let triangles: Vec<[usize; 3]> = triangles.iter().map(|t| t.map(|i| welded[i])).collect();
+    let mut first = vec![0; ids.len()];
+    for (i, &w) in welded.iter().enumerate().rev() {
+        first[w] = i;
+    }
+    let position = |w: usize| positions[first[w]];
+    let normal = |w: usize| normals[first[w]];
+
+    let faces: Vec<Face> = triangles
+        .iter()
+        .map(|&[a, b, c]| {
+            let (t1, t2, t3) = (
+                tex_coords[first[a]],
+                tex_coords[first[b]],
+                tex_coords[first[c]],
+            );
+            let (s21, t21) = (t2.x - t1.x, t2.y - t1.y);
+            let (s31, t31) = (t3.x - t1.x, t3.y - t1.y);
+            let d1 = position(b) - position(a);
+            let d2 = position(c) - position(a);
+
+            let area = s21 * t31 - t21 * s31;
+            let preserving = area > 0.0;
+            let tangent = (area.abs() > f32::MIN_POSITIVE)
+                .then(|| (d1 * t31 - d2 * t21) * area.signum())
+                .and_then(|t| t.try_norm())
+                .filter(|_| d1.cross(&d2).len_sqd() > 0.0);
+            Face {
+                tangent,
+                preserving,
+            }
+        })
+        .collect();
+
+    // Corners around each welded vertex
+    let mut incident: Vec<Vec<(usize, usize)>> = vec![Vec::new(); ids.len()];
+    for (t, triangle) in triangles.iter().enumerate() {
+        for (k, &v) in triangle.iter().enumerate() {
+            incident[v].push((t, k));
+        }
+    }
+
+    let mut result = vec![[Vec4::new(0.0, 0.0, 0.0, 0.0); 3]; triangles.len()];
+    for (v, corners) in incident.iter().enumerate() {
+        let n = normal(v);
+
+        // Join corners whose triangles share an edge through this vertex
+        let mut parent: Vec<usize> = (0..corners.len()).collect();
+        for i in 0..corners.len() {
+            for j in i + 1..corners.len() {
+                let ((t, k), (u, l)) = (corners[i], corners[j]);
+                if faces[t].tangent.is_none()
+                    || faces[u].tangent.is_none()
+                    || faces[t].preserving != faces[u].preserving
+                {
+                    continue;
+                }
+                let others = [triangles[t][(k + 1) % 3], triangles[t][(k + 2) % 3]];
+                let shared = [triangles[u][(l + 1) % 3], triangles[u][(l + 2) % 3]]
+                    .iter()
+                    .any(|w| others.contains(w));
+                if shared {
+                    let (a, b) = (find(&mut parent, i), find(&mut parent, j));
+                    parent[a] = b;
+                }
+            }
+        }
+
+        // Angle-weighted sum of the projected face tangents per group
+        let mut sums = vec![Vec3::new(0.0, 0.0, 0.0); corners.len()];
+        for (i, &(t, k)) in corners.iter().enumerate() {
+            let Some(tangent) = faces[t].tangent else {
+                continue;
+            };
+            let Some(tangent) = (tangent - n * n.dot(&tangent)).try_norm() else {
+                continue;
+            };
+            let [a, b, c] = triangles[t];
+            let (prev, next) = match k {
+                0 => (c, b),
+                1 => (a, c),
+                _ => (b, a),
+            };
+            let angle = corner_angle(position(v), position(prev), position(next), n);
+            let root = find(&mut parent, i);
+            sums[root] = sums[root] + tangent * angle;
+        }
+
+        // Degenerate triangles borrow the first usable group
+        let fallback = (0..corners.len())
+            .map(|i| find(&mut parent, i))
+            .find(|&root| sums[root].try_norm().is_some());
+
+        for (i, &(t, k)) in corners.iter().enumerate() {
+            let root = if faces[t].tangent.is_some() {
+                Some(find(&mut parent, i))
+            } else {
+                fallback
+            };
+            // Without any usable triangle the frame is arbitrary
+            let frame = root.and_then(|root| {
+                let sign = if faces[corners[root].0].preserving {
+                    1.0
+                } else {
+                    -1.0
+                };
+                Some(Vec4::from_vec3(&sums[root].try_norm()?, sign))
+            });
+            result[t][k] = frame.unwrap_or_else(|| Vec4::from_vec3(&perpendicular(n), 1.0));
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tangents::corner_tangents;
+    use crate::vec3::Vec3;
+    use crate::vec4::Vec4;
+
+    // Two quads side by side in the xy plane facing +z. The right one has its
+    // texture mirrored in u around the shared edge x = 1.
+    fn mirrored() -> (Vec<Vec3>, Vec<Vec3>, Vec<[usize; 3]>) {
+        let positions = vec![
+            Vec3::new(0.0, 0.0, 0.0),
+            Vec3::new(1.0, 0.0, 0.0),
+            Vec3::new(2.0, 0.0, 0.0),
+            Vec3::new(0.0, 1.0, 0.0),
+            Vec3::new(1.0, 1.0, 0.0),
+            Vec3::new(2.0, 1.0, 0.0),
+        ];
+        let tex_coords = vec![
+            Vec3::new(0.0, 0.0, 0.0),
+            Vec3::new(1.0, 0.0, 0.0),
+            Vec3::new(0.0, 0.0, 0.0),
+            Vec3::new(0.0, 1.0, 0.0),
+            Vec3::new(1.0, 1.0, 0.0),
+            Vec3::new(0.0, 1.0, 0.0),
+        ];
+        let triangles = vec![[0, 1, 4], [0, 4, 3], [1, 2, 5], [1, 5, 4]];
+        (positions, tex_coords, triangles)
+    }
+
+    #[test]
+    fn test_mirrored_uvs() {
+        let (positions, tex_coords, triangles) = mirrored();
+        let normals = vec![Vec3::new(0.0, 0.0, 1.0); positions.len()];
+        let tangents = corner_tangents(&positions, &normals, &tex_coords, &triangles);
+
+        let left = Vec4::new(1.0, 0.0, 0.0, 1.0);
+        let right = Vec4::new(-1.0, 0.0, 0.0, -1.0);
+        assert_eq!(tangents[0], [left; 3]);
+        assert_eq!(tangents[1], [left; 3]);
+        // The seam vertices 1 and 4 get one frame per side
+        assert_eq!(tangents[2], [right; 3]);
+        assert_eq!(tangents[3], [right; 3]);
+    }
+
+    #[test]
+    fn test_projection_and_degenerate() {
+        let (positions, mut tex_coords, mut triangles) = mirrored();
+        // Tilted normals pull the tangent out of the xy plane
+        let tilt = Vec3::new(0.0, -1.0, 1.0).norm();
+        let normals = vec![tilt; positions.len()];
+        // A triangle without area on the left edge, and one with UVs
+        // squashed flat on the right
+        triangles.push([0, 3, 3]);
+        tex_coords[5] = Vec3::new(0.5, 0.0, 0.0);
+
+        let tangents = corner_tangents(&positions, &normals, &tex_coords, &triangles);
+        for corners in &tangents {
+            for t in corners {
+                assert!((t.xyz().len() - 1.0).abs() < 1e-6);
+                assert!(t.xyz().dot(&tilt).abs() < 1e-6);
+            }
+        }
+        assert_eq!(tangents[4][0], tangents[0][0]);
+    }
+}
@@ -0,0 +1,542 @@
+use crate::codec::{self, Bitmap, DecodeError};
+use crate::vec3::Vec3;
+use crate::vec4::Vec4;
+use std::f32::consts::PI;
+use std::path::Path;
+
+/// How texture coordinates outside `[0, 1]` are mapped back onto the texture.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub enum Wrap {
+    #[default]
+    Repeat,
+    ClampToEdge,
+    MirroredRepeat,
+}
+
+impl Wrap {
+    /// Maps a texel index onto `0..size`.
+    fn apply(&self, i: i32, size: usize) -> usize {
+        let size = size as i32;
+        let i = match self {
+            Wrap::Repeat => i.rem_euclid(size),
+            Wrap::ClampToEdge => i.clamp(0, size - 1),
+            Wrap::MirroredRepeat => {
+                let i = i.rem_euclid(2 * size);
+                if i < size {
+                    i
+                } else {
+                    2 * size - 1 - i
+                }
+            }
+        };
+        i as usize
+    }
+}
+
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub enum Filter {
+    #[default]
+    Nearest,
+    Bilinear,
+}
+
+/// How a texel is chosen between mip levels.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub enum MipFilter {
+    /// Always sample the base level.
+    #[default]
+    None,
+    /// Sample the closest level.
+    Nearest,
+    /// Blend the two closest levels (trilinear filtering with `Bilinear`).
+    Linear,
+}
+
+/// Downsampling kernel used to build mip chains.
+#[derive(Debug, Copy, Clone, Default, PartialEq)]
+pub enum MipGen {
+    /// Averages the texels each destination texel covers.
+    #[default]
+    Box,
+    /// Kaiser-windowed sinc, sharper than `Box` with less aliasing.
+    Kaiser { width: f32, alpha: f32 },
+}
+
+impl MipGen {
+    /// Kernel value at `t` destination texels from the center, and the
+    /// kernel's support radius.
+    fn weight(&self, t: f32) -> f32 {
+        match *self {
+            MipGen::Box => {
+                if t.abs() <= 0.5 {
+                    1.0
+                } else {
+                    0.0
+                }
+            }
+            MipGen::Kaiser { width, alpha } => {
+                if t.abs() >= width {
+                    return 0.0;
+                }
+                let sinc = if t == 0.0 {
+                    1.0
+                } else {
+                    (PI * t).sin() / (PI * t)
+                };
+                let r = t / width;
+                sinc * bessel_i0(alpha * (1.0 - r * r).sqrt()) / bessel_i0(alpha)
+            }
+        }
+    }
+
+    fn radius(&self) -> f32 {
+        match *self {
+            MipGen::Box => 0.5,
+            MipGen::Kaiser { width, .. } => width,
+        }
+    }
+}
+
+/// Zeroth-order modified Bessel function of the first kind, by power series.
+fn bessel_i0(x: f32) -> f32 {
+    let (mut sum, mut term) = (1.0, 1.0);
+    let q = x * x / 4.0;
+    for k in 1..32 {
+        term *= q / (k * k) as f32;
+        sum += term;
+        if term < sum * 1e-7 {
+            break;
+        }
+    }
+    sum
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Level {
+    width: usize,
+    height: usize,
+    texels: Vec<Vec4>,
+}
+
+impl Level {
+    fn texel(&self, x: usize, y: usize) -> Vec4 {
+        self.texels[y * self.width + x]
+    }
+
+    /// Resamples along one axis to `size` texels with `kernel`, clamping at
+    /// the edges.
+    fn downsample(&self, kernel: &MipGen, horizontal: bool, size: usize) -> Level {
+        let (src_size, width, height) = if horizontal {
+            (self.width, size, self.height)
+        } else {
+            (self.height, self.width, size)
+        };
+        let scale = src_size as f32 / size as f32;
+        let radius = kernel.radius() * scale;
+
+        let mut texels = Vec::with_capacity(width * height);
+        for y in 0..height {
+            for x in 0..width {
+                let i = if horizontal { x } else { y };
+                let center = (i as f32 + 0.5) * scale;
+                let first = (center - radius).floor() as i32;
+                let last = (center + radius).ceil() as i32;
+
+                let mut sum = Vec4::default();
+                let mut total = 0.0;
+                for j in first..=last {
+                    let w = kernel.weight((j as f32 + 0.5 - center) / scale);
+                    if w == 0.0 {
+                        continue;
+                    }
+                    let j = j.clamp(0, src_size as i32 - 1) as usize;
+                    let texel = if horizontal {
+                        self.texel(j, y)
+                    } else {
+                        self.texel(x, j)
+                    };
+                    sum = sum + texel * w;
+                    total += w;
+                }
+
+                texels.push(sum / total);
+            }
+        }
+
+        Level {
+            width,
+            height,
+            texels,
+        }
+    }
+}
+
+/// RGBA texels in `[0, 1]`, row 0 at the top, with an optional mip chain.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Texture {
+    levels: Vec<Level>,
+}
+
+impl Texture {
+    /// A texture with a single level; see `generate_mipmaps`.
+    pub fn new(width: usize, height: usize, texels: Vec<Vec4>) -> Self {
+        assert_eq!(texels.len(), width * height, "texel count mismatch");
+        Self {
+            levels: vec![Level {
+                width,
+                height,
+                texels,
+            }],
+        }
+    }
+
+    /// Decodes an image file and builds its mip chain with a box filter.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, DecodeError> {
+        Ok(Self::from(&codec::load(path)?))
+    }
+
+    /// Replaces the mip chain with successive halvings of the base level, down
+    /// to 1x1. Odd sizes round down.
+    pub fn generate_mipmaps(&mut self, kernel: MipGen) {
+        self.levels.truncate(1);
+
+        let (mut w, mut h) = (self.width(), self.height());
+        while w > 1 || h > 1 {
+            (w, h) = ((w / 2).max(1), (h / 2).max(1));
+            let prev = self.levels.last().unwrap();
+            let level = prev
+                .downsample(&kernel, true, w)
+                .downsample(&kernel, false, h);
+            self.levels.push(level);
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.levels[0].width
+    }
+
+    pub fn height(&self) -> usize {
+        self.levels[0].height
+    }
+
+    pub fn level_count(&self) -> usize {
+        self.levels.len()
+    }
+
+    /// Size of mip level `level`.
+    pub fn level_size(&self, level: usize) -> (usize, usize) {
+        (self.levels[level].width, self.levels[level].height)
+    }
+
+    pub fn texel(&self, x: usize, y: usize) -> Vec4 {
+        self.levels[0].texel(x, y)
+    }
+
+    pub fn level_texel(&self, level: usize, x: usize, y: usize) -> Vec4 {
+        self.levels[level].texel(x, y)
+    }
+}
+
+impl From<&Bitmap> for Texture {
+    fn from(bitmap: &Bitmap) -> Self {
+        let texels = bitmap
+            .pixels
+            .iter()
+            .map(|p| {
+                Vec4::new(
+                    f32::from(p[0]),
+                    f32::from(p[1]),
+                    f32::from(p[2]),
+                    f32::from(p[3]),
+                ) / f32::from(u8::MAX)
+            })
+            .collect();
+
+        let mut texture = Self::new(bitmap.width, bitmap.height, texels);
+        texture.generate_mipmaps(MipGen::Box);
+        texture
+    }
+}
+
+/// Filtering and addressing state used to look up a `Texture`.
+///
+/// `max_anisotropy` above 1 enables anisotropic filtering in `sample_grad`:
+/// up to that many probes are taken along the longer axis of the pixel
+/// footprint.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Sampler {
+    pub filter: Filter,
+    pub mip_filter: MipFilter,
+    pub wrap_u: Wrap,
+    pub wrap_v: Wrap,
+    pub max_anisotropy: u32,
+}
+
+impl Default for Sampler {
+    fn default() -> Self {
+        Self::new(Filter::default(), Wrap::default())
+    }
+}
+
+impl Sampler {
+    pub fn new(filter: Filter, wrap: Wrap) -> Self {
+        Self {
+            filter,
+            mip_filter: MipFilter::None,
+            wrap_u: wrap,
+            wrap_v: wrap,
+            max_anisotropy: 1,
+        }
+    }
+
+    /// Bilinear filtering within and linear blending between mip levels.
+    pub fn trilinear(wrap: Wrap) -> Self {
+        Self {
+            mip_filter: MipFilter::Linear,
+            ..Self::new(Filter::Bilinear, wrap)
+        }
+    }
+
+    fn fetch(&self, level: &Level, x: i32, y: i32) -> Vec4 {
+        let x = self.wrap_u.apply(x, level.width);
+        let y = self.wrap_v.apply(y, level.height);
+        level.texel(x, y)
+    }
+
+    fn sample_level(&self, level: &Level, u: f32, v: f32) -> Vec4 {
+        // Continuous texel coordinates, with texel centers at half integers
+        let x = u * level.width as f32;
+        let y = (1.0 - v) * level.height as f32;
+
+        match self.filter {
+            Filter::Nearest => self.fetch(level, x.floor() as i32, y.floor() as i32),
+            Filter::Bilinear => {
+                let (x, y) = (x - 0.5, y - 0.5);
+                let (x0, y0) = (x.floor(), y.floor());
+                let (fx, fy) = (x - x0, y - y0);
+                let (x0, y0) = (x0 as i32, y0 as i32);
+
+                let top =
+                    self.fetch(level, x0, y0) * (1.0 - fx) + self.fetch(level, x0 + 1, y0) * fx;
+                let bottom = self.fetch(level, x0, y0 + 1) * (1.0 - fx)
+                    + self.fetch(level, x0 + 1, y0 + 1) * fx;
+                top * (1.0 - fy) + bottom * fy
+            }
+        }
+    }
+
+    /// Samples at a fractional level of detail according to `mip_filter`.
+    fn sample_lod(&self, texture: &Texture, u: f32, v: f32, lod: f32) -> Vec4 {
+        let last = texture.levels.len() - 1;
+        let lod = lod.clamp(0.0, last as f32);
+
+        match self.mip_filter {
+            MipFilter::None => self.sample_level(&texture.levels[0], u, v),
+            MipFilter::Nearest => self.sample_level(&texture.levels[lod.round() as usize], u, v),
+            MipFilter::Linear => {
+                let lo = lod.floor() as usize;
+                let hi = (lo + 1).min(last);
+                let t = lod - lo as f32;
+
+                let a = self.sample_level(&texture.levels[lo], u, v);
+                if t == 0.0 {
+                    return a;
+                }
+                a * (1.0 - t) + self.sample_level(&texture.levels[hi], u, v) * t
+            }
+        }
+    }
+
+    /// Looks up the base level at `(u, v)`. As in OBJ files, `v = 0` is the
+    /// bottom edge of the image.
+    pub fn sample(&self, texture: &Texture, u: f32, v: f32) -> Vec4 {
+        self.sample_level(&texture.levels[0], u, v)
+    }
+
+    /// Looks up `(u, v)` choosing the mip level from the screen-space UV
+    /// derivatives, as found in `Fragment::ddx` and `Fragment::ddy`.
+    pub fn sample_grad(&self, texture: &Texture, u: f32, v: f32, ddx: &Vec3, ddy: &Vec3) -> Vec4 {
+        // Footprint axes in base-level texels
+        let (w, h) = (texture.width() as f32, texture.height() as f32);
+        let len_x = (ddx.x * w).hypot(ddx.y * h);
+        let len_y = (ddy.x * w).hypot(ddy.y * h);
+
+        let (axis, major, minor) = if len_x >= len_y {
+            (ddx, len_x, len_y)
+        } else {
+            (ddy, len_y, len_x)
+        };
+
+        let probes = if self.max_anisotropy > 1 && minor > 0.0 {
+            (major / minor).ceil().min(self.max_anisotropy as f32)
+        } else {
+            1.0
+        };
+
+        // With several probes along the major axis each covers less of it
+        let lod = (major / probes).max(f32::MIN_POSITIVE).log2();
+        if probes <= 1.0 {
+            return self.sample_lod(texture, u, v, lod);
+        }
+
+        let mut sum = Vec4::default();
+        for i in 0..probes as usize {
+            // Spread evenly across the footprint, centered on (u, v)
+            let t = (i as f32 + 0.5) / probes - 0.5;
+            sum = sum + self.sample_lod(texture, u + axis.x * t, v + axis.y * t, lod);
+        }
+        sum / probes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::texture::{Filter, MipFilter, MipGen, Sampler, Texture, Wrap};
+    use crate::vec3::Vec3;
+    use crate::vec4::Vec4;
+
+    // 4x4 black and white checkerboard with 1-texel squares
+    fn checker() -> Texture {
+        let texels = (0..16)
+            .map(|i| {
+                let c = ((i % 4 + i / 4) % 2) as f32;
+                Vec4::new(c, c, c, 1.0)
+            })
+            .collect();
+        Texture::new(4, 4, texels)
+    }
+
+    // 2x2: black, white / red, green
+    fn texture() -> Texture {
+        Texture::new(
+            2,
+            2,
+            vec![
+                Vec4::new(0.0, 0.0, 0.0, 1.0),
+                Vec4::new(1.0, 1.0, 1.0, 1.0),
+                Vec4::new(1.0, 0.0, 0.0, 1.0),
+                Vec4::new(0.0, 1.0, 0.0, 1.0),
+            ],
+        )
+    }
+
+    #[test]
+    fn test_nearest() {
+        let sampler = Sampler::new(Filter::Nearest, Wrap::Repeat);
+        let tex = texture();
+
+        assert_eq!(sampler.sample(&tex, 0.25, 0.75), tex.texel(0, 0));
+        assert_eq!(sampler.sample(&tex, 0.75, 0.25), tex.texel(1, 1));
+        assert_eq!(sampler.sample(&tex, 1.25, 1.75), tex.texel(0, 0));
+    }
+
+    #[test]
+    fn test_wrap_modes() {
+        let tex = texture();
+
+        let clamp = Sampler::new(Filter::Nearest, Wrap::ClampToEdge);
+        assert_eq!(clamp.sample(&tex, 5.0, 0.75), tex.texel(1, 0));
+        assert_eq!(clamp.sample(&tex, -5.0, 0.75), tex.texel(0, 0));
+
+        let mirror = Sampler::new(Filter::Nearest, Wrap::MirroredRepeat);
+        assert_eq!(mirror.sample(&tex, 1.25, 0.75), tex.texel(1, 0));
+        assert_eq!(mirror.sample(&tex, 1.75, 0.75), tex.texel(0, 0));
+        assert_eq!(mirror.sample(&tex, -0.25, 0.75), tex.texel(0, 0));
+    }
+
+    #[test]
+    fn test_bilinear() {
+        let sampler = Sampler::new(Filter::Bilinear, Wrap::ClampToEdge);
+        let tex = texture();
+
+        // Center of the texture averages all four texels
+        assert_eq!(
+            sampler.sample(&tex, 0.5, 0.5),
+            Vec4::new(0.5, 0.5, 0.25, 1.0)
+        );
+        // Texel centers are exact
+        assert_eq!(sampler.sample(&tex, 0.75, 0.75), tex.texel(1, 0));
+        // Halfway along the top row
+        assert_eq!(
+            sampler.sample(&tex, 0.5, 0.75),
+            Vec4::new(0.5, 0.5, 0.5, 1.0)
+        );
+    }
+
+    #[test]
+    fn test_generate_mipmaps() {
+        for kernel in [
+            MipGen::Box,
+            MipGen::Kaiser {
+                width: 3.0,
+                alpha: 4.0,
+            },
+        ] {
+            let mut tex = checker();
+            tex.generate_mipmaps(kernel);
+
+            assert_eq!(tex.level_count(), 3);
+            assert_eq!(tex.level_size(1), (2, 2));
+            assert_eq!(tex.level_size(2), (1, 1));
+
+            let gray = tex.level_texel(2, 0, 0);
+            assert!((gray.x - 0.5).abs() < 0.05, "{:?}", kernel);
+            assert!((gray.w - 1.0).abs() < 1e-5);
+        }
+    }
+
+    #[test]
+    fn test_mipmaps_non_square() {
+        let mut tex = Texture::new(5, 2, vec![Vec4::new(1.0, 0.5, 0.25, 1.0); 10]);
+        tex.generate_mipmaps(MipGen::Box);
+
+        assert_eq!(tex.level_count(), 3);
+        assert_eq!(tex.level_size(1), (2, 1));
+        assert_eq!(tex.level_size(2), (1, 1));
+        assert_eq!(tex.level_texel(2, 0, 0), Vec4::new(1.0, 0.5, 0.25, 1.0));
+    }
+
+    #[test]
+    fn test_trilinear_lod() {
+        let mut tex = checker();
+        tex.generate_mipmaps(MipGen::Box);
+        let sampler = Sampler::trilinear(Wrap::Repeat);
+        let (u, v) = (0.375, 0.625);
+
+        // One texel per pixel: base level
+        let d = 0.25;
+        let near =
+            sampler.sample_grad(&tex, u, v, &Vec3::new(d, 0.0, 0.0), &Vec3::new(0.0, d, 0.0));
+        assert_eq!(near, sampler.sample(&tex, u, v));
+
+        // Four texels per pixel: the 1x1 gray level
+        let d = 1.0;
+        let far = sampler.sample_grad(&tex, u, v, &Vec3::new(d, 0.0, 0.0), &Vec3::new(0.0, d, 0.0));
+        assert!((far.x - 0.5).abs() < 1e-5);
+
+        // Halfway between levels 0 and 1
+        let d = 0.25 * 2.0_f32.sqrt();
+        let mid = sampler.sample_grad(&tex, u, v, &Vec3::new(d, 0.0, 0.0), &Vec3::new(0.0, d, 0.0));
+        let base = sampler.sample(&tex, u, v).x;
+        assert!((mid.x - (base + 0.5) / 2.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_nearest_mip_and_anisotropy() {
+        let mut tex = checker();
+        tex.generate_mipmaps(MipGen::Box);
+        let ddx = Vec3::new(1.0, 0.0, 0.0);
+        let ddy = Vec3::new(0.0, 0.25, 0.0);
+
+        let mut sampler = Sampler::new(Filter::Nearest, Wrap::Repeat);
+        sampler.mip_filter = MipFilter::Nearest;
+        let iso = sampler.sample_grad(&tex, 0.125, 0.875, &ddx, &ddy);
+        assert_eq!(iso, tex.level_texel(2, 0, 0));
+
+        // Four probes along u resolve the footprint at the base level and
+        // average the checker row
+        sampler.max_anisotropy = 4;
+        let aniso = sampler.sample_grad(&tex, 0.5, 0.875, &ddx, &ddy);
+        assert!((aniso.x - 0.5).abs() < 1e-5);
+    }
+}
@@ -0,0 +1,294 @@
+use crate::vec3::Vec3;
+
+/// Splits a simple polygon, convex or not, into triangles by ear clipping.
+///
+/// The polygon is projected onto the coordinate plane its normal is most
+/// aligned with, so it only needs to be roughly planar. Triangles keep the
+/// winding of the outline and index into `points`. Degenerate triangles,
+/// such as those left by collinear vertices, are dropped, so fewer than
+/// `points.len() - 2` may be returned.
+pub fn triangulate(points: &[Vec3]) -> Vec<[usize; 3]> {
+    let n = points.len();
+    if n < 3 {
+        return Vec::new();
+    }
+
+    // Newell's method gives a normal that is robust for concave polygons
+    let mut normal = Vec3::new(0.0, 0.0, 0.0);
+    for i in 0..n {
+        let (a, b) = (points[i], points[(i + 1) % n]);
+        normal.x += (a.y - b.y) * (a.z + b.z);
+        normal.y += (a.z - b.z) * (a.x + b.x);
+        normal.z += (a.x - b.x) * (a.y + b.y);
+    }
+
+    // Areas below this are treated as zero
+    let mut extent = 0.0_f32;
+    for i in 0..n {
+        extent = extent.max((points[(i + 1) % n] - points[i]).len_sqd());
+    }
+    let epsilon = extent * 1e-6;
+    if normal.len() <= epsilon {
+        return Vec::new();
+    }
+
+    // Drop the dominant axis, keeping the projected outline counterclockwise
+    let (ax, ay, az) = (normal.x.abs(), normal.y.abs(), normal.z.abs());
+    let projected: Vec<(f32, f32)> = points
+        .iter()
+        .map(|p| {
+            if az >= ax && az >= ay {
+                (p.x, p.y * normal.z.signum())
+            } else if ay >= ax {
+                (p.z, p.x * normal.y.signum())
+            } else {
+                (p.y, p.z * normal.x.signum())
+            }
+        })
+        .collect();
+
+    let cross = |a: usize, b: usize, c: usize| {
+        let (a, b, c) = (projected[a], projected[b], projected[c]);
+        (b.0 - a.0) * (c.1 - a.1) - (b.1 - a.1) * (c.0 - a.0)
+    };
+
+    let mut remaining: Vec<usize> = (0..n).collect();
+    let mut triangles = Vec::with_capacity(n - 2);
+
+    while remaining.len() > 3 {
+        let m = remaining.len();
+        let corner = |i: usize| {
+            (
+                remaining[(i + m - 1) % m],
+                remaining[i],
+                remaining[(i + 1) % m],
+            )
+        };
+
+        // An ear is a convex corner whose triangle holds no other vertex
+        let is_ear = |i: usize| {
+            let (a, b, c) = corner(i);
+            cross(a, b, c) > epsilon
+                && remaining.iter().all(|&p| {
+                    let q = projected[p];
+                    // Vertices shared by the outline at the same spot don't count
+                    [a, b, c].iter().any(|&v| projected[v] == q)
+                        || cross(a, b, p) < 0.0
+                        || cross(b, c, p) < 0.0
+                        || cross(c, a, p) < 0.0
+                })
+        };
+
+        let ear = (0..m).find(|&i| is_ear(i)).or_else(|| {
+            // Nothing qualifies on a self-intersecting or noisy outline: clip
+            // a flat corner, which only drops a degenerate triangle, or else
+            // the most convex one
+            (0..m)
+                .find(|&i| {
+                    let (a, b, c) = corner(i);
+                    cross(a, b, c).abs() <= epsilon
+                })
+                .or_else(|| {
+                    (0..m).max_by(|&i, &j| {
+                        let (a, b, c) = corner(i);
+                        let (d, e, f) = corner(j);
+                        cross(a, b, c).total_cmp(&cross(d, e, f))
+                    })
+                })
+        });
+
+        let i = ear.unwrap();
+        let (a, b, c) = corner(i);
+        triangles.push([a, b, c]);
+        remaining.remove(i);
+    }
+    triangles.push([remaining[0], remaining[1], remaining[2]]);
+
+    // Drop slivers using the real, unprojected area
+    triangles.retain(|&[a, b, c]| {
+        let area = (points[b] - points[a]).cross(&(points[c] - points[a]));
+        area.len() > epsilon
+    });
+    triangles
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::quat::Quat;
+    use crate::triangulate::triangulate;
+    use crate::vec3::Vec3;
+
+    fn polygon(coords: &[(f32, f32)]) -> Vec<Vec3> {
+        coords.iter().map(|&(x, y)| Vec3::new(x, y, 0.0)).collect()
+    }
+
+    // Twice the signed area of an outline in the xy plane
+    fn shoelace(points: &[Vec3]) -> f32 {
+        (0..points.len())
+            .map(|i| {
+                let (a, b) = (points[i], points[(i + 1) % points.len()]);
+                a.x * b.y - b.x * a.y
+            })
+            .sum()
+    }
+
+    // Checks that the triangles tile the polygon: all wound like the
+    // outline, with areas summing to its area
+    fn check_tiling(points: &[Vec3], expected: usize) {
+        let triangles = triangulate(points);
+        assert_eq!(triangles.len(), expected, "{:?}", triangles);
+
+        let mut total = 0.0;
+        for &[a, b, c] in &triangles {
+            let area = shoelace(&[points[a], points[b], points[c]]);
+            assert!(area > 0.0, "{:?} is flipped or flat", [a, b, c]);
+            total += area;
+        }
+        assert!(
+            (total - shoelace(points)).abs() < 1e-4,
+            "{} vs {}",
+            total,
+            shoelace(points)
+        );
+    }
+
+    #[test]
+    fn test_convex() {
+        check_tiling(
+            &polygon(&[(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0)]),
+            2,
+        );
+        let hexagon: Vec<_> = (0..6)
+            .map(|i| {
+                let a = i as f32 * std::f32::consts::PI / 3.0;
+                (a.cos(), a.sin())
+            })
+            .collect();
+        check_tiling(&polygon(&hexagon), 4);
+    }
+
+    #[test]
+    fn test_concave_corpus() {
+        let corpus: &[&[(f32, f32)]] = &[
+            // L shape
+            &[
+                (0.0, 0.0),
+                (2.0, 0.0),
+                (2.0, 1.0),
+                (1.0, 1.0),
+                (1.0, 2.0),
+                (0.0, 2.0),
+            ],
+            // Chevron, whose reflex vertex defeats a fan from vertex 0
+            &[(0.0, 0.0), (2.0, 1.0), (4.0, 0.0), (2.0, 3.0)],
+            &[(2.0, 1.0), (4.0, 0.0), (2.0, 3.0), (0.0, 0.0)],
+            // U shape
+            &[
+                (0.0, 0.0),
+                (3.0, 0.0),
+                (3.0, 3.0),
+                (2.0, 3.0),
+                (2.0, 1.0),
+                (1.0, 1.0),
+                (1.0, 3.0),
+                (0.0, 3.0),
+            ],
+            // Comb with three teeth
+            &[
+                (0.0, 0.0),
+                (5.0, 0.0),
+                (5.0, 3.0),
+                (4.0, 3.0),
+                (4.0, 1.0),
+                (3.0, 1.0),
+                (3.0, 3.0),
+                (2.0, 3.0),
+                (2.0, 1.0),
+                (1.0, 1.0),
+                (1.0, 3.0),
+                (0.0, 3.0),
+            ],
+        ];
+        for outline in corpus {
+            check_tiling(&polygon(outline), outline.len() - 2);
+        }
+
+        // Five-pointed star
+        let star: Vec<_> = (0..10)
+            .map(|i| {
+                let r = if i % 2 == 0 { 1.0 } else { 0.4 };
+                let a = i as f32 * std::f32::consts::PI / 5.0;
+                (r * a.cos(), r * a.sin())
+            })
+            .collect();
+        check_tiling(&polygon(&star), 8);
+
+        // Spiral-like hook
+        let hook = [
+            (0.0, 0.0),
+            (4.0, 0.0),
+            (4.0, 4.0),
+            (1.0, 4.0),
+            (1.0, 2.0),
+            (3.0, 2.0),
+            (3.0, 3.0),
+            (2.0, 3.0),
+            (2.0, 2.5),
+            (2.5, 2.5),
+            (2.5, 2.2),
+            (1.5, 2.2),
+            (1.5, 3.5),
+            (3.5, 3.5),
+            (3.5, 1.0),
+            (0.0, 1.0),
+        ];
+        check_tiling(&polygon(&hook), hook.len() - 2);
+    }
+
+    #[test]
+    fn test_degenerate() {
+        // Collinear points along the bottom edge add no triangles
+        let points = polygon(&[(0.0, 0.0), (1.0, 0.0), (2.0, 0.0), (2.0, 1.0), (0.0, 1.0)]);
+        check_tiling(&points, 3);
+
+        let points = polygon(&[(0.0, 0.0), (1.0, 0.0), (2.0, 0.0), (3.0, 0.0)]);
+        assert!(triangulate(&points).is_empty());
+        assert!(triangulate(&points[..2]).is_empty());
+
+        // Repeated vertex
+        let points = polygon(&[(0.0, 0.0), (1.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0)]);
+        check_tiling(&points, 2);
+    }
+
+    #[test]
+    fn test_orientation_and_plane() {
+        // Clockwise outline: triangles stay clockwise
+        let mut points = polygon(&[
+            (0.0, 0.0),
+            (2.0, 0.0),
+            (2.0, 1.0),
+            (1.0, 1.0),
+            (1.0, 2.0),
+            (0.0, 2.0),
+        ]);
+        points.reverse();
+        for [a, b, c] in triangulate(&points) {
+            assert!(shoelace(&[points[a], points[b], points[c]]) < 0.0);
+        }
+
+        // The same L shape tilted out of the xy plane keeps its winding
+        // relative to the rotated normal. Rounding may let an ear through
+        // that leaves a flat triangle behind, so only the area is checked.
+        points.reverse();
+        let q = Quat::from_axis_angle(&Vec3::new(1.0, 0.3, 0.2), 1.1);
+        let tilted: Vec<_> = points.iter().map(|p| q.rotate(p)).collect();
+        let normal = q.rotate(&Vec3::new(0.0, 0.0, 1.0));
+        let mut area = 0.0;
+        for [a, b, c] in triangulate(&tilted) {
+            let n = (tilted[b] - tilted[a]).cross(&(tilted[c] - tilted[a]));
+            assert!(n.dot(&normal) > 0.0);
+            area += n.len();
+        }
+        assert!((area - 6.0).abs() < 1e-4);
+    }
+}
@@ -0,0 +1,107 @@
+use crate::vec3::Vec3;
+use crate::vec4::Vec4;
+
+/// Per-vertex data that the rasterizer can interpolate across a triangle.
+///
+/// Anything that forms a vector space works: colors, normals, UVs, world
+/// positions, or a tuple of those.
+pub trait Varying: Copy {
+    fn scaled(&self, k: f32) -> Self;
+    fn added(&self, other: &Self) -> Self;
+
+    fn lerp(&self, other: &Self, t: f32) -> Self {
+        self.scaled(1.0 - t).added(&other.scaled(t))
+    }
+
+    /// Combines three values with the barycentric weights stored in `w`.
+    fn weighted(a: &Self, b: &Self, c: &Self, w: &Vec3) -> Self {
+        a.scaled(w.x).added(&b.scaled(w.y)).added(&c.scaled(w.z))
+    }
+}
+
+impl Varying for () {
+    fn scaled(&self, _: f32) -> Self {}
+
+    fn added(&self, _: &Self) -> Self {}
+}
+
+impl Varying for f32 {
+    fn scaled(&self, k: f32) -> Self {
+        self * k
+    }
+
+    fn added(&self, other: &Self) -> Self {
+        self + other
+    }
+}
+
+impl Varying for Vec3 {
+    fn scaled(&self, k: f32) -> Self {
+        *self * k
+    }
+
+    fn added(&self, other: &Self) -> Self {
+        *self + *other
+    }
+}
+
+impl Varying for Vec4 {
+    fn scaled(&self, k: f32) -> Self {
+        *self * k
+    }
+
+    fn added(&self, other: &Self) -> Self {
+        *self + *other
+    }
+}
+
+impl<A: Varying, B: Varying> Varying for (A, B) {
+    fn scaled(&self, k: f32) -> Self {
+        (self.0.scaled(k), self.1.scaled(k))
+    }
+
+    fn added(&self, other: &Self) -> Self {
+        (self.0.added(&other.0), self.1.added(&other.1))
+    }
+}
+
+impl<A: Varying, B: Varying, C: Varying> Varying for (A, B, C) {
+    fn scaled(&self, k: f32) -> Self {
+        (self.0.scaled(k), self.1.scaled(k), self.2.scaled(k))
+    }
+
+    fn added(&self, other: &Self) -> Self {
+        (
+            self.0.added(&other.0),
+            self.1.added(&other.1),
+            self.2.added(&other.2),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::varying::Varying;
+    use crate::vec3::Vec3;
+
+    #[test]
+    fn test_lerp() {
+        let a = Vec3::new(0.0, 2.0, 4.0);
+        let b = Vec3::new(2.0, 4.0, 8.0);
+
+        assert_eq!(a.lerp(&b, 0.5), Vec3::new(1.0, 3.0, 6.0));
+        assert_eq!(1.0_f32.lerp(&3.0, 0.25), 1.5);
+    }
+
+    #[test]
+    fn test_weighted_tuple() {
+        let a = (1.0_f32, Vec3::new(1.0, 0.0, 0.0));
+        let b = (2.0_f32, Vec3::new(0.0, 1.0, 0.0));
+        let c = (3.0_f32, Vec3::new(0.0, 0.0, 1.0));
+        let w = Vec3::new(0.5, 0.25, 0.25);
+
+        let (s, v) = Varying::weighted(&a, &b, &c, &w);
+        assert_eq!(s, 1.75);
+        assert_eq!(v, Vec3::new(0.5, 0.25, 0.25));
+    }
+}
@@ -44,6 +44,17 @@ impl Vec3 {
     pub fn norm(&self) -> Self {
         *self / self.len()
     }
+
+    /// Unit vector in the same direction, or `None` for a zero or
+    /// non-finite vector that `norm` would turn into NaN.
+    pub fn try_norm(&self) -> Option<Self> {
+        let len = self.len();
+        if len > 0.0 && len.is_finite() {
+            Some(*self / len)
+        } else {
+            None
+        }
+    }
 }
 
 impl Add for Vec3 {
@@ -186,4 +197,11 @@ mod tests {
         let v1 = Vec3::new(4.0, 8.0, 1.0);
         assert_eq!(v1.len(), 9.0);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_try_norm() {
+        assert_eq!(Vec3::new(0.0, 3.0, 4.0).try_norm(), Some(Vec3::new(0.0, 0.6, 0.8)));
+        assert_eq!(Vec3::new(0.0, 0.0, 0.0).try_norm(), None);
+        assert_eq!(Vec3::new(f32::INFINITY, 0.0, 0.0).try_norm(), None);
+    }
+}
@@ -0,0 +1,181 @@
+use crate::vec3::Vec3;
+use std::fmt;
+use std::ops::*;
+
+/// Homogeneous coordinates, as produced by `Mat44` before the perspective
+/// divide.
+#[derive(Debug, Copy, Clone, Default, PartialEq)]
+pub struct Vec4 {
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+    pub w: f32,
+}
+
+impl Vec4 {
+    pub fn new(x: f32, y: f32, z: f32, w: f32) -> Self {
+        Self { x, y, z, w }
+    }
+
+    pub fn from_vec3(v: &Vec3, w: f32) -> Self {
+        Self::new(v.x, v.y, v.z, w)
+    }
+
+    pub fn xyz(&self) -> Vec3 {
+        Vec3::new(self.x, self.y, self.z)
+    }
+
+    pub fn dot(&self, other: &Self) -> f32 {
+        self.x * other.x + self.y * other.y + self.z * other.z + self.w * other.w
+    }
+
+    pub fn neg(&self) -> Self {
+        Self {
+            x: -self.x,
+            y: -self.y,
+            z: -self.z,
+            w: -self.w,
+        }
+    }
+
+    pub fn len_sqd(&self) -> f32 {
+        self.dot(self)
+    }
+
+    pub fn len(&self) -> f32 {
+        self.len_sqd().sqrt()
+    }
+
+    pub fn norm(&self) -> Self {
+        *self / self.len()
+    }
+
+    /// Perspective divide.
+    pub fn to_ndc(&self) -> Vec3 {
+        self.xyz() / self.w
+    }
+}
+
+impl Add for Vec4 {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Self {
+            x: self.x + other.x,
+            y: self.y + other.y,
+            z: self.z + other.z,
+            w: self.w + other.w,
+        }
+    }
+}
+
+impl Sub for &Vec4 {
+    type Output = Vec4;
+
+    fn sub(self, other: Self) -> Vec4 {
+        Vec4 {
+            x: self.x - other.x,
+            y: self.y - other.y,
+            z: self.z - other.z,
+            w: self.w - other.w,
+        }
+    }
+}
+
+impl Sub for Vec4 {
+    type Output = Self;
+
+    fn sub(self, other: Self) -> Self {
+        Self {
+            x: self.x - other.x,
+            y: self.y - other.y,
+            z: self.z - other.z,
+            w: self.w - other.w,
+        }
+    }
+}
+
+impl Mul<f32> for Vec4 {
+    type Output = Self;
+
+    fn mul(self, other: f32) -> Self {
+        Self {
+            x: self.x * other,
+            y: self.y * other,
+            z: self.z * other,
+            w: self.w * other,
+        }
+    }
+}
+
+impl Mul<Vec4> for f32 {
+    type Output = Vec4;
+
+    fn mul(self, other: Vec4) -> Vec4 {
+        Vec4 {
+            x: self * other.x,
+            y: self * other.y,
+            z: self * other.z,
+            w: self * other.w,
+        }
+    }
+}
+
+impl Div<f32> for Vec4 {
+    type Output = Self;
+
+    fn div(self, other: f32) -> Self {
+        Self {
+            x: self.x / other,
+            y: self.y / other,
+            z: self.z / other,
+            w: self.w / other,
+        }
+    }
+}
+
+impl fmt::Display for Vec4 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {} {} {}", self.x, self.y, self.z, self.w)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::vec3::Vec3;
+    use crate::vec4::Vec4;
+
+    #[test]
+    fn test_to_ndc() {
+        let v = Vec4::new(2.0, 4.0, 6.0, 2.0);
+        assert_eq!(v.to_ndc(), Vec3::new(1.0, 2.0, 3.0));
+    }
+
+    #[test]
+    fn test_add_sub() {
+        let v1 = Vec4::new(2.0, 3.0, 4.0, 5.0);
+        let v2 = Vec4::new(1.0, 2.0, 3.0, 4.0);
+
+        assert_eq!(v1 + v2, Vec4::new(3.0, 5.0, 7.0, 9.0));
+        assert_eq!(v1 - v2, Vec4::new(1.0, 1.0, 1.0, 1.0));
+    }
+
+    #[test]
+    fn test_mul_div() {
+        let v = Vec4::new(2.0, 3.0, 4.0, 5.0);
+
+        assert_eq!(v * 2.0, Vec4::new(4.0, 6.0, 8.0, 10.0));
+        assert_eq!(2.0 * v, Vec4::new(4.0, 6.0, 8.0, 10.0));
+        assert_eq!(v / 2.0, Vec4::new(1.0, 1.5, 2.0, 2.5));
+    }
+
+    #[test]
+    fn test_dot_len() {
+        let v1 = Vec4::new(1.0, 2.0, 2.0, 4.0);
+        let v2 = Vec4::new(2.0, 1.0, 0.0, 1.0);
+
+        assert_eq!(v1.dot(&v2), 8.0);
+        assert_eq!(v1.len(), 5.0);
+        assert_eq!(v1.neg(), Vec4::new(-1.0, -2.0, -2.0, -4.0));
+    }
+}